@@ -4,6 +4,7 @@ use crate::infer::types::Property;
 pub(crate) use syntax::ast::{AssignOperator, Literal, Op, UnaryOp};
 use util::pos::Spanned;
 use util::symbol::Symbol;
+pub(crate) use util::symbol::SELF;
 
 #[derive(Debug)]
 pub struct Program {
@@ -11,14 +12,14 @@ pub struct Program {
     pub classes: Vec<Class>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Function {
     pub name: Symbol,
     pub params: Vec<FunctionParam>,
     pub body: Box<Spanned<TypedStatement>>,
     pub returns: Type,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Class {
     pub name: Symbol,
     pub superclass: Option<Spanned<Symbol>>,
@@ -26,43 +27,61 @@ pub struct Class {
     pub methods: Vec<Function>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Method {
     pub name: Symbol,
     pub params: Vec<Type>,
     pub returns: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct TypedExpression {
     pub expr: Box<Spanned<Expression>>,
     pub ty: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct TypedStatement {
     pub statement: Box<Spanned<Statement>>,
     pub ty: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct FunctionParam {
     pub name: Symbol,
     pub ty: Type,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
+pub enum Pattern {
+    Expr(Spanned<TypedExpression>),
+    Range(Spanned<TypedExpression>, Spanned<TypedExpression>),
+    Or(Vec<Spanned<Pattern>>),
+    Wildcard,
+    Bind(Symbol),
+    Variant {
+        tag: u32,
+        inner: Option<Box<Spanned<Pattern>>>,
+    },
+}
+
+#[derive(Debug, Clone, Hash)]
 pub struct MatchArm {
-    pub pattern: Option<Spanned<TypedExpression>>,
+    pub pattern: Spanned<Pattern>,
+    pub guard: Option<Spanned<TypedExpression>>,
     pub body: Spanned<TypedStatement>,
     pub is_all: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum Statement {
     Block(Vec<Spanned<TypedStatement>>),
-    Break,
-    Continue,
+    /// The label of the loop to leave if it isn't the innermost one and the value given to a
+    /// `loop`
+    Break(Option<Symbol>, Option<Spanned<TypedExpression>>),
+    Continue(Option<Symbol>),
+    /// Runs when the enclosing block exits
+    Defer(Spanned<TypedStatement>),
     Expr(Spanned<TypedExpression>),
 
     If {
@@ -73,29 +92,50 @@ pub enum Statement {
 
     Print(Spanned<TypedExpression>),
 
-    While(Spanned<TypedExpression>, Spanned<TypedStatement>),
+    EPrint(Spanned<TypedExpression>),
+
+    /// Cond, body and the loop's label
+    While(Spanned<TypedExpression>, Spanned<TypedStatement>, Option<Symbol>),
 
     Let {
         ident: Symbol,
         ty: Type,
         expr: Option<Spanned<TypedExpression>>,
+        constant: bool,
+    },
+
+    /// Binds each item of the tuple to a new variable
+    LetTuple {
+        idents: Vec<Symbol>,
+        expr: Spanned<TypedExpression>,
+    },
+
+    /// Binds each item of the array to a new variable after checking its length
+    LetArray {
+        idents: Vec<Symbol>,
+        expr: Spanned<TypedExpression>,
     },
 
     Return(Spanned<TypedExpression>),
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct ClassLiteralProperty {
     pub name: Symbol,
     pub expr: Spanned<TypedExpression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum Expression {
     // The different type of expressions availabe
     Array(Vec<Spanned<TypedExpression>>),
     Assign(Symbol, AssignOperator, Spanned<TypedExpression>),
     Binary(Spanned<TypedExpression>, Op, Spanned<TypedExpression>),
+    /// The statements of the block and the value it evaluates to
+    Block(Vec<Spanned<TypedStatement>>, Spanned<TypedExpression>),
     Call(Symbol, Vec<Spanned<TypedExpression>>),
+    /// Call a function value i.e a closure stored in a variable
+    /// Callee, Args
+    CallValue(Spanned<TypedExpression>, Vec<Spanned<TypedExpression>>),
 
     Cast(Spanned<TypedExpression>, Type),
 
@@ -123,13 +163,28 @@ pub enum Expression {
     },
     Literal(Literal),
 
+    /// The body and the loop's label
+    Loop(Spanned<TypedStatement>, Option<Symbol>),
+
     Match {
         cond: Spanned<TypedExpression>,
         arms: Spanned<Vec<Spanned<MatchArm>>>,
     },
 
-    /// Name, Object, Value
-    Set(Symbol, Spanned<TypedExpression>, Spanned<TypedExpression>),
+    /// Name, Operator, Object, Value
+    Set(
+        Symbol,
+        AssignOperator,
+        Spanned<TypedExpression>,
+        Spanned<TypedExpression>,
+    ),
+    /// Target, Index, Operator, Value
+    SetIndex(
+        Spanned<TypedExpression>,
+        Spanned<TypedExpression>,
+        AssignOperator,
+        Spanned<TypedExpression>,
+    ),
     StaticMethodCall {
         class_name: Symbol,
         method_name: Symbol,
@@ -140,6 +195,14 @@ pub enum Expression {
         Spanned<TypedExpression>,
         Spanned<TypedExpression>,
     ),
+    Tuple(Vec<Spanned<TypedExpression>>),
+    /// The tuple and the index of the item
+    TupleIndex(Spanned<TypedExpression>, u8),
+    /// `value?` where tag is the tag of the variant that is returned early
+    Try {
+        value: Spanned<TypedExpression>,
+        tag: u32,
+    },
     Unary(UnaryOp, Spanned<TypedExpression>),
     Var(Symbol, Type),
 
@@ -1,20 +1,37 @@
 use super::infer::types::{Type, TypeCon};
 use crate::ast;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 use opcode;
-use std::hash::Hash;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::rc::Rc;
 use util::emmiter::Reporter;
-use util::pos::{Span, Spanned};
+use util::pos::{Span, Spanned, EMPTYSPAN};
 use util::symbol::{Symbol, Symbols};
 use vm::{Chunk, Class, Function, FunctionObject, Program, RawObject, StringObject, Value};
 type ParseResult<T> = Result<T, ()>;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct LoopDescription {
+    /// The label used by `break` and `continue` to refer to the loop
+    label: Option<Symbol>,
     /// The index of the start label
     start: usize,
-    /// The index of the end label
-    end: usize,
+    /// The jumps emitted by each `break` which are patched to the end of the loop
+    breaks: Vec<usize>,
+    /// The number of blocks with deferred statements outside of the loop
+    defers: usize,
+    /// If each `break` leaves a value on the stack i.e the loop is a `loop` expression
+    value: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    /// The postion of the local on the local stack
+    slot: usize,
+    /// False if the local was declared with `const`
+    mutable: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -35,11 +52,16 @@ impl<K: Hash + Eq + Copy, V: Clone> StackedMap<K, V> {
         self.scopes.push(None);
     }
 
-    pub fn end_scope(&mut self) {
+    /// Ends the scope returning the values that were entered in it
+    pub fn end_scope(&mut self) -> Vec<V> {
+        let mut values = Vec::new();
+
         while let Some(Some(value)) = self.scopes.pop() {
             let mapping = self.table.get_mut(&value).expect("Symbol not in Symbols");
-            mapping.pop();
+            values.extend(mapping.pop());
         }
+
+        values
     }
 
     /// Enters a peice of data into the current scope
@@ -59,27 +81,50 @@ pub struct Builder<'a> {
     chunk: Chunk,
     /// A count of all local vars
     /// The number is the postion of the local on the local stack
-    locals: StackedMap<Symbol, usize>,
+    locals: StackedMap<Symbol, Local>,
 
     params: FnvHashMap<Symbol, usize>,
-    current_loop: Option<LoopDescription>,
+    /// The loops enclosing the current statement, innermost last
+    loops: Vec<LoopDescription>,
+    /// The statements deferred in each enclosing block, innermost last
+    deferred: Vec<Vec<Spanned<ast::TypedStatement>>>,
     ///  A linked list of all the objects allocated. This
     /// is passed to the vm so runtime collection can be done
     pub objects: RawObject,
 
     symbols: &'a Symbols<()>,
+    /// The methods each class can be called with including inherited ones
+    class_methods: &'a ClassMethods,
+    /// The functions defined by the program. Calls to anything else are calls to natives
+    functions: &'a FnvHashSet<Symbol>,
+    /// The constants of the whole program
+    constants: &'a mut Vec<Value>,
     /// The reporter used to reporter any errors
     reporter: &'a mut Reporter,
     /// The slot of the variable
     slots: u32,
+    /// The name and span of the `let` that declared each slot
+    declared: FnvHashMap<usize, (Symbol, Span)>,
+    /// The slots that have been read
+    read: FnvHashSet<usize>,
+    /// The span of the last store to each slot that hasn't been read since.
+    /// Cleared at every jump as the analysis only looks at straight line code
+    stores: FnvHashMap<usize, Span>,
+    /// The slots declared without a value that might not have been assigned to yet
+    uninit: FnvHashSet<usize>,
     ///
     line: u32,
+    /// The span of the statement or expression being compiled
+    span: Span,
 }
 
 impl<'a> Builder<'a> {
     pub fn new(
         reporter: &'a mut Reporter,
         symbols: &'a Symbols<()>,
+        class_methods: &'a ClassMethods,
+        functions: &'a FnvHashSet<Symbol>,
+        constants: &'a mut Vec<Value>,
         objects: RawObject,
         params: FnvHashMap<Symbol, usize>,
     ) -> Self {
@@ -87,9 +132,18 @@ impl<'a> Builder<'a> {
             chunk: Chunk::new(),
             locals: StackedMap::new(),
             line: 0,
+            span: EMPTYSPAN,
             slots: 0,
-            current_loop: None,
+            declared: FnvHashMap::default(),
+            read: FnvHashSet::default(),
+            stores: FnvHashMap::default(),
+            uninit: FnvHashSet::default(),
+            loops: Vec::new(),
+            deferred: Vec::new(),
             symbols,
+            class_methods,
+            functions,
+            constants,
             params,
             objects,
             reporter,
@@ -106,26 +160,154 @@ impl<'a> Builder<'a> {
         slot
     }
 
-    pub fn patch_jump(&mut self, offset: usize) {
+    /// Records a store to `slot`, warning if the previous store to it was never read
+    fn store(&mut self, slot: usize, span: Span) {
+        if let Some(previous) = self.stores.insert(slot, span) {
+            self.warn_dead_store(slot, previous);
+        }
+    }
+
+    fn warn_dead_store(&mut self, slot: usize, span: Span) {
+        if let Some(&(name, _)) = self.declared.get(&slot) {
+            let name = self.symbols.name(name);
+
+            if !name.starts_with('_') {
+                let msg = format!("value assigned to `{}` is never read", name);
+                self.reporter.warn(msg, span);
+            }
+        }
+    }
+
+    /// Finds the loop that a `break` or `continue` refers to, the innermost one if there is no
+    /// label. The parser checks that labels are declared
+    fn find_loop(&self, label: &Option<Symbol>) -> usize {
+        match *label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|l| l.label == Some(label))
+                .expect("Undefined label"),
+            None => self
+                .loops
+                .len()
+                .checked_sub(1)
+                .expect("Using break or continue outside a loop"),
+        }
+    }
+
+    /// Compiles the deferred statements of every block nested deeper than `depth`, innermost
+    /// first and in the reverse order they were deferred
+    fn compile_deferred(&mut self, depth: usize) -> ParseResult<()> {
+        // Taken out while compiling so a `return` in a deferred statement doesn't run itself
+        let deferred = self.deferred.split_off(depth);
+
+        for statement in deferred.iter().rev().flat_map(|block| block.iter().rev()) {
+            self.compile_statement(statement)?;
+        }
+
+        self.deferred.extend(deferred);
+
+        Ok(())
+    }
+
+    /// Returns the value on top of the stack after running every deferred statement
+    fn emit_return(&mut self) -> ParseResult<()> {
+        self.compile_deferred_keeping_value(0)?;
+        self.emit_byte(opcode::RETURN);
+
+        Ok(())
+    }
+
+    /// Compiles the deferred statements like `compile_deferred` leaving the value on top of the
+    /// stack on top once they have run
+    fn compile_deferred_keeping_value(&mut self, depth: usize) -> ParseResult<()> {
+        if self.deferred[depth..].iter().all(Vec::is_empty) {
+            return Ok(());
+        }
+
+        // The deferred statements can leave values on the stack so the value is kept in a
+        // local until they have run
+        let slot = self.new_slot();
+
+        self.emit_bytes(opcode::SETLOCAL, slot as u8);
+        self.compile_deferred(depth)?;
+        self.emit_bytes(opcode::GETLOCAL, slot as u8);
+
+        Ok(())
+    }
+
+    /// Reports an error if the slot might be read before it has been assigned to
+    fn check_initialized(&mut self, slot: usize, span: Span) -> ParseResult<()> {
+        if self.uninit.contains(&slot) {
+            self.reporter.error("use of possibly-uninitialized variable", span);
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    /// Ends the current scope warning about any variables declared in it that were never read
+    /// and about stores to them that are never read
+    pub fn end_scope(&mut self) {
+        for Local { slot, .. } in self.locals.end_scope() {
+            let store = self.stores.remove(&slot);
+
+            if self.read.contains(&slot) {
+                if let Some(span) = store {
+                    self.warn_dead_store(slot, span);
+                }
+
+                continue;
+            }
+
+            if let Some(&(name, span)) = self.declared.get(&slot) {
+                let name = self.symbols.name(name);
+
+                if !name.starts_with('_') {
+                    self.reporter.warn(format!("unused variable `{}`", name), span);
+                }
+            }
+        }
+    }
+
+    pub fn patch_jump(&mut self, offset: usize) -> ParseResult<()> {
+        self.stores.clear();
+
         // -2 to adjust for the bytecode for the jump offset itself.
-        let jump = self.chunk.code.len() - offset - 2;
+        let jump = self.check_jump(self.chunk.code.len() - offset - 2)?;
 
         self.chunk.code[offset] = ((jump >> 8) & 0xff) as u8;
         self.chunk.code[offset + 1] = (jump & 0xff) as u8;
+
+        Ok(())
     }
 
     pub fn emit_jump(&mut self, byte: u8) -> usize {
+        self.stores.clear();
         self.emit_byte(byte);
         self.emit_bytes(0xff, 0xff);
         self.chunk.code.len() - 2
     }
 
-    pub fn emit_loop(&mut self, loop_start: usize) {
+    pub fn emit_loop(&mut self, loop_start: usize) -> ParseResult<()> {
+        self.stores.clear();
         self.emit_byte(opcode::LOOP);
 
-        let offset = self.chunk.code.len() - loop_start + 2;
+        let offset = self.check_jump(self.chunk.code.len() - loop_start + 2)?;
+
+        self.emit_bytes(((offset >> 8) & 0xff) as u8, (offset & 0xff) as u8);
+
+        Ok(())
+    }
 
-        self.emit_bytes(((offset >> 8) & 0xff) as u8, (offset & 0xff) as u8)
+    /// Jump offsets are stored in 16 bits so a jump can't cross more than 65535 bytes
+    fn check_jump(&mut self, offset: usize) -> ParseResult<usize> {
+        if offset > usize::from(u16::max_value()) {
+            self.reporter.error("jump too large; function body exceeds 64KB", self.span);
+            Err(())
+        } else {
+            Ok(offset)
+        }
     }
 
     pub fn emit_bytes(&mut self, byte1: u8, byte2: u8) {
@@ -133,17 +315,35 @@ impl<'a> Builder<'a> {
         self.emit_byte(byte2);
     }
 
+    /// Emits the operation of a compound assignment on a value of type `ty`.
+    /// Sized ints are truncated to their size afterwards
+    pub fn emit_compound(&mut self, op: &ast::AssignOperator, ty: &Type) {
+        self.emit_byte(compound_opcode(op, ty));
+
+        if let Type::App(TypeCon::SizedInt(kind), _) = *ty {
+            self.emit_bytes(opcode::WRAPINT, kind as u8);
+        }
+    }
+
     pub fn emit_constant(&mut self, constant: Value, span: Span) -> ParseResult<()> {
         let value = self.make_constant(constant, span)?;
         self.emit_bytes(opcode::CONSTANT, value);
         Ok(())
     }
 
+    /// Adds `value` to the program's constant pool returning its index.
+    /// Equal constants share an index even if they are used in different functions
     pub fn make_constant(&mut self, value: Value, span: Span) -> ParseResult<u8> {
-        let index = self.chunk.add_constant(value);
+        let index = match self.constants.iter().position(|constant| *constant == value) {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        };
 
-        if index > 256 {
-            self.reporter.error("too many constants in one chunk", span);
+        if index > 255 {
+            self.reporter.error("too many constants in one program", span);
             Err(())
         } else {
             Ok(index as u8)
@@ -151,6 +351,8 @@ impl<'a> Builder<'a> {
     }
 
     pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+
         if span.start.line > self.line {
             self.line = span.start.line
         }
@@ -165,27 +367,61 @@ impl<'a> Builder<'a> {
         match statement.value.statement.value {
             Statement::Block(ref statements) => {
                 self.locals.begin_scope();
+                self.deferred.push(Vec::new());
 
                 for statement in statements {
                     self.compile_statement(statement)?;
                 }
-                self.locals.end_scope();
+
+                let deferred = self.deferred.pop().unwrap();
+
+                for statement in deferred.iter().rev() {
+                    self.compile_statement(statement)?;
+                }
+
+                self.end_scope();
+
+                Ok(())
+            }
+
+            Statement::Break(ref label, ref value) => {
+                let index = self.find_loop(label);
+                let defers = self.loops[index].defers;
+
+                if self.loops[index].value {
+                    match *value {
+                        Some(ref value) => self.compile_expression(value)?,
+                        None => self.emit_constant(Value::nil(), statement.span)?,
+                    }
+
+                    self.compile_deferred_keeping_value(defers)?;
+                } else {
+                    self.compile_deferred(defers)?;
+                }
+
+                let label = self.emit_jump(opcode::JUMP);
+
+                self.loops[index].breaks.push(label);
 
                 Ok(())
             }
 
-            Statement::Break => {
-                let description = self.current_loop.expect("Using break outside a loop");
+            Statement::Continue(ref label) => {
+                let index = self.find_loop(label);
+                let (start, defers) = (self.loops[index].start, self.loops[index].defers);
 
-                self.emit_bytes(opcode::JUMP, description.end as u8);
+                self.compile_deferred(defers)?;
 
+                self.emit_loop(start)?;
                 Ok(())
             }
 
-            Statement::Continue => {
-                let description = self.current_loop.expect("Using break outside a loop");
+            Statement::Defer(ref statement) => {
+                self.deferred
+                    .last_mut()
+                    .expect("Using defer outside a block")
+                    .push(statement.clone());
 
-                self.emit_bytes(opcode::JUMP, description.start as u8);
                 Ok(())
             }
 
@@ -202,14 +438,19 @@ impl<'a> Builder<'a> {
                 Ok(())
             }
 
-            Statement::Return(ref expr) => {
+            Statement::EPrint(ref expr) => {
                 self.compile_expression(expr)?;
 
-                self.emit_byte(opcode::RETURN);
-
+                self.emit_byte(opcode::EPRINT);
                 Ok(())
             }
 
+            Statement::Return(ref expr) => {
+                self.compile_expression(expr)?;
+
+                self.emit_return()
+            }
+
             Statement::If {
                 ref cond,
                 ref then,
@@ -221,9 +462,13 @@ impl<'a> Builder<'a> {
 
                 self.emit_byte(opcode::POP);
 
+                let uninit = self.uninit.clone(); // `then` might not run
+
                 self.compile_statement(then)?;
 
-                self.patch_jump(false_label);
+                self.uninit = uninit;
+
+                self.patch_jump(false_label)?;
 
                 self.emit_byte(opcode::POP);
 
@@ -241,17 +486,24 @@ impl<'a> Builder<'a> {
 
                 self.emit_byte(opcode::POP);
 
+                let uninit = self.uninit.clone();
+
                 self.compile_statement(then)?;
 
                 let end_label = self.emit_jump(opcode::JUMP);
 
-                self.patch_jump(false_label);
+                self.patch_jump(false_label)?;
 
                 self.emit_byte(opcode::POP);
 
+                // Only slots assigned to on both branches are initialized afterwards
+                let then_uninit = mem::replace(&mut self.uninit, uninit);
+
                 self.compile_statement(otherwise)?;
 
-                self.patch_jump(end_label);
+                self.uninit.extend(then_uninit);
+
+                self.patch_jump(end_label)?;
 
                 Ok(())
             }
@@ -259,6 +511,7 @@ impl<'a> Builder<'a> {
             Statement::Let {
                 ref ident,
                 ref expr,
+                constant,
                 ..
             } => {
                 //
@@ -270,35 +523,98 @@ impl<'a> Builder<'a> {
 
                 let slot = self.new_slot();
 
-                self.locals.insert(*ident, slot as usize);
+                self.locals.insert(
+                    *ident,
+                    Local {
+                        slot: slot as usize,
+                        mutable: !constant,
+                    },
+                );
+                self.declared.insert(slot as usize, (*ident, statement.span));
+
+                if expr.is_some() {
+                    self.store(slot as usize, statement.span);
+                } else {
+                    self.uninit.insert(slot as usize);
+                }
 
                 self.emit_bytes(opcode::SETLOCAL, slot as u8); // Write the symbol id
 
                 Ok(())
             }
 
-            Statement::While(ref cond, ref body) => {
+            Statement::LetTuple {
+                ref idents,
+                ref expr,
+            } => {
+                self.compile_expression(expr)?;
+
+                for (index, ident) in idents.iter().enumerate() {
+                    self.emit_byte(opcode::DUP);
+                    self.emit_bytes(opcode::INDEXTUPLE, index as u8);
+
+                    self.bind_item(*ident, statement.span);
+                }
+
+                self.emit_byte(opcode::POP); // removes the tuple
+
+                Ok(())
+            }
+
+            Statement::LetArray {
+                ref idents,
+                ref expr,
+            } => {
+                self.compile_expression(expr)?;
+
+                self.emit_bytes(opcode::CHECKLEN, idents.len() as u8);
+
+                for (index, ident) in idents.iter().enumerate() {
+                    self.emit_byte(opcode::DUP);
+                    self.emit_constant(Value::int(index as i64), statement.span)?;
+                    self.emit_byte(opcode::INDEXARRAY);
+
+                    self.bind_item(*ident, statement.span);
+                }
+
+                self.emit_byte(opcode::POP); // removes the array
+
+                Ok(())
+            }
+
+            Statement::While(ref cond, ref body, label) => {
                 let start_label = self.chunk.code.len();
 
                 self.compile_expression(cond)?;
 
                 let out = self.emit_jump(opcode::JUMPNOT);
 
-                self.current_loop = Some(LoopDescription {
+                self.loops.push(LoopDescription {
+                    label,
                     start: start_label,
-                    end: out,
+                    breaks: Vec::new(),
+                    defers: self.deferred.len(),
+                    value: false,
                 });
 
                 self.emit_byte(opcode::POP);
 
+                let uninit = self.uninit.clone(); // the body might not run
+
                 self.compile_statement(body)?;
 
-                self.emit_loop(start_label); // Jumps back to the start
+                self.uninit = uninit;
 
-                self.patch_jump(out); // the outer label
+                self.emit_loop(start_label)?; // Jumps back to the start
+
+                self.patch_jump(out)?; // the outer label
 
                 self.emit_byte(opcode::POP); //removes cond from stack
 
+                for label in self.loops.pop().unwrap().breaks {
+                    self.patch_jump(label)?; // a break skips the cond as it was already popped
+                }
+
                 Ok(())
             }
         }
@@ -309,83 +625,54 @@ impl<'a> Builder<'a> {
         self.set_span(expr.span);
 
         match expr.value.expr.value {
-            Expression::Assign(ref ident, ref op, ref expr) => {
-                let pos = if let Some(pos) = self.locals.get(ident) {
-                    *pos
+            Expression::Assign(ref ident, ref op, ref value) => {
+                let (pos, is_local) = if let Some(local) = self.locals.get(ident).cloned() {
+                    if !local.mutable {
+                        let msg = format!(
+                            "Cannot assign to `{}` as it is a const",
+                            self.symbols.name(*ident)
+                        );
+                        self.reporter.error(msg, expr.span);
+                        return Err(());
+                    }
+
+                    (local.slot, true)
                 } else if let Some(pos) = self.params.get(ident) {
-                    *pos
+                    (*pos, false)
                 } else {
                     unreachable!(); // Params are treated as locals so it should be present
                 };
 
                 match *op {
                     AssignOperator::Equal => {
-                        self.compile_expression(expr)?;
+                        self.compile_expression(value)?;
                         self.emit_bytes(opcode::SETLOCAL, pos as u8);
-                    }
-                    AssignOperator::MinusEqual => {
-                        self.emit_bytes(opcode::GETLOCAL, pos as u8); // get the var
-
-                        let opcode = match expr.value.ty {
-                            Type::App(TypeCon::Int, _) => opcode::SUB,
-                            Type::App(TypeCon::Float, _) => opcode::SUBF,
-                            _ => unreachable!(), // type checker should prevent this
-                        };
-
-                        self.compile_expression(expr)?; // get the expr
-
-                        self.emit_byte(opcode);
-
-                        self.emit_bytes(opcode::SETLOCAL, pos as u8); // store it in x
-                    }
-
-                    AssignOperator::PlusEqual => {
-                        self.emit_bytes(opcode::GETLOCAL, pos as u8); // get the var
-
-                        let opcode = match expr.value.ty {
-                            Type::App(TypeCon::Int, _) => opcode::ADD,
-                            Type::App(TypeCon::Float, _) => opcode::ADDF,
-                            _ => unreachable!(), // type checker should prevent this
-                        };
-
-                        self.compile_expression(expr)?; // get the expr
-
-                        self.emit_byte(opcode);
 
-                        self.emit_bytes(opcode::SETLOCAL, pos as u8); // store it in x
+                        if is_local {
+                            self.uninit.remove(&pos);
+                        }
                     }
+                    ref op => {
+                        if is_local {
+                            self.check_initialized(pos, expr.span)?;
+                        }
 
-                    AssignOperator::SlashEqual => {
                         self.emit_bytes(opcode::GETLOCAL, pos as u8); // get the var
 
-                        let opcode = match expr.value.ty {
-                            Type::App(TypeCon::Int, _) => opcode::DIV,
-                            Type::App(TypeCon::Float, _) => opcode::DIVF,
-                            _ => unreachable!(), // type checker should prevent this
-                        };
+                        if is_local {
+                            self.stores.remove(&pos); // the old value is used
+                        }
 
-                        self.compile_expression(expr)?; // get the expr
+                        self.compile_expression(value)?; // get the expr
 
-                        self.emit_byte(opcode);
+                        self.emit_compound(op, &value.value.ty);
 
                         self.emit_bytes(opcode::SETLOCAL, pos as u8); // store it in x
                     }
+                }
 
-                    AssignOperator::StarEqual => {
-                        self.emit_bytes(opcode::GETLOCAL, pos as u8); // get the var
-
-                        let opcode = match expr.value.ty {
-                            Type::App(TypeCon::Int, _) => opcode::MUL,
-                            Type::App(TypeCon::Float, _) => opcode::MULF,
-                            _ => unreachable!(), // type checker should prevent this
-                        };
-
-                        self.compile_expression(expr)?; // get the expr
-
-                        self.emit_byte(opcode);
-
-                        self.emit_bytes(opcode::SETLOCAL, pos as u8); // store it in x
-                    }
+                if is_local {
+                    self.store(pos, expr.span);
                 }
             }
 
@@ -398,8 +685,22 @@ impl<'a> Builder<'a> {
                 self.emit_bytes(opcode::ARRAY, exprs.len() as u8);
             }
 
+            Expression::Tuple(ref exprs) => {
+                for expr in exprs.iter().rev() {
+                    self.compile_expression(expr)?;
+                }
+
+                self.emit_bytes(opcode::TUPLE, exprs.len() as u8);
+            }
+
+            Expression::TupleIndex(ref tuple, index) => {
+                self.compile_expression(tuple)?;
+
+                self.emit_bytes(opcode::INDEXTUPLE, index);
+            }
+
             Expression::Index(ref target, ref index) => {
-                match expr.value.ty {
+                match target.value.ty {
                     Type::App(TypeCon::Str, _) => {
                         self.compile_expression(target)?;
                         self.compile_expression(index)?;
@@ -434,8 +735,13 @@ impl<'a> Builder<'a> {
                 Literal::Float(ref f) => {
                     self.emit_constant(Value::float(*f), expr.value.expr.span)?;
                 }
+                Literal::Char(ref ch) => {
+                    self.emit_constant(Value::char(*ch), expr.value.expr.span)?;
+                }
                 Literal::Str(ref string) => {
-                    let object = StringObject::new(string, self.objects);
+                    // The constant owns a copy of the string as the ast it was parsed into is
+                    // dropped long before the program stops running
+                    let object = StringObject::from_owned(string.clone(), self.objects);
 
                     self.emit_constant(Value::object(object), expr.value.expr.span)?;
                 }
@@ -466,39 +772,59 @@ impl<'a> Builder<'a> {
                         // For comparisson the lhs and the rhs should be the same so only
                         // check the type of the lhs
                         (Type::App(TypeCon::Bool, _), Op::LessThan) => match lhs.value.ty {
-                            Type::App(TypeCon::Int, _) => self.emit_byte(opcode::LESS),
+                            ref ty if ty.is_integer() => self.emit_byte(opcode::LESS),
                             Type::App(TypeCon::Float, _) => self.emit_byte(opcode::LESSF),
+                            Type::App(TypeCon::Str, _) => self.emit_byte(opcode::LESSSTR),
                             _ => unreachable!(),
                         },
 
+                        // `a <= b` is `!(a > b)` for ints but floats need their own opcode
+                        // because every comparison with NaN is false
                         (Type::App(TypeCon::Bool, _), Op::LessThanEqual) => match lhs.value.ty {
-                            Type::App(TypeCon::Int, _) => {
-                                self.emit_bytes(opcode::LESS, opcode::NOT)
+                            ref ty if ty.is_integer() => {
+                                self.emit_bytes(opcode::GREATER, opcode::NOT)
                             }
-                            Type::App(TypeCon::Float, _) => {
-                                self.emit_bytes(opcode::LESSF, opcode::NOT)
+                            Type::App(TypeCon::Float, _) => self.emit_byte(opcode::LESSEQUALF),
+                            Type::App(TypeCon::Str, _) => {
+                                self.emit_bytes(opcode::GREATERSTR, opcode::NOT)
                             }
                             _ => unreachable!(),
                         },
 
                         (Type::App(TypeCon::Bool, _), Op::GreaterThan) => match lhs.value.ty {
-                            Type::App(TypeCon::Int, _) => self.emit_byte(opcode::GREATER),
+                            ref ty if ty.is_integer() => self.emit_byte(opcode::GREATER),
                             Type::App(TypeCon::Float, _) => self.emit_byte(opcode::GREATERF),
+                            Type::App(TypeCon::Str, _) => self.emit_byte(opcode::GREATERSTR),
                             _ => unreachable!(),
                         },
 
                         (Type::App(TypeCon::Bool, _), Op::GreaterThanEqual) => match lhs.value.ty {
-                            Type::App(TypeCon::Int, _) => {
-                                self.emit_bytes(opcode::GREATER, opcode::NOT)
+                            ref ty if ty.is_integer() => {
+                                self.emit_bytes(opcode::LESS, opcode::NOT)
                             }
-                            Type::App(TypeCon::Float, _) => {
-                                self.emit_bytes(opcode::GREATERF, opcode::NOT)
+                            Type::App(TypeCon::Float, _) => self.emit_byte(opcode::GREATEREQUALF),
+                            Type::App(TypeCon::Str, _) => {
+                                self.emit_bytes(opcode::LESSSTR, opcode::NOT)
                             }
                             _ => unreachable!(),
                         },
 
                         (Type::App(TypeCon::Str, _), Op::Plus) => self.emit_byte(opcode::CONCAT),
 
+                        // sized ints use the int opcodes and are then truncated to their size
+                        (Type::App(TypeCon::SizedInt(kind), _), op) => {
+                            let instruction = match op {
+                                Op::Plus => opcode::ADD,
+                                Op::Minus => opcode::SUB,
+                                Op::Star => opcode::MUL,
+                                Op::Slash => opcode::DIV,
+                                _ => unreachable!("`{:?}` is rejected by the type checker", op),
+                            };
+
+                            self.emit_byte(instruction);
+                            self.emit_bytes(opcode::WRAPINT, *kind as u8);
+                        }
+
                         (_, Op::EqualEqual) => self.emit_byte(opcode::EQUAL),
                         (_, Op::BangEqual) => self.emit_bytes(opcode::EQUAL, opcode::NOT),
 
@@ -537,7 +863,34 @@ impl<'a> Builder<'a> {
                         self.emit_byte(opcode::FLOAT2STR)
                     }
 
-                    _ => unreachable!(), // cast only allows int -> float, float -> int, bool -> int
+                    (Type::App(TypeCon::Char, _), Type::App(TypeCon::Int, _)) => {
+                        self.emit_byte(opcode::CHAR2INT)
+                    }
+
+                    (Type::App(TypeCon::Int, _), Type::App(TypeCon::Char, _)) => {
+                        self.emit_byte(opcode::INT2CHAR)
+                    }
+
+                    (Type::App(TypeCon::Char, _), Type::App(TypeCon::Str, _)) => {
+                        self.emit_byte(opcode::CHAR2STR)
+                    }
+
+                    (_, Type::App(TypeCon::SizedInt(kind), _)) => {
+                        self.emit_bytes(opcode::CHECKINT, *kind as u8)
+                    }
+
+                    // a sized int is already stored as an int
+                    (Type::App(TypeCon::SizedInt(_), _), Type::App(TypeCon::Int, _)) => (),
+
+                    (Type::App(TypeCon::SizedInt(_), _), Type::App(TypeCon::Float, _)) => {
+                        self.emit_byte(opcode::INT2FLOAT)
+                    }
+
+                    (Type::App(TypeCon::SizedInt(_), _), Type::App(TypeCon::Str, _)) => {
+                        self.emit_byte(opcode::INT2STR)
+                    }
+
+                    _ => unreachable!(), // the type checker only allows the casts above
                 }
             }
 
@@ -546,25 +899,32 @@ impl<'a> Builder<'a> {
                     self.compile_expression(arg)?;
                 }
 
-                let name = self.symbols.name(*callee);
+                // The type checker only allows calls to the program's functions, the builtins and
+                // the natives registered by the host
+                if self.functions.contains(callee) {
+                    self.emit_bytes(opcode::CALL, callee.0 as u8);
+                    self.emit_byte(args.len() as u8)
+                } else {
+                    self.emit_bytes(opcode::CALLNATIVE, callee.0 as u8)
+                }
+            }
 
-                match name.as_str() {
-                    "clock" | "random" | "read" | "fopen" => {
-                        self.emit_bytes(opcode::CALLNATIVE, callee.0 as u8)
-                    }
-                    _ => {
-                        self.emit_bytes(opcode::CALL, callee.0 as u8);
-                        self.emit_byte(args.len() as u8)
-                    }
+            Expression::CallValue(ref callee, ref args) => {
+                for arg in args {
+                    self.compile_expression(arg)?;
                 }
+
+                self.compile_expression(callee)?;
+
+                self.emit_bytes(opcode::CALLVALUE, args.len() as u8);
             }
 
             Expression::ClassLiteral {
                 ref symbol,
                 ref properties,
             } => {
-                for property in properties.iter().rev() {
-                    //rev because poped of stack
+                // properties are evaluated left to right
+                for property in properties.iter() {
                     self.compile_expression(&property.value.expr)?;
                 }
 
@@ -572,7 +932,7 @@ impl<'a> Builder<'a> {
                 self.emit_byte(properties.len() as u8);
 
                 for property in properties.iter().rev() {
-                    //rev because poped of stack
+                    //rev because the last property is on the top of the stack
                     self.emit_byte(property.value.name.0 as u8);
                 }
             }
@@ -597,6 +957,31 @@ impl<'a> Builder<'a> {
                 ref method_name,
                 ref params,
             } => {
+                let receiver = self
+                    .class_methods
+                    .get(class_name)
+                    .and_then(|methods| methods.get(method_name));
+
+                let msg = match receiver {
+                    Some(Receiver::Static) => None,
+                    // a method that uses `self` has no receiver when called on the class
+                    Some(Receiver::Instance) => Some(format!(
+                        "method {} uses self so it can't be called on class {}",
+                        self.symbols.name(*method_name),
+                        self.symbols.name(*class_name)
+                    )),
+                    None => Some(format!(
+                        "class {} has no static method {}",
+                        self.symbols.name(*class_name),
+                        self.symbols.name(*method_name)
+                    )),
+                };
+
+                if let Some(msg) = msg {
+                    self.reporter.error(msg, expr.span);
+                    return Err(());
+                }
+
                 for param in params {
                     self.compile_expression(param)?;
                 }
@@ -624,36 +1009,103 @@ impl<'a> Builder<'a> {
                 self.emit_bytes(opcode::GETMETHOD, method_name.0 as u8)
             }
 
+            Expression::Loop(ref body, label) => {
+                let start_label = self.chunk.code.len();
+
+                self.loops.push(LoopDescription {
+                    label,
+                    start: start_label,
+                    breaks: Vec::new(),
+                    defers: self.deferred.len(),
+                    value: true,
+                });
+
+                let uninit = self.uninit.clone(); // a `break` might come before any assignment
+
+                self.compile_statement(body)?;
+
+                self.uninit = uninit;
+
+                self.emit_loop(start_label)?;
+
+                for label in self.loops.pop().unwrap().breaks {
+                    self.patch_jump(label)?; // the value of the `break` is left on the stack
+                }
+            }
+
+            Expression::Block(ref statements, ref value) => {
+                self.locals.begin_scope();
+
+                for statement in statements {
+                    self.compile_statement(statement)?;
+                }
+
+                self.compile_expression(value)?; // left on the stack as the value of the block
+
+                self.end_scope();
+            }
+
             Expression::Grouping(ref expr) => {
                 self.compile_expression(expr)?;
             }
 
             Expression::Match { ref cond, ref arms } => {
-                self.compile_expression(cond)?;
+                self.compile_expression(cond)?; // evaluated once and kept on the stack
 
                 let mut jumps = Vec::new();
 
+                // Any arm might be the one that runs
+                let uninit = self.uninit.clone();
+                let mut arms_uninit = FnvHashSet::default();
+
                 for arm in arms.value.iter() {
-                    println!("{}", arm.value.is_all);
-                    if arm.value.is_all {
-                        self.compile_statement(&arm.value.body)?;
-                        jumps.push(self.emit_jump(opcode::JUMP));
-                    } else {
-                        self.compile_expression(arm.value.pattern.as_ref().unwrap())?;
-                        self.compile_expression(cond)?; //TODO: check if legal i.e if a+1
-                        self.emit_byte(opcode::EQUAL);
+                    let mut failed = Vec::new(); // each leaves a false bool above the cond
+
+                    if !arm.value.is_all {
+                        self.compile_pattern(&arm.value.pattern)?;
+
+                        failed.push(self.emit_jump(opcode::JUMPNOT));
 
-                        let offset = self.emit_jump(opcode::JUMPNOT);
+                        self.emit_byte(opcode::POP); // removes the result of the pattern
+                    }
+
+                    self.locals.begin_scope();
+
+                    // the cond stays on the stack for the next arm if the guard fails
+                    self.bind_pattern(&arm.value.pattern);
+
+                    if let Some(ref guard) = arm.value.guard {
+                        self.compile_expression(guard)?;
+
+                        failed.push(self.emit_jump(opcode::JUMPNOT));
+
+                        self.emit_byte(opcode::POP); // removes the result of the guard
+                    }
+
+                    self.emit_byte(opcode::POP); // the cond is no longer needed
 
-                        self.compile_statement(&arm.value.body)?;
-                        jumps.push(self.emit_jump(opcode::JUMP));
+                    self.compile_statement(&arm.value.body)?;
+                    jumps.push(self.emit_jump(opcode::JUMP));
 
-                        self.patch_jump(offset);
+                    arms_uninit.extend(mem::replace(&mut self.uninit, uninit.clone()));
+
+                    self.end_scope();
+
+                    if !failed.is_empty() {
+                        for offset in failed {
+                            self.patch_jump(offset)?;
+                        }
+
+                        self.emit_byte(opcode::POP); // removes the result of the pattern or guard
                     }
                 }
 
+                self.emit_byte(opcode::POP); // no arm matched so remove the cond
+
+                self.uninit.extend(arms_uninit);
+
                 for label in jumps {
-                    self.patch_jump(label);
+                    self.patch_jump(label)?;
                 }
             }
 
@@ -662,15 +1114,44 @@ impl<'a> Builder<'a> {
 
                 let false_label = self.emit_jump(opcode::JUMPNOT);
 
+                let uninit = self.uninit.clone();
+
+                self.emit_byte(opcode::POP); // pop off the condition
+
                 self.compile_expression(if_true)?;
 
                 let end_label = self.emit_jump(opcode::JUMP);
 
-                self.patch_jump(false_label);
+                self.patch_jump(false_label)?;
+
+                self.emit_byte(opcode::POP); // pop off the condition
+
+                let true_uninit = mem::replace(&mut self.uninit, uninit);
 
                 self.compile_expression(if_false)?;
 
-                self.patch_jump(end_label);
+                self.uninit.extend(true_uninit);
+
+                self.patch_jump(end_label)?;
+            }
+
+            Expression::Try { ref value, ref tag } => {
+                self.compile_expression(value)?; // evaluated once and kept on the stack
+
+                self.emit_byte(opcode::DUP);
+                self.emit_byte(opcode::ENUMTAG);
+                self.emit_constant(Value::int(i64::from(*tag)), expr.span)?;
+                self.emit_byte(opcode::EQUAL);
+
+                let offset = self.emit_jump(opcode::JUMPNOT);
+
+                self.emit_byte(opcode::POP); // removes the result of EQUAL
+                self.emit_return()?; // the `None` or `Err` is returned as is
+
+                self.patch_jump(offset)?;
+
+                self.emit_byte(opcode::POP); // removes the result of EQUAL
+                self.emit_byte(opcode::ENUMINNER);
             }
 
             Expression::Unary(ref op, ref expr) => {
@@ -686,16 +1167,26 @@ impl<'a> Builder<'a> {
                     UnaryOp::Minus => match &expr.value.ty {
                         Type::App(TypeCon::Int, _) => self.emit_byte(opcode::NEGATE),
                         Type::App(TypeCon::Float, _) => self.emit_byte(opcode::NEGATEF),
+                        Type::App(TypeCon::SizedInt(kind), _) => {
+                            self.emit_byte(opcode::NEGATE);
+                            self.emit_bytes(opcode::WRAPINT, *kind as u8);
+                        }
                         _ => unreachable!(),
                     },
                 }
             }
 
-            Expression::Var(ref ident, _) => {
-                if let Some(pos) = self.locals.get(ident).cloned() {
+            Expression::Var(ref ident, ref ty) => {
+                if let Some(Local { slot: pos, .. }) = self.locals.get(ident).cloned() {
+                    self.check_initialized(pos, expr.span)?;
+                    self.read.insert(pos);
+                    self.stores.remove(&pos);
                     self.emit_bytes(opcode::GETLOCAL, pos as u8);
                 } else if let Some(offset) = self.params.get(ident).cloned() {
                     self.emit_bytes(opcode::GETPARAM, offset as u8);
+                } else if is_function(ty) {
+                    // a top level function used as a value
+                    self.emit_bytes(opcode::GETFUNCTION, ident.0 as u8);
                 } else {
                     self.reporter.error("Undefined variable", expr.span);
                     return Err(()); // Params are treated as locals so it should be present
@@ -715,28 +1206,69 @@ impl<'a> Builder<'a> {
                 ref tag,
                 ref inner,
             } => {
-                self.emit_byte(opcode::ENUM);
-                self.emit_bytes(enum_name.value.0 as u8, *tag as u8);
                 self.compile_expression(inner)?;
+                self.emit_byte(opcode::ENUMDATA);
+                self.emit_bytes(enum_name.value.0 as u8, *tag as u8);
             }
 
             Expression::Closure(ref func) => {
-                let closure = compile_function(func, self.symbols, self.reporter, self.objects)?;
-
-                let func = FunctionObject::new(closure.params.len(), closure, self.objects);
+                let closure = compile_function(
+                    func,
+                    self.symbols,
+                    self.class_methods,
+                    self.functions,
+                    self.constants,
+                    self.reporter,
+                    self.objects,
+                    false,
+                )?;
+
+                let func =
+                    FunctionObject::new(closure.params.len(), Rc::new(closure), self.objects);
 
                 self.emit_constant(Value::object(func), expr.span)?;
             }
 
-            Expression::Set(ref property, ref instance, ref value) => {
+            Expression::Set(ref property, AssignOperator::Equal, ref instance, ref value) => {
                 self.compile_expression(value)?;
                 self.compile_expression(instance)?;
                 self.emit_bytes(opcode::SETPROPERTY, property.0 as u8);
             }
-        }
 
-        Ok(())
-    }
+            Expression::Set(ref property, ref op, ref instance, ref value) => {
+                self.compile_expression(instance)?; // only evaluated once
+                self.emit_byte(opcode::DUP);
+                self.emit_bytes(opcode::GETPROPERTY, property.0 as u8);
+
+                self.compile_expression(value)?;
+                self.emit_compound(op, &expr.value.ty);
+
+                self.emit_byte(opcode::SWAP); // SETPROPERTY expects the instance on top
+                self.emit_bytes(opcode::SETPROPERTY, property.0 as u8);
+            }
+
+            Expression::SetIndex(ref target, ref index, AssignOperator::Equal, ref value) => {
+                self.compile_expression(target)?;
+                self.compile_expression(index)?;
+                self.compile_expression(value)?;
+                self.emit_byte(opcode::SETINDEXARRAY);
+            }
+
+            Expression::SetIndex(ref target, ref index, ref op, ref value) => {
+                self.compile_expression(target)?; // only evaluated once
+                self.compile_expression(index)?; // only evaluated once
+                self.emit_byte(opcode::DUP2);
+                self.emit_byte(opcode::INDEXARRAY);
+
+                self.compile_expression(value)?;
+                self.emit_compound(op, &expr.value.ty);
+
+                self.emit_byte(opcode::SETINDEXARRAY);
+            }
+        }
+
+        Ok(())
+    }
 
     fn compile_and(
         &mut self,
@@ -747,9 +1279,13 @@ impl<'a> Builder<'a> {
 
         let false_label = self.emit_jump(opcode::JUMPNOT);
 
+        let uninit = self.uninit.clone(); // the rhs might not run
+
         self.compile_expression(rhs)?;
 
-        self.patch_jump(false_label);
+        self.uninit = uninit;
+
+        self.patch_jump(false_label)?;
 
         Ok(())
     }
@@ -763,42 +1299,585 @@ impl<'a> Builder<'a> {
 
         let else_label = self.emit_jump(opcode::JUMPIF);
 
+        let uninit = self.uninit.clone(); // the rhs might not run
+
         self.compile_expression(rhs)?;
 
-        self.patch_jump(else_label);
+        self.uninit = uninit;
+
+        self.patch_jump(else_label)?;
 
         self.emit_byte(opcode::POP);
 
         Ok(())
     }
+
+    /// Checks the cond on top of the stack against the pattern.
+    /// The cond is left on the stack with the result of the check above it
+    fn compile_pattern(&mut self, pattern: &Spanned<ast::Pattern>) -> ParseResult<()> {
+        match pattern.value {
+            ast::Pattern::Expr(ref expr) => {
+                self.emit_byte(opcode::DUP);
+                self.compile_expression(expr)?;
+                self.emit_byte(opcode::EQUAL);
+            }
+
+            ast::Pattern::Range(ref start, ref end) => {
+                self.emit_byte(opcode::DUP);
+                self.compile_expression(start)?;
+                self.emit_byte(opcode::LESS);
+                self.emit_byte(opcode::NOT); // cond >= start
+
+                let below_start = self.emit_jump(opcode::JUMPNOT);
+
+                self.emit_byte(opcode::POP);
+                self.emit_byte(opcode::DUP);
+                self.compile_expression(end)?;
+                self.emit_byte(opcode::LESS); // cond < end
+
+                self.patch_jump(below_start)?;
+            }
+
+            ast::Pattern::Or(ref patterns) => {
+                let mut matched = Vec::with_capacity(patterns.len());
+
+                for (i, pattern) in patterns.iter().enumerate() {
+                    self.compile_pattern(pattern)?;
+
+                    if i + 1 != patterns.len() {
+                        matched.push(self.emit_jump(opcode::JUMPIF));
+
+                        self.emit_byte(opcode::POP); // this alternative didn't match
+                    }
+                }
+
+                for offset in matched {
+                    self.patch_jump(offset)?;
+                }
+            }
+
+            ast::Pattern::Wildcard | ast::Pattern::Bind(_) => {
+                self.emit_byte(opcode::TRUE);
+            }
+
+            ast::Pattern::Variant { ref tag, ref inner } => {
+                self.emit_byte(opcode::DUP);
+                self.emit_byte(opcode::ENUMTAG);
+                self.emit_constant(Value::int(i64::from(*tag)), pattern.span)?;
+                self.emit_byte(opcode::EQUAL);
+
+                if let Some(ref inner) = *inner {
+                    let other_variant = self.emit_jump(opcode::JUMPNOT);
+
+                    self.emit_byte(opcode::POP); // removes the result of EQUAL
+                    self.emit_byte(opcode::DUP);
+                    self.emit_byte(opcode::ENUMINNER);
+
+                    self.compile_pattern(inner)?;
+
+                    self.emit_byte(opcode::SWAP);
+                    self.emit_byte(opcode::POP); // removes the data leaving the result on top
+
+                    self.patch_jump(other_variant)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores the item of a destructured tuple or array on top of the stack into a new local
+    fn bind_item(&mut self, ident: Symbol, span: Span) {
+        let slot = self.new_slot();
+
+        self.locals.insert(
+            ident,
+            Local {
+                slot: slot as usize,
+                mutable: true,
+            },
+        );
+        self.declared.insert(slot as usize, (ident, span));
+        self.store(slot as usize, span);
+
+        self.emit_bytes(opcode::SETLOCAL, slot as u8);
+        self.emit_byte(opcode::POP);
+    }
+
+    /// Stores the parts of the matched value on top of the stack that the pattern binds
+    /// into new locals. The value is left on the stack
+    fn bind_pattern(&mut self, pattern: &Spanned<ast::Pattern>) {
+        match pattern.value {
+            ast::Pattern::Bind(ref name) => {
+                let slot = self.new_slot();
+
+                self.locals.insert(
+                    *name,
+                    Local {
+                        slot: slot as usize,
+                        mutable: true,
+                    },
+                );
+
+                self.emit_bytes(opcode::SETLOCAL, slot as u8);
+            }
+
+            ast::Pattern::Variant {
+                inner: Some(ref inner),
+                ..
+            } => {
+                self.emit_byte(opcode::DUP);
+                self.emit_byte(opcode::ENUMINNER);
+
+                self.bind_pattern(inner);
+
+                self.emit_byte(opcode::POP); // removes the data
+            }
+
+            _ => (),
+        }
+    }
+}
+
+fn is_function(ty: &Type) -> bool {
+    match ty {
+        Type::Generic(_, ty) => is_function(ty),
+        Type::App(TypeCon::Arrow, _) => true,
+        _ => false,
+    }
+}
+
+/// The opcode that performs the operation of a compound assignment
+/// on a value of type `ty`
+fn compound_opcode(op: &ast::AssignOperator, ty: &Type) -> u8 {
+    use crate::ast::AssignOperator;
+
+    if let Type::App(TypeCon::SizedInt(_), _) = *ty {
+        // sized ints use the int opcodes
+        return compound_opcode(op, &Type::App(TypeCon::Int, vec![]));
+    }
+
+    match (op, ty) {
+        (AssignOperator::PlusEqual, Type::App(TypeCon::Int, _)) => opcode::ADD,
+        (AssignOperator::PlusEqual, Type::App(TypeCon::Float, _)) => opcode::ADDF,
+        (AssignOperator::PlusEqual, Type::App(TypeCon::Str, _)) => opcode::CONCAT,
+        (AssignOperator::MinusEqual, Type::App(TypeCon::Int, _)) => opcode::SUB,
+        (AssignOperator::MinusEqual, Type::App(TypeCon::Float, _)) => opcode::SUBF,
+        (AssignOperator::StarEqual, Type::App(TypeCon::Int, _)) => opcode::MUL,
+        (AssignOperator::StarEqual, Type::App(TypeCon::Float, _)) => opcode::MULF,
+        (AssignOperator::SlashEqual, Type::App(TypeCon::Int, _)) => opcode::DIV,
+        (AssignOperator::SlashEqual, Type::App(TypeCon::Float, _)) => opcode::DIVF,
+        _ => unreachable!(), // type checker should prevent this
+    }
+}
+
+/// How a method has to be called
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum Receiver {
+    /// The method uses `self` so it can only be called on an instance
+    Instance,
+    /// The method can be called on the class or on an instance
+    Static,
+}
+
+/// The methods of each class keyed by their name
+type ClassMethods = FnvHashMap<Symbol, FnvHashMap<Symbol, Receiver>>;
+
+/// Collects the methods each class can be called with.
+/// A subclass also has the methods of its superclass unless it overrides them, which is
+/// looked up by name so the classes can be declared in any order
+fn class_methods(classes: &[ast::Class]) -> ClassMethods {
+    let by_name: FnvHashMap<Symbol, &ast::Class> =
+        classes.iter().map(|class| (class.name, class)).collect();
+
+    let mut class_methods = ClassMethods::default();
+
+    for class in classes {
+        let mut methods = FnvHashMap::default();
+        let mut visited = FnvHashSet::default();
+        let mut current = Some(class);
+
+        while let Some(class) = current {
+            if !visited.insert(class.name) {
+                break;
+            }
+
+            for method in class.methods.iter() {
+                let receiver = if uses_self(method) {
+                    Receiver::Instance
+                } else {
+                    Receiver::Static
+                };
+
+                methods.entry(method.name).or_insert(receiver);
+            }
+
+            current = class
+                .superclass
+                .as_ref()
+                .and_then(|superclass| by_name.get(&superclass.value).cloned());
+        }
+
+        class_methods.insert(class.name, methods);
+    }
+
+    class_methods
+}
+
+/// Whether the body of `method` refers to `self`.
+/// A method that doesn't can be called on the class as well as on an instance
+fn uses_self(method: &ast::Function) -> bool {
+    statement_uses_self(&method.body.value)
+}
+
+fn statement_uses_self(statement: &ast::TypedStatement) -> bool {
+    use crate::ast::Statement;
+
+    match statement.statement.value {
+        Statement::Block(ref statements) => statements
+            .iter()
+            .any(|statement| statement_uses_self(&statement.value)),
+        Statement::Break(_, ref value) => value
+            .as_ref()
+            .is_some_and(|value| expression_uses_self(&value.value)),
+        Statement::Continue(_) => false,
+        Statement::Defer(ref statement) => statement_uses_self(&statement.value),
+        Statement::Expr(ref expr)
+        | Statement::Print(ref expr)
+        | Statement::EPrint(ref expr)
+        | Statement::Return(ref expr)
+        | Statement::LetTuple { ref expr, .. }
+        | Statement::LetArray { ref expr, .. } => expression_uses_self(&expr.value),
+        Statement::If {
+            ref cond,
+            ref then,
+            ref otherwise,
+        } => {
+            expression_uses_self(&cond.value)
+                || statement_uses_self(&then.value)
+                || otherwise
+                    .as_ref()
+                    .is_some_and(|otherwise| statement_uses_self(&otherwise.value))
+        }
+        Statement::While(ref cond, ref body, _) => {
+            expression_uses_self(&cond.value) || statement_uses_self(&body.value)
+        }
+        Statement::Let { ref expr, .. } => expr
+            .as_ref()
+            .is_some_and(|expr| expression_uses_self(&expr.value)),
+    }
+}
+
+fn expressions_use_self(exprs: &[Spanned<ast::TypedExpression>]) -> bool {
+    exprs.iter().any(|expr| expression_uses_self(&expr.value))
+}
+
+fn expression_uses_self(expr: &ast::TypedExpression) -> bool {
+    use crate::ast::Expression;
+
+    match expr.expr.value {
+        Expression::Array(ref exprs)
+        | Expression::Tuple(ref exprs)
+        | Expression::Call(_, ref exprs)
+        | Expression::InstanceMethodCall { params: ref exprs, .. }
+        | Expression::StaticMethodCall { params: ref exprs, .. } => expressions_use_self(exprs),
+        Expression::Assign(_, _, ref expr)
+        | Expression::Cast(ref expr, _)
+        | Expression::GetProperty { property: ref expr, .. }
+        | Expression::GetMethod { method: ref expr, .. }
+        | Expression::Grouping(ref expr)
+        | Expression::TupleIndex(ref expr, _)
+        | Expression::Try { value: ref expr, .. }
+        | Expression::Unary(_, ref expr)
+        | Expression::VariantWithData { inner: ref expr, .. } => {
+            expression_uses_self(&expr.value)
+        }
+        Expression::Binary(ref lhs, _, ref rhs)
+        | Expression::Index(ref lhs, ref rhs)
+        | Expression::Set(_, _, ref lhs, ref rhs) => {
+            expression_uses_self(&lhs.value) || expression_uses_self(&rhs.value)
+        }
+        Expression::SetIndex(ref target, ref index, _, ref value) => {
+            expression_uses_self(&target.value)
+                || expression_uses_self(&index.value)
+                || expression_uses_self(&value.value)
+        }
+        Expression::Ternary(ref cond, ref then, ref otherwise) => {
+            expression_uses_self(&cond.value)
+                || expression_uses_self(&then.value)
+                || expression_uses_self(&otherwise.value)
+        }
+        Expression::Block(ref statements, ref value) => {
+            statements
+                .iter()
+                .any(|statement| statement_uses_self(&statement.value))
+                || expression_uses_self(&value.value)
+        }
+        Expression::CallValue(ref callee, ref args) => {
+            expression_uses_self(&callee.value) || expressions_use_self(args)
+        }
+        Expression::ClassLiteral { ref properties, .. } => properties
+            .iter()
+            .any(|property| expression_uses_self(&property.value.expr.value)),
+        Expression::Closure(ref closure) => uses_self(closure),
+        Expression::Loop(ref body, _) => statement_uses_self(&body.value),
+        Expression::Match { ref cond, ref arms } => {
+            expression_uses_self(&cond.value)
+                || arms.value.iter().any(|arm| {
+                    pattern_uses_self(&arm.value.pattern.value)
+                        || arm
+                            .value
+                            .guard
+                            .as_ref()
+                            .is_some_and(|guard| expression_uses_self(&guard.value))
+                        || statement_uses_self(&arm.value.body.value)
+                })
+        }
+        Expression::Var(symbol, _) => symbol == ast::SELF,
+        Expression::Literal(_) | Expression::VariantNoData { .. } => false,
+    }
+}
+
+fn pattern_uses_self(pattern: &ast::Pattern) -> bool {
+    match *pattern {
+        ast::Pattern::Expr(ref expr) => expression_uses_self(&expr.value),
+        ast::Pattern::Range(ref start, ref end) => {
+            expression_uses_self(&start.value) || expression_uses_self(&end.value)
+        }
+        ast::Pattern::Or(ref patterns) => patterns
+            .iter()
+            .any(|pattern| pattern_uses_self(&pattern.value)),
+        ast::Pattern::Variant { ref inner, .. } => inner
+            .as_ref()
+            .is_some_and(|inner| pattern_uses_self(&inner.value)),
+        ast::Pattern::Wildcard | ast::Pattern::Bind(_) => false,
+    }
 }
 
 fn compile_class(
     class: &ast::Class,
     symbols: &Symbols<()>,
+    class_methods: &ClassMethods,
+    functions: &FnvHashSet<Symbol>,
+    constants: &mut Vec<Value>,
     reporter: &mut Reporter,
     objects: RawObject,
 ) -> ParseResult<Class> {
     let mut methods = FnvHashMap::default();
 
     for method in class.methods.iter() {
-        methods.insert(
-            method.name,
-            compile_function(method, symbols, reporter, objects)?,
-        );
+        let compiled = compile_function(
+            method,
+            symbols,
+            class_methods,
+            functions,
+            constants,
+            reporter,
+            objects,
+            true,
+        )?;
+
+        methods.insert(method.name, Rc::new(compiled));
     }
 
     Ok(Class {
         name: class.name,
         methods,
+        superclass: class.superclass.as_ref().map(|superclass| superclass.value),
+        properties: class
+            .properties
+            .iter()
+            .map(|property| property.name)
+            .collect(),
     })
 }
 
+/// A guess at the number of bytes compiled from each statement and expression.
+/// Most instructions are one or two bytes long but jumps and calls are three
+const BYTES_PER_NODE: usize = 3;
+
+/// The number of statements and expressions in the body of a function.
+/// Used to size its chunk up front so it isn't reallocated as it grows
+#[derive(Debug, Default, Clone, Copy)]
+struct Size {
+    statements: usize,
+    expressions: usize,
+}
+
+impl Size {
+    fn of(func: &ast::Function) -> Size {
+        let mut size = Size::default();
+        size.statement(&func.body.value);
+        size
+    }
+
+    /// The estimated length of the function's code
+    fn bytes(&self) -> usize {
+        (self.statements + self.expressions) * BYTES_PER_NODE
+    }
+
+    fn statement(&mut self, statement: &ast::TypedStatement) {
+        use crate::ast::Statement;
+
+        self.statements += 1;
+
+        match statement.statement.value {
+            Statement::Block(ref statements) => {
+                for statement in statements {
+                    self.statement(&statement.value);
+                }
+            }
+            Statement::Break(_, ref value) => {
+                if let Some(ref value) = *value {
+                    self.expression(&value.value);
+                }
+            }
+            Statement::Continue(_) => (),
+            Statement::Defer(ref statement) => self.statement(&statement.value),
+            Statement::Expr(ref expr)
+            | Statement::Print(ref expr)
+            | Statement::EPrint(ref expr)
+            | Statement::Return(ref expr)
+            | Statement::LetTuple { ref expr, .. }
+            | Statement::LetArray { ref expr, .. } => self.expression(&expr.value),
+            Statement::If {
+                ref cond,
+                ref then,
+                ref otherwise,
+            } => {
+                self.expression(&cond.value);
+                self.statement(&then.value);
+
+                if let Some(ref otherwise) = *otherwise {
+                    self.statement(&otherwise.value);
+                }
+            }
+            Statement::While(ref cond, ref body, _) => {
+                self.expression(&cond.value);
+                self.statement(&body.value);
+            }
+            Statement::Let { ref expr, .. } => {
+                if let Some(ref expr) = *expr {
+                    self.expression(&expr.value);
+                }
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Spanned<ast::TypedExpression>]) {
+        for expr in exprs {
+            self.expression(&expr.value);
+        }
+    }
+
+    fn expression(&mut self, expr: &ast::TypedExpression) {
+        use crate::ast::Expression;
+
+        self.expressions += 1;
+
+        match expr.expr.value {
+            Expression::Array(ref exprs)
+            | Expression::Tuple(ref exprs)
+            | Expression::Call(_, ref exprs)
+            | Expression::InstanceMethodCall { params: ref exprs, .. }
+            | Expression::StaticMethodCall { params: ref exprs, .. } => self.exprs(exprs),
+            Expression::Assign(_, _, ref expr)
+            | Expression::Cast(ref expr, _)
+            | Expression::GetProperty { property: ref expr, .. }
+            | Expression::GetMethod { method: ref expr, .. }
+            | Expression::Grouping(ref expr)
+            | Expression::TupleIndex(ref expr, _)
+            | Expression::Try { value: ref expr, .. }
+            | Expression::Unary(_, ref expr)
+            | Expression::VariantWithData { inner: ref expr, .. } => self.expression(&expr.value),
+            Expression::Binary(ref lhs, _, ref rhs)
+            | Expression::Index(ref lhs, ref rhs)
+            | Expression::Set(_, _, ref lhs, ref rhs) => {
+                self.expression(&lhs.value);
+                self.expression(&rhs.value);
+            }
+            Expression::SetIndex(ref target, ref index, _, ref value) => {
+                self.expression(&target.value);
+                self.expression(&index.value);
+                self.expression(&value.value);
+            }
+            Expression::Ternary(ref cond, ref then, ref otherwise) => {
+                self.expression(&cond.value);
+                self.expression(&then.value);
+                self.expression(&otherwise.value);
+            }
+            Expression::Block(ref statements, ref value) => {
+                for statement in statements {
+                    self.statement(&statement.value);
+                }
+
+                self.expression(&value.value);
+            }
+            Expression::CallValue(ref callee, ref args) => {
+                self.expression(&callee.value);
+                self.exprs(args);
+            }
+            Expression::ClassLiteral { ref properties, .. } => {
+                for property in properties {
+                    self.expression(&property.value.expr.value);
+                }
+            }
+            Expression::Loop(ref body, _) => self.statement(&body.value),
+            Expression::Match { ref cond, ref arms } => {
+                self.expression(&cond.value);
+
+                for arm in arms.value.iter() {
+                    self.pattern(&arm.value.pattern.value);
+
+                    if let Some(ref guard) = arm.value.guard {
+                        self.expression(&guard.value);
+                    }
+
+                    self.statement(&arm.value.body.value);
+                }
+            }
+            // A closure is compiled to its own chunk
+            Expression::Closure(_)
+            | Expression::Literal(_)
+            | Expression::Var(_, _)
+            | Expression::VariantNoData { .. } => (),
+        }
+    }
+
+    fn pattern(&mut self, pattern: &ast::Pattern) {
+        match *pattern {
+            ast::Pattern::Expr(ref expr) => self.expression(&expr.value),
+            ast::Pattern::Range(ref start, ref end) => {
+                self.expression(&start.value);
+                self.expression(&end.value);
+            }
+            ast::Pattern::Or(ref patterns) => {
+                for pattern in patterns {
+                    self.pattern(&pattern.value);
+                }
+            }
+            ast::Pattern::Variant { ref inner, .. } => {
+                self.expressions += 1;
+
+                if let Some(ref inner) = *inner {
+                    self.pattern(&inner.value);
+                }
+            }
+            ast::Pattern::Wildcard | ast::Pattern::Bind(_) => self.expressions += 1,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn compile_function(
     func: &ast::Function,
     symbols: &Symbols<()>,
+    class_methods: &ClassMethods,
+    functions: &FnvHashSet<Symbol>,
+    constants: &mut Vec<Value>,
     reporter: &mut Reporter,
     objects: RawObject,
+    is_method: bool,
 ) -> ParseResult<Function> {
     let mut params = FnvHashMap::default();
 
@@ -806,7 +1885,30 @@ fn compile_function(
         params.insert(param.name, i);
     } // store param id and the index in the vec
 
-    let mut builder = Builder::new(reporter, symbols, objects, params);
+    let mut builder = Builder::new(
+        reporter,
+        symbols,
+        class_methods,
+        functions,
+        constants,
+        objects,
+        params,
+    );
+
+    let size = Size::of(func);
+    builder.chunk = Chunk::with_capacity(size.bytes(), size.statements);
+
+    if is_method {
+        // The receiver is always stored in the first local slot
+        let slot = builder.new_slot();
+        builder.locals.insert(
+            ast::SELF,
+            Local {
+                slot: slot as usize,
+                mutable: true,
+            },
+        );
+    }
 
     builder.compile_statement(&func.body)?;
 
@@ -825,25 +1927,45 @@ pub fn compile(
 ) -> ParseResult<(Program, RawObject)> {
     let mut funcs = FnvHashMap::default();
     let mut classes: FnvHashMap<Symbol, Class> = FnvHashMap::default();
+    let mut names = FnvHashMap::default();
 
     let objects = ::std::ptr::null::<RawObject>() as RawObject;
 
+    let class_methods = class_methods(&ast.classes);
+    let functions: FnvHashSet<Symbol> =
+        ast.functions.iter().map(|function| function.name).collect();
+    let mut constants = Vec::new();
+
     for function in ast.functions.iter() {
-        funcs.insert(
-            function.name,
-            compile_function(function, symbols, reporter, objects)?,
-        );
+        let compiled = compile_function(
+            function,
+            symbols,
+            &class_methods,
+            &functions,
+            &mut constants,
+            reporter,
+            objects,
+            false,
+        )?;
+
+        funcs.insert(function.name, Rc::new(compiled));
     }
 
     for class in ast.classes.iter() {
-        let mut compiled_class = compile_class(class, symbols, reporter, objects)?;
+        let compiled_class = compile_class(
+            class,
+            symbols,
+            &class_methods,
+            &functions,
+            &mut constants,
+            reporter,
+            objects,
+        )?;
 
-        if let Some(ref superclass) = class.superclass {
-            let superclass = &classes[&superclass.value];
+        names.insert(class.name, symbols.name(class.name));
 
-            compiled_class
-                .methods
-                .extend(superclass.methods.clone().into_iter());
+        for property in class.properties.iter() {
+            names.insert(property.name, symbols.name(property.name));
         }
 
         classes.insert(class.name, compiled_class);
@@ -853,7 +1975,895 @@ pub fn compile(
         Program {
             functions: funcs,
             classes,
+            names,
+            constants,
         },
         objects,
     ))
 }
+
+/// The output of previous compilations.
+/// A function or class is only recompiled when its ast changes
+#[derive(Debug, Default)]
+pub struct CompiledCache {
+    /// A hash of the class names, superclasses, properties, methods and function names.
+    /// When it changes everything is recompiled
+    layout: u64,
+    functions: FnvHashMap<Symbol, (u64, Rc<Function>)>,
+    classes: FnvHashMap<Symbol, (u64, Class)>,
+    /// The constant pool the cached functions and classes refer to.
+    /// Constants are only added so the indices in the cached code stay valid
+    constants: Vec<Value>,
+    recompiled: Vec<Symbol>,
+}
+
+impl CompiledCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The functions and classes compiled by the last call to `compile_incremental`
+    pub fn recompiled(&self) -> &[Symbol] {
+        &self.recompiled
+    }
+}
+
+fn ast_hash<T: Hash>(node: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    node.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn layout_hash(
+    classes: &[ast::Class],
+    class_methods: &ClassMethods,
+    functions: &FnvHashSet<Symbol>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    // Whether a call compiles to `CALL` or `CALLNATIVE` depends on the functions defined
+    let mut names: Vec<_> = functions.iter().collect();
+    names.sort_by_key(|name| name.0);
+    names.hash(&mut hasher);
+
+    for class in classes {
+        class.name.hash(&mut hasher);
+        class.superclass.hash(&mut hasher);
+        class.properties.hash(&mut hasher);
+
+        // Whether a method call compiles to an instance or a static call depends on the
+        // receiver of every method the class has, including the inherited ones
+        let mut methods: Vec<_> = class_methods[&class.name].iter().collect();
+        methods.sort_by_key(|(name, _)| name.0);
+        methods.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+/// Like `compile` but reuses the functions and classes in `cache` whose ast hasn't changed
+pub fn compile_incremental(
+    ast: &ast::Program,
+    symbols: &Symbols<()>,
+    cache: &mut CompiledCache,
+    reporter: &mut Reporter,
+) -> ParseResult<(Program, RawObject)> {
+    let mut funcs = FnvHashMap::default();
+    let mut classes: FnvHashMap<Symbol, Class> = FnvHashMap::default();
+    let mut names = FnvHashMap::default();
+
+    let objects = ::std::ptr::null::<RawObject>() as RawObject;
+
+    let class_methods = class_methods(&ast.classes);
+    let functions: FnvHashSet<Symbol> =
+        ast.functions.iter().map(|function| function.name).collect();
+
+    let layout = layout_hash(&ast.classes, &class_methods, &functions);
+
+    if layout != cache.layout {
+        cache.functions.clear();
+        cache.classes.clear();
+        cache.constants.clear();
+        cache.layout = layout;
+    }
+
+    cache.recompiled.clear();
+
+    let mut cached_functions = FnvHashMap::default();
+
+    for function in ast.functions.iter() {
+        let hash = ast_hash(function);
+
+        let compiled = match cache.functions.remove(&function.name) {
+            Some((old, compiled)) if old == hash => compiled,
+            _ => {
+                cache.recompiled.push(function.name);
+                Rc::new(compile_function(
+                    function,
+                    symbols,
+                    &class_methods,
+                    &functions,
+                    &mut cache.constants,
+                    reporter,
+                    objects,
+                    false,
+                )?)
+            }
+        };
+
+        funcs.insert(function.name, Rc::clone(&compiled));
+        cached_functions.insert(function.name, (hash, compiled));
+    }
+
+    let mut cached_classes = FnvHashMap::default();
+
+    for class in ast.classes.iter() {
+        let hash = ast_hash(class);
+
+        let compiled = match cache.classes.remove(&class.name) {
+            Some((old, compiled)) if old == hash => compiled,
+            _ => {
+                cache.recompiled.push(class.name);
+                compile_class(
+                    class,
+                    symbols,
+                    &class_methods,
+                    &functions,
+                    &mut cache.constants,
+                    reporter,
+                    objects,
+                )?
+            }
+        };
+
+        names.insert(class.name, symbols.name(class.name));
+
+        for property in class.properties.iter() {
+            names.insert(property.name, symbols.name(property.name));
+        }
+
+        classes.insert(class.name, compiled.clone());
+        cached_classes.insert(class.name, (hash, compiled));
+    }
+
+    // functions and classes that were removed are dropped from the cache
+    cache.functions = cached_functions;
+    cache.classes = cached_classes;
+
+    Ok((
+        Program {
+            functions: funcs,
+            classes,
+            names,
+            constants: cache.constants.clone(),
+        },
+        objects,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compile, compile_incremental, CompiledCache, Size};
+    use crate::ast;
+    use crate::infer::Infer;
+    use crate::native::{Native, NativeType};
+    use opcode::{self, OpCode};
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::rc::Rc;
+    use syntax::parser::Parser;
+    use util::emmiter::Reporter;
+    use util::symbol::{SymbolFactory, Symbols};
+    use vm::{Chunk, Error, Program, Value, VM};
+
+    /// Parses and type checks `input` with `natives` available to it.
+    /// The reporter is returned so the caller can compile the ast and check what was reported
+    fn infer_source(input: &str, natives: Vec<Native>) -> (ast::Program, Symbols<()>, Reporter) {
+        let mut reporter = Reporter::new();
+        let strings = Rc::new(SymbolFactory::new());
+        let mut symbols = Symbols::new(Rc::clone(&strings));
+
+        let ast = Parser::new(input, reporter.clone(), &mut symbols)
+            .parse()
+            .unwrap();
+        let typed_ast = Infer::new()
+            .with_natives(natives)
+            .infer(ast, &strings, &mut reporter)
+            .unwrap();
+
+        (typed_ast, symbols, reporter)
+    }
+
+    /// Compiles `input` returning the typed ast along with the program.
+    /// Callers hold on to the ast so nothing the program was built from is freed while it runs
+    fn compile_source(input: &str) -> (Program, Symbols<()>, ast::Program) {
+        let (typed_ast, symbols, mut reporter) = infer_source(input, vec![]);
+        let (program, _) = compile(&typed_ast, &symbols, &mut reporter).unwrap();
+
+        (program, symbols, typed_ast)
+    }
+
+    /// Compiles `input` returning the warnings that were reported
+    fn compile_warnings(input: &str) -> Vec<String> {
+        let (typed_ast, symbols, mut reporter) = infer_source(input, vec![]);
+        compile(&typed_ast, &symbols, &mut reporter).unwrap();
+
+        reporter.warnings()
+    }
+
+    /// The disassembler pads its operands so trailing whitespace is ignored
+    fn trim_lines(text: &str) -> String {
+        text.lines().map(str::trim_end).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Compares the disassembly of every function and method in `snapshots/{name}.tox` with
+    /// `snapshots/{name}.txt`. Set `UPDATE_SNAPSHOTS` to overwrite the expected disassembly
+    fn assert_snapshot(name: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots");
+        let source = fs::read_to_string(dir.join(format!("{}.tox", name))).unwrap();
+
+        let (program, symbols, _ast) = compile_source(&source);
+
+        let mut chunks: Vec<(String, &Chunk)> = program
+            .functions
+            .values()
+            .map(|function| (symbols.name(function.name), &function.body))
+            .collect();
+
+        for class in program.classes.values() {
+            for method in class.methods.values() {
+                let name = format!("{}.{}", symbols.name(class.name), symbols.name(method.name));
+                chunks.push((name, &method.body));
+            }
+        }
+
+        chunks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let disassembly = chunks
+            .iter()
+            .map(|(name, chunk)| chunk.disassembly(name, &program.constants))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let disassembly = trim_lines(&disassembly);
+
+        let path = dir.join(format!("{}.txt", name));
+
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&path, disassembly + "\n").unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap();
+
+        assert_eq!(disassembly, trim_lines(&expected), "snapshot `{}`", name);
+    }
+
+    #[test]
+    fn arithmetic_snapshot() {
+        assert_snapshot("arithmetic");
+    }
+
+    #[test]
+    fn loop_snapshot() {
+        assert_snapshot("loop");
+    }
+
+    #[test]
+    fn call_snapshot() {
+        assert_snapshot("call");
+    }
+
+    #[test]
+    fn class_snapshot() {
+        assert_snapshot("class");
+    }
+
+    /// Counts how many times the function `callee` is called by `caller`
+    fn count_calls(input: &str, caller: &str, callee: &str) -> usize {
+        let (program, mut symbols, _ast) = compile_source(input);
+
+        let callee = symbols.symbol(callee).0 as u8;
+        let code = &program.functions[&symbols.symbol(caller)].body.code;
+
+        code.windows(2)
+            .filter(|window| window[0] == opcode::CALL && window[1] == callee)
+            .count()
+    }
+
+    #[test]
+    fn match_evaluates_cond_once() {
+        let input = "
+            fn next() -> int {
+                return 2;
+            }
+
+            fn main() {
+                let name = match next() {
+                    1 => \"one\",
+                    2 => \"two\",
+                    _ => \"other\"
+                };
+
+                print name;
+            }
+        ";
+
+        assert_eq!(count_calls(input, "main", "next"), 1);
+    }
+
+    #[test]
+    fn compound_set_evaluates_instance_once() {
+        let input = "
+            class Counter {
+                count:int;
+            }
+
+            fn make() -> Counter {
+                return Counter { count:0 };
+            }
+
+            fn main() {
+                make().count += 1;
+            }
+        ";
+
+        assert_eq!(count_calls(input, "main", "make"), 1);
+    }
+
+    #[test]
+    fn compound_set_index_evaluates_index_once() {
+        let input = "
+            fn next() -> int {
+                return 1;
+            }
+
+            fn main() {
+                let arr = [1, 2, 3];
+                arr[next()] += 1;
+            }
+        ";
+
+        assert_eq!(count_calls(input, "main", "next"), 1);
+    }
+
+    #[test]
+    fn class_literal_evaluates_properties_in_order() {
+        let input = "
+            class Pair {
+                a:int, b:int;
+            }
+
+            fn first() -> int {
+                return 1;
+            }
+
+            fn second() -> int {
+                return 2;
+            }
+
+            fn main() {
+                let pair = Pair { a:first(), b:second() };
+            }
+        ";
+
+        let (program, mut symbols, _ast) = compile_source(input);
+
+        let first = symbols.symbol("first").0 as u8;
+        let second = symbols.symbol("second").0 as u8;
+        let code = &program.functions[&symbols.symbol("main")].body.code;
+
+        let position = |callee| {
+            code.windows(2)
+                .position(|window| window[0] == opcode::CALL && window[1] == callee)
+                .unwrap()
+        };
+
+        assert!(position(first) < position(second));
+    }
+
+    #[test]
+    fn run_program_returns_main_value() {
+        let (program, mut symbols, _ast) = compile_source("fn main() -> int { return 0; }");
+
+        let mut out = Vec::new();
+
+        let result =
+            VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(result.as_int(), 0);
+    }
+
+    #[test]
+    fn run_program_prints_to_the_output() {
+        let (program, mut symbols, _ast) = compile_source("fn main() { print \"hello\"; }");
+        let mut out = Vec::new();
+
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(out, b"hello\n");
+    }
+
+    #[test]
+    fn methods_resolve_regardless_of_declaration_order() {
+        let input = "
+            fn helper() -> int {
+                return Derived.square(3);
+            }
+
+            class Base {
+                fn square(x:int) -> int {
+                    return x * x;
+                }
+            }
+
+            class Derived extends Base {}
+
+            fn main() {
+                print helper();
+            }
+        ";
+
+        let (_, mut symbols, mut typed_ast) = compile_source(input);
+
+        // as if the subclass had been declared before the class it inherits from
+        typed_ast.classes.reverse();
+
+        let (program, _) = compile(&typed_ast, &symbols, &mut Reporter::new()).unwrap();
+        let mut out = Vec::new();
+
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(out, b"9\n");
+    }
+
+    #[test]
+    fn static_calls_to_methods_using_self_are_rejected() {
+        let input = "
+            class Counter {
+                count:int;
+
+                fn get() -> int {
+                    return self.count;
+                }
+            }
+
+            fn main() {
+                print Counter.get();
+            }
+        ";
+
+        let (typed_ast, symbols, mut reporter) = infer_source(input, vec![]);
+
+        assert!(compile(&typed_ast, &symbols, &mut reporter).is_err());
+        assert_eq!(
+            reporter.diagnostics()[0].msg(),
+            "method get uses self so it can't be called on class Counter"
+        );
+    }
+
+    #[test]
+    fn deferred_statements_run_on_every_exit() {
+        let input = "
+            fn early(leave:bool) -> int {
+                defer print \"first\";
+                defer print \"second\";
+
+                if leave {
+                    return 1;
+                }
+
+                let i = 0;
+
+                while true {
+                    defer print i;
+                    i += 1;
+
+                    if i == 2 {
+                        break;
+                    }
+                }
+
+                return 2;
+            }
+
+            fn main() {
+                print early(true);
+                print early(false);
+            }
+        ";
+
+        let (program, mut symbols, _ast) = compile_source(input);
+        let mut out = Vec::new();
+
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "second\nfirst\n1\n1\n2\nsecond\nfirst\n2\n"
+        );
+    }
+
+    #[test]
+    fn labeled_break_leaves_the_outer_loop() {
+        let input = "
+            fn main() {
+                let i = 0;
+
+                outer: while true {
+                    i += 1;
+
+                    while true {
+                        break outer;
+                    }
+                }
+
+                print i;
+            }
+        ";
+
+        let (program, mut symbols, _ast) = compile_source(input);
+        let mut out = Vec::new();
+
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(out, b"1\n");
+    }
+
+    #[test]
+    fn break_gives_a_loop_its_value() {
+        let input = "
+            fn main() {
+                let i = 0;
+                let x = loop {
+                    i += 1;
+
+                    if i == 5 {
+                        break i * 2;
+                    }
+                };
+
+                print x;
+            }
+        ";
+
+        let (program, mut symbols, _ast) = compile_source(input);
+        let mut out = Vec::new();
+
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(out, b"10\n");
+    }
+
+    #[test]
+    fn profile_counts_loop_iterations() {
+        let (program, mut symbols, _ast) =
+            compile_source("fn main() { let i = 0; while i < 10 { i = i + 1; } }");
+
+        let mut vm = VM::new(symbols.symbol("main"), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_profiling();
+        vm.run().unwrap();
+
+        let profile = vm.profile().unwrap();
+
+        assert_eq!(profile.count(OpCode::Add), 10);
+        assert_eq!(profile.count(OpCode::Less), 11);
+    }
+
+    #[test]
+    fn calls_registered_native() {
+        let input = "fn main() { print host_add(1, 2); }";
+        let host_add = Native::new(
+            "host_add",
+            vec![NativeType::Int, NativeType::Int],
+            NativeType::Int,
+        );
+
+        let (typed_ast, mut symbols, mut reporter) = infer_source(input, vec![host_add]);
+        let (program, _) = compile(&typed_ast, &symbols, &mut reporter).unwrap();
+
+        let main = &program.functions[&symbols.symbol("main")].body.code;
+        assert!(main.contains(&opcode::CALLNATIVE));
+
+        let mut out = Vec::new();
+
+        {
+            let mut vm = VM::new(symbols.symbol("main"), &program, ::std::ptr::null_mut())
+                .unwrap()
+                .with_output(&mut out);
+
+            vm.register_native(
+                symbols.symbol("host_add"),
+                2,
+                Box::new(|args| Ok(Value::int(args[0].as_int() + args[1].as_int()))),
+            );
+
+            vm.run().unwrap();
+        }
+
+        assert_eq!(out, b"3\n");
+    }
+
+    #[test]
+    fn sandbox_disables_fopen() {
+        let (program, mut symbols, _ast) =
+            compile_source("fn main() { print fopen(\"Cargo.toml\"); }");
+        let mut out = Vec::new();
+
+        let result = VM::new(symbols.symbol("main"), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_sandbox()
+            .with_output(&mut out)
+            .run();
+
+        match result {
+            Err(Error::NativeDisabled { name }) => assert_eq!(name, "fopen"),
+            other => panic!("expected fopen to be disabled but got {:?}", other),
+        }
+
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn step_limit_stops_infinite_loop() {
+        let (program, mut symbols, _ast) = compile_source("fn main() { while true { } }");
+
+        let result = VM::new(symbols.symbol("main"), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_step_limit(1000)
+            .run();
+
+        match result {
+            Err(Error::StepLimitExceeded) => (),
+            other => panic!("expected the step limit to be exceeded but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn clock_can_be_overridden() {
+        let (program, mut symbols, _ast) =
+            compile_source("fn main() { print clock(); let now = clock; print now(); }");
+        let mut out = Vec::new();
+
+        {
+            let mut vm = VM::new(symbols.symbol("main"), &program, ::std::ptr::null_mut())
+                .unwrap()
+                .with_output(&mut out);
+
+            vm.set_clock(Box::new(|| 1.5));
+
+            vm.run().unwrap();
+        }
+
+        assert_eq!(out, b"1.5\n1.5\n");
+    }
+
+    #[test]
+    fn constants_are_shared_between_functions() {
+        let input = "
+            fn fail() -> str {
+                return \"error\";
+            }
+
+            fn main() {
+                print \"error\";
+                print fail();
+            }
+        ";
+
+        let (program, _, _ast) = compile_source(input);
+
+        assert_eq!(program.constants.len(), 1);
+        assert_eq!(program.constants[0].as_string().to_string(), "error");
+    }
+
+    #[test]
+    fn chunk_is_sized_from_the_ast() {
+        let input = format!("fn main() {{ let x = 41;\n{}}}", "print x + 1;\n".repeat(1000));
+
+        let (program, mut symbols, typed_ast) = compile_source(&input);
+        let size = Size::of(&typed_ast.functions[0]);
+
+        let chunk = &program.functions[&symbols.symbol("main")].body;
+
+        // The chunk never grew past the size it started with
+        assert!(chunk.code.len() <= size.bytes());
+        assert_eq!(chunk.code.capacity(), size.bytes());
+        assert_eq!(chunk.lines.capacity(), size.statements);
+
+        let mut out = Vec::new();
+        VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out).unwrap();
+
+        assert_eq!(out, "42\n".repeat(1000).into_bytes());
+    }
+
+    #[test]
+    fn incremental_recompiles_changed_functions() {
+        let mut cache = CompiledCache::new();
+
+        // symbols are interned in the order they're seen so each build gets the same ones
+        let build = |input: &str, cache: &mut CompiledCache| {
+            let (typed_ast, symbols, mut reporter) = infer_source(input, vec![]);
+
+            compile_incremental(&typed_ast, &symbols, cache, &mut reporter).unwrap();
+
+            symbols
+        };
+
+        build(
+            "fn one() -> int { return 1; } fn main() { print one(); }",
+            &mut cache,
+        );
+
+        assert_eq!(cache.recompiled().len(), 2);
+
+        let mut symbols = build(
+            "fn one() -> int { return 1; } fn main() { print one() + 1; }",
+            &mut cache,
+        );
+
+        assert_eq!(cache.recompiled(), &[symbols.symbol("main")]);
+    }
+
+    #[test]
+    fn incremental_recompiles_everything_when_a_receiver_changes() {
+        let mut cache = CompiledCache::new();
+
+        // symbols are interned in the order they're seen so each build gets the same ones
+        let build = |input: &str, cache: &mut CompiledCache| {
+            let (typed_ast, symbols, mut reporter) = infer_source(input, vec![]);
+
+            compile_incremental(&typed_ast, &symbols, cache, &mut reporter).unwrap();
+
+            symbols
+        };
+
+        build(
+            "class A { x:int; fn f() -> int { return 1; } } fn main() { print 1; }",
+            &mut cache,
+        );
+
+        // `f` now needs an instance so calls to it compile differently
+        let mut symbols = build(
+            "class A { x:int; fn f() -> int { return self.x; } } fn main() { print 1; }",
+            &mut cache,
+        );
+
+        assert!(cache.recompiled().contains(&symbols.symbol("main")));
+    }
+
+    #[test]
+    fn ordered_output_is_reproducible() {
+        let input = "
+            class Counter {
+                count:int;
+
+                fn get() -> int {
+                    return self.count;
+                }
+
+                fn zero() -> int {
+                    return 0;
+                }
+            }
+
+            fn b() -> int {
+                return 2;
+            }
+
+            fn a() -> int {
+                return 1;
+            }
+
+            fn main() {
+                print a() + b();
+            }
+        ";
+
+        let bytes = |program: &Program| {
+            let mut bytes: Vec<u8> = vec![];
+
+            for function in program.ordered_functions() {
+                bytes.extend(&function.body.code);
+            }
+
+            for class in program.ordered_classes() {
+                for method in class.ordered_methods() {
+                    bytes.extend(&method.body.code);
+                }
+            }
+
+            bytes
+        };
+
+        let (first, _, _first_ast) = compile_source(input);
+        let (second, _, _second_ast) = compile_source(input);
+
+        assert_eq!(bytes(&first), bytes(&second));
+
+        let names: Vec<_> = first
+            .ordered_functions()
+            .iter()
+            .map(|function| function.name)
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort_by_key(|name| name.0);
+
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn warns_about_unused_variables() {
+        let input = "
+            fn main() {
+                let unused = 1;
+                let used = 2;
+
+                print used;
+            }
+        ";
+
+        assert_eq!(compile_warnings(input), vec!["unused variable `unused`"]);
+    }
+
+    #[test]
+    fn underscore_variables_can_be_unused() {
+        let input = "
+            fn main() {
+                let _unused = 1;
+            }
+        ";
+
+        assert!(compile_warnings(input).is_empty());
+    }
+
+    #[test]
+    fn warns_about_dead_stores() {
+        let input = "
+            fn main() {
+                let x = 0;
+                print x;
+                x = 1;
+                x = 2;
+                print x;
+            }
+        ";
+
+        assert_eq!(compile_warnings(input), vec!["value assigned to `x` is never read"]);
+    }
+
+    #[test]
+    fn stores_read_on_another_path_are_not_dead() {
+        let input = "
+            fn main() {
+                let x = 0;
+
+                if true {
+                    x = 1;
+                }
+
+                x += 1;
+                print x;
+            }
+        ";
+
+        assert!(compile_warnings(input).is_empty());
+    }
+
+    #[test]
+    fn jumps_over_64kb_are_an_error() {
+        let input = format!(
+            "fn main() {{ let total = 1; if true {{ {} }} print total; }}",
+            "total = total + total;".repeat(10_000)
+        );
+
+        let (typed_ast, symbols, mut reporter) = infer_source(&input, vec![]);
+
+        assert!(compile(&typed_ast, &symbols, &mut reporter).is_err());
+        assert!(reporter
+            .diagnostics()
+            .iter()
+            .any(|diagnostic| diagnostic.msg() == "jump too large; function body exceeds 64KB"));
+    }
+}
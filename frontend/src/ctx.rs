@@ -1,10 +1,12 @@
 use crate::infer::env::VarEntry;
-use crate::infer::types::{Type, TypeCon};
+use crate::infer::types::{Type, TypeCon, TypeVar, Variant};
+use crate::native::Native;
 
 use std::rc::Rc;
 use util::emmiter::Reporter;
 use util::pos::Span;
 use util::symbol::{Symbol, SymbolFactory, Symbols};
+use vm::IntKind;
 
 #[derive(Debug)]
 pub struct CompileCtx<'a> {
@@ -22,42 +24,126 @@ impl<'a> CompileCtx<'a> {
         let float_symbol = types.symbol("float");
         let nil_symbol = types.symbol("nil");
         let bool_symbol = types.symbol("bool");
+        let char_symbol = types.symbol("char");
 
         types.enter(int_symbol, Type::App(TypeCon::Int, vec![]));
         types.enter(float_symbol, Type::App(TypeCon::Float, vec![]));
         types.enter(bool_symbol, Type::App(TypeCon::Bool, vec![]));
+        types.enter(char_symbol, Type::App(TypeCon::Char, vec![]));
         types.enter(nil_symbol, Type::Nil);
         types.enter(string_symbol, Type::App(TypeCon::Str, vec![]));
 
+        for kind in IntKind::ALL.iter() {
+            let symbol = types.symbol(kind.name());
+            types.enter(symbol, Type::App(TypeCon::SizedInt(*kind), vec![]));
+        }
+
+        {
+            // `Array<T>` is another way to write `[T]`
+            let t = TypeVar::new();
+            let array = Type::App(TypeCon::Array(Box::new(Type::Var(t))), vec![]);
+            let array_symbol = types.symbol("Array");
+
+            types.enter(array_symbol, Type::Generic(vec![t], Box::new(array)));
+        }
+
+        {
+            let mut add_enum =
+                |name: &str, typevars: Vec<TypeVar>, variants: Vec<(&str, Option<Type>)>| {
+                    let name = types.symbol(name);
+                    let variants = variants
+                        .into_iter()
+                        .enumerate()
+                        .map(|(tag, (variant, inner))| {
+                            let tag = tag as u32;
+                            (types.symbol(variant), Variant { tag, inner })
+                        })
+                        .collect();
+
+                    let ty = Type::Generic(typevars, Box::new(Type::Enum { name, variants }));
+                    types.enter(name, ty);
+                };
+
+            let t = TypeVar::new();
+            add_enum(
+                "Option",
+                vec![t],
+                vec![("Some", Some(Type::Var(t))), ("None", None)],
+            );
+
+            let (t, e) = (TypeVar::new(), TypeVar::new());
+            add_enum(
+                "Result",
+                vec![t, e],
+                vec![("Ok", Some(Type::Var(t))), ("Err", Some(Type::Var(e)))],
+            );
+        }
+
         let mut vars = Symbols::new(Rc::clone(strings));
 
         {
-            let mut add_builtin = |name: &str, mut params: Vec<Type>, returns: Type| {
-                let symbol = vars.symbol(name);
-                params.push(returns);
-                vars.enter(
-                    symbol,
-                    VarEntry::Fun {
-                        ty: Type::Generic(vec![], Box::new(Type::App(TypeCon::Arrow, params))),
-                    },
-                );
-            };
-
-            add_builtin("clock", vec![], Type::App(TypeCon::Float, vec![]));
+            let mut add_builtin =
+                |name: &str, typevars: Vec<TypeVar>, mut params: Vec<Type>, returns: Type| {
+                    let symbol = vars.symbol(name);
+                    params.push(returns);
+                    let ty = Type::Generic(typevars, Box::new(Type::App(TypeCon::Arrow, params)));
+                    vars.enter(symbol, VarEntry::Fun { ty });
+                };
+
+            add_builtin("clock", vec![], vec![], Type::App(TypeCon::Float, vec![]));
             add_builtin(
                 "random",
+                vec![],
                 vec![
                     Type::App(TypeCon::Int, vec![]),
                     Type::App(TypeCon::Int, vec![]),
                 ],
                 Type::App(TypeCon::Int, vec![]),
             );
-            add_builtin("read", vec![], Type::App(TypeCon::Str, vec![]));
+            add_builtin("read", vec![], vec![], Type::App(TypeCon::Str, vec![]));
             add_builtin(
                 "fopen",
+                vec![],
                 vec![Type::App(TypeCon::Str, vec![])],
                 Type::App(TypeCon::Str, vec![]),
             );
+
+            let array = |ty: TypeVar| Type::App(TypeCon::Array(Box::new(Type::Var(ty))), vec![]);
+            let function = |types: Vec<Type>| Type::App(TypeCon::Arrow, types);
+
+            let (t, u) = (TypeVar::new(), TypeVar::new());
+            add_builtin(
+                "map",
+                vec![t, u],
+                vec![array(t), function(vec![Type::Var(t), Type::Var(u)])],
+                array(u),
+            );
+
+            let t = TypeVar::new();
+            add_builtin(
+                "filter",
+                vec![t],
+                vec![
+                    array(t),
+                    function(vec![Type::Var(t), Type::App(TypeCon::Bool, vec![])]),
+                ],
+                array(t),
+            );
+
+            let (t, u) = (TypeVar::new(), TypeVar::new());
+            add_builtin(
+                "reduce",
+                vec![t, u],
+                vec![
+                    array(t),
+                    Type::Var(u),
+                    function(vec![Type::Var(u), Type::Var(t), Type::Var(u)]),
+                ],
+                Type::Var(u),
+            );
+
+            let t = TypeVar::new();
+            add_builtin("clone", vec![t], vec![Type::Var(t)], Type::Var(t));
         }
 
         CompileCtx {
@@ -122,4 +208,14 @@ impl<'a> CompileCtx<'a> {
     pub fn add_var(&mut self, symbol: Symbol, data: VarEntry) {
         self.vars.enter(symbol, data)
     }
+
+    /// Makes a function registered with the vm callable by its name
+    pub fn add_native(&mut self, native: &Native) {
+        let symbol = self.vars.symbol(&native.name);
+        let mut params: Vec<Type> = native.params.iter().map(|param| param.ty()).collect();
+        params.push(native.returns.ty());
+
+        let ty = Type::Generic(vec![], Box::new(Type::App(TypeCon::Arrow, params)));
+        self.vars.enter(symbol, VarEntry::Fun { ty });
+    }
 }
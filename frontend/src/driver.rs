@@ -0,0 +1,346 @@
+//! Helpers that run the whole pipeline over a string of source code.
+use crate::codegen::compile;
+use crate::infer::Infer;
+use crate::inline::inline;
+use std::rc::Rc;
+use syntax::parser::Parser;
+use util::emmiter::{Diagnostic, Reporter};
+use util::symbol::{SymbolFactory, Symbols};
+use vm::{Program, RawObject, VM};
+
+/// A program compiled from a string along with everything needed to run it
+#[derive(Debug)]
+pub struct Compiled {
+    pub program: Program,
+    /// The objects allocated while compiling which the vm adds its own objects to
+    pub objects: RawObject,
+    pub symbols: Symbols<()>,
+}
+
+/// Parses, type checks and compiles `source` reporting any diagnostics to `reporter`.
+/// Returns `None` if any stage failed
+fn compile_with(source: &str, reporter: &mut Reporter) -> Option<Compiled> {
+    let strings = Rc::new(SymbolFactory::new());
+    let mut symbols = Symbols::new(Rc::clone(&strings));
+
+    let ast = Parser::new(source, reporter.clone(), &mut symbols)
+        .parse()
+        .ok()?;
+
+    let typed_ast = Infer::new().infer(ast, &strings, reporter).ok()?;
+
+    let (mut program, objects) = compile(&typed_ast, &symbols, reporter).ok()?;
+
+    inline(&mut program);
+
+    Some(Compiled {
+        program,
+        objects,
+        symbols,
+    })
+}
+
+/// Compiles `source` into a program returning every diagnostic that was reported along the way.
+/// The program is `None` if the source failed to compile
+pub fn compile_str(source: &str) -> (Option<Compiled>, Vec<Diagnostic>) {
+    let mut reporter = Reporter::new();
+
+    let compiled = compile_with(source, &mut reporter);
+
+    (compiled, reporter.diagnostics())
+}
+
+/// Compiles and runs `source` returning everything it printed.
+/// Any compile or runtime errors are returned instead
+pub fn run_str(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let mut reporter = Reporter::new();
+
+    let mut compiled = match compile_with(source, &mut reporter) {
+        Some(compiled) => compiled,
+        None => return Err(reporter.diagnostics()),
+    };
+
+    let mut out = Vec::new();
+
+    if let Err(err) = VM::run_program(
+        &compiled.program,
+        compiled.objects,
+        &mut compiled.symbols,
+        &mut out,
+    ) {
+        reporter.global_run_time_error(&err.to_string());
+        return Err(reporter.diagnostics());
+    }
+
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile_str, run_str};
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use std::fmt::{Debug, Display};
+    use util::emmiter::Level;
+
+    /// A random arithmetic expression that is run on the vm and evaluated in rust
+    #[derive(Debug)]
+    enum Arith<T> {
+        Lit(T),
+        Neg(Box<Arith<T>>),
+        Bin(Box<Arith<T>>, char, Box<Arith<T>>),
+    }
+
+    impl<T: Copy + Debug> Arith<T> {
+        fn generate(rng: &mut XorShiftRng, depth: usize, lit: fn(&mut XorShiftRng) -> T) -> Self {
+            if depth == 0 || rng.gen_weighted_bool(4) {
+                return Arith::Lit(lit(rng));
+            }
+
+            if rng.gen_weighted_bool(5) {
+                return Arith::Neg(Box::new(Arith::generate(rng, depth - 1, lit)));
+            }
+
+            let op = *rng.choose(&['+', '-', '*', '/']).unwrap();
+
+            Arith::Bin(
+                Box::new(Arith::generate(rng, depth - 1, lit)),
+                op,
+                Box::new(Arith::generate(rng, depth - 1, lit)),
+            )
+        }
+
+        fn precedence(&self) -> u8 {
+            match *self {
+                Arith::Lit(_) => 4,
+                Arith::Neg(_) => 3,
+                Arith::Bin(_, '+', _) | Arith::Bin(_, '-', _) => 1,
+                Arith::Bin(..) => 2,
+            }
+        }
+
+        /// Writes the expression using only the parentheses needed to keep its shape so the
+        /// precedence of the parser is checked too
+        fn render(&self, out: &mut String, parent: u8, right: bool) {
+            let precedence = self.precedence();
+            let parens = precedence < parent || (precedence == parent && right);
+
+            if parens {
+                out.push('(');
+            }
+
+            match *self {
+                Arith::Lit(value) => out.push_str(&format!("{:?}", value)),
+                Arith::Neg(ref expr) => {
+                    out.push('-');
+                    expr.render(out, precedence, true);
+                }
+                Arith::Bin(ref lhs, op, ref rhs) => {
+                    lhs.render(out, precedence, false);
+                    out.push_str(&format!(" {} ", op));
+                    rhs.render(out, precedence, true);
+                }
+            }
+
+            if parens {
+                out.push(')');
+            }
+        }
+
+        fn source(&self) -> String {
+            let mut out = String::new();
+            self.render(&mut out, 0, false);
+            out
+        }
+    }
+
+    /// Evaluates the expression the way the vm should, returning the runtime error otherwise
+    fn eval_int(expr: &Arith<i64>) -> Result<i64, &'static str> {
+        const OVERFLOW: &str = "Integer overflow";
+
+        match *expr {
+            Arith::Lit(value) => Ok(value),
+            Arith::Neg(ref expr) => eval_int(expr)?.checked_neg().ok_or(OVERFLOW),
+            Arith::Bin(ref lhs, op, ref rhs) => {
+                let a = eval_int(lhs)?;
+                let b = eval_int(rhs)?;
+
+                let result = match op {
+                    '+' => a.checked_add(b),
+                    '-' => a.checked_sub(b),
+                    '*' => a.checked_mul(b),
+                    _ if b == 0 => return Err("Division by zero"),
+                    _ => a.checked_div(b),
+                };
+
+                result.ok_or(OVERFLOW)
+            }
+        }
+    }
+
+    fn eval_float(expr: &Arith<f64>) -> f64 {
+        match *expr {
+            Arith::Lit(value) => value,
+            Arith::Neg(ref expr) => -eval_float(expr),
+            Arith::Bin(ref lhs, op, ref rhs) => {
+                let a = eval_float(lhs);
+                let b = eval_float(rhs);
+
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ => a / b,
+                }
+            }
+        }
+    }
+
+    /// Checks that printing `expr` outputs `expected` or fails with the runtime error
+    fn assert_prints<T: Debug + Display>(expr: &str, expected: Result<T, &str>) {
+        let source = format!("fn main() {{ print {}; }}", expr);
+
+        match (run_str(&source), expected) {
+            (Ok(out), Ok(expected)) => assert_eq!(out, format!("{}\n", expected), "{}", expr),
+            (Err(diagnostics), Err(msg)) => assert!(
+                diagnostics.iter().any(|diagnostic| diagnostic.msg() == msg),
+                "`{}` failed with {:?} instead of {}",
+                expr,
+                diagnostics,
+                msg
+            ),
+            (out, expected) => panic!("`{}` gave {:?} expected {:?}", expr, out, expected),
+        }
+    }
+
+    #[test]
+    fn int_arithmetic_matches_rust() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        for _ in 0..200 {
+            let expr = Arith::generate(&mut rng, 4, |rng| {
+                if rng.gen_weighted_bool(8) {
+                    i64::max_value() - rng.gen_range(0, 10)
+                } else {
+                    rng.gen_range(0, 10)
+                }
+            });
+
+            assert_prints(&expr.source(), eval_int(&expr));
+        }
+    }
+
+    #[test]
+    fn float_arithmetic_matches_rust() {
+        let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+
+        for _ in 0..200 {
+            let expr = Arith::generate(&mut rng, 4, |rng| {
+                f64::from(rng.gen_range::<u16>(0, 400)) / 4.0
+            });
+
+            assert_prints(&expr.source(), Ok::<_, &str>(eval_float(&expr)));
+        }
+    }
+
+    #[test]
+    fn indexing_past_the_end_is_an_error() {
+        let msg = "Index out of bounds: the len is 3 but the index is 5";
+
+        assert_prints::<i64>("[1, 2, 3][5]", Err(msg));
+    }
+
+    #[test]
+    fn compiles_valid_source() {
+        let (program, diagnostics) = compile_str("fn main() { print 1; }");
+
+        assert!(program.is_some());
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.level() != Level::Error));
+    }
+
+    #[test]
+    fn reports_invalid_source() {
+        let (program, diagnostics) = compile_str("fn main() { print undefined; }");
+
+        assert!(program.is_none());
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.level() == Level::Error));
+    }
+
+    #[test]
+    fn rejects_unsupported_operators() {
+        for expr in ["7 % 2", "(7 as u8) % (2 as u8)", "2 ^ 3"].iter() {
+            let (program, diagnostics) = compile_str(&format!("fn main() {{ print {}; }}", expr));
+
+            assert!(program.is_none(), "{} compiled", expr);
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.msg().ends_with("operator is not supported")),
+                "{} gave {:?}",
+                expr,
+                diagnostics
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_source_is_reported() {
+        // the same inputs the fuzz target starts from
+        let inputs = [
+            "fn",
+            "fn main(",
+            "fn main() { print 1 +; }",
+            "fn main() { print \"unterminated; }",
+            "class { fn }",
+            "}}}",
+        ];
+
+        for source in inputs.iter() {
+            let (program, diagnostics) = compile_str(source);
+
+            assert!(program.is_none(), "{:?} compiled", source);
+            assert!(
+                diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.level() == Level::Error),
+                "{:?} had no errors",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn reports_codegen_warnings() {
+        let (program, diagnostics) = compile_str("fn main() { let x = 0; x = 1; x = 2; print x; }");
+
+        assert!(program.is_some());
+        assert!(diagnostics.iter().any(|diagnostic| {
+            diagnostic.level() == Level::Warn
+                && diagnostic.msg() == "value assigned to `x` is never read"
+        }));
+    }
+
+    #[test]
+    fn captures_printed_output() {
+        assert_eq!(run_str("fn main() { print 42; }").unwrap(), "42\n");
+    }
+
+    #[test]
+    fn strings_outlive_the_ast() {
+        let input = "
+            fn greeting() -> str {
+                defer print \"deferred\";
+                return \"hello\";
+            }
+
+            fn main() {
+                print greeting() + \" world\";
+            }
+        ";
+
+        assert_eq!(run_str(input).unwrap(), "deferred\nhello world\n");
+    }
+}
@@ -14,13 +14,19 @@ impl Infer {
 
         let mut poly_tvs = Vec::with_capacity(alias.value.alias.value.type_params.len());
 
+        ctx.begin_scope(); // the type params are only visible in the aliased type
+
         for ident in &alias.value.alias.value.type_params {
             let tv = TypeVar::new();
             ctx.add_type(ident.value, Type::Var(tv));
             poly_tvs.push(tv);
         }
 
-        let entry = Type::Generic(poly_tvs, Box::new(self.trans_type(&alias.value.ty, ctx)?));
+        let ty = self.trans_type(&alias.value.ty, ctx);
+
+        ctx.end_scope();
+
+        let entry = Type::Generic(poly_tvs, Box::new(ty?));
 
         ctx.add_type(alias.value.alias.value.name.value, entry);
 
@@ -1,5 +1,6 @@
 use crate::ast as t;
 use crate::ctx::CompileCtx;
+use crate::infer::env::VarEntry;
 
 use crate::infer::types::{Method, Property, Type, TypeVar, Unique};
 use crate::infer::{Infer, InferResult};
@@ -103,7 +104,23 @@ impl Infer {
             ),
         ); // Ensures that if the class returned from a function has the right number of properties
 
+        // methods can refer to their instance with `self`
+        let instance = ctx.look_type(class.value.name.value.name.value).cloned().unwrap();
+        ctx.add_var(t::SELF, VarEntry::Var(instance));
+
         for method in class.value.methods {
+            for param in method.value.params.value.iter() {
+                if let Some(ref default) = param.value.default {
+                    ctx.error("Methods can't have default values", default.span);
+                    return Err(());
+                }
+
+                if param.value.rest {
+                    ctx.error("Methods can't have a rest param", param.span);
+                    return Err(());
+                }
+            }
+
             let fun = self.infer_function(method, ctx)?;
             let mut types: Vec<Type> = fun
                 .params
@@ -20,6 +20,10 @@ impl Infer {
 
             Expression::Binary { lhs, op, rhs } => self.infer_binary(*lhs, op, *rhs, expr.span, ctx),
 
+            Expression::Block { statements, value } => {
+                self.infer_block(statements, *value, expr.span, ctx)
+            }
+
             Expression::Call(call) => {
                 let whole_span = expr.span;
                 match call.value {
@@ -27,7 +31,8 @@ impl Infer {
                         types,
                         callee,
                         args,
-                    } => self.infer_call(*callee, args, types, whole_span, ctx),
+                        named,
+                    } => self.infer_call(*callee, args, named, types, whole_span, ctx),
                 }
             }
 
@@ -85,11 +90,21 @@ impl Infer {
 
             Expression::Literal(literal) => self.infer_literal(literal, expr.span),
 
+            Expression::Loop { body, label } => self.infer_loop(*body, label, expr.span, ctx),
+
             Expression::Set {
                 object,
                 name,
                 value,
-            } => self.infer_set(*object, name, *value, expr.span, ctx),
+                kind,
+            } => self.infer_set(*object, name, kind, *value, expr.span, ctx),
+
+            Expression::SetSubScript {
+                target,
+                index,
+                value,
+                kind,
+            } => self.infer_set_subscript(*target, *index, kind, *value, expr.span, ctx),
 
             Expression::Ternary {
                 condition,
@@ -97,6 +112,14 @@ impl Infer {
                 else_branch,
             } => self.infer_ternary(*condition, *then_branch, *else_branch, expr.span, ctx),
 
+            Expression::Try { expr: inner } => self.infer_try(*inner, expr.span, ctx),
+
+            Expression::Tuple { items } => self.infer_tuple(items, expr.span, ctx),
+
+            Expression::TupleIndex { tuple, index } => {
+                self.infer_tuple_index(*tuple, index, expr.span, ctx)
+            }
+
             Expression::Unary { expr: operand, op } => {
                 self.infer_unary(op, *operand, expr.span, ctx)
             }
@@ -52,4 +52,30 @@ impl Infer {
             whole_span,
         ))
     }
+
+    /// Checks that the compound assignment `kind` can be used on a value of type `ty`
+    /// i.e `+=` can be used on a str but `-=` can't
+    pub(crate) fn check_compound_assign(
+        &self,
+        kind: &Spanned<AssignOperator>,
+        ty: &Type,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<()> {
+        match (&kind.value, ty) {
+            (AssignOperator::Equal, _)
+            | (_, Type::App(TypeCon::Int, _))
+            | (_, Type::App(TypeCon::SizedInt(_), _))
+            | (_, Type::App(TypeCon::Float, _))
+            | (AssignOperator::PlusEqual, Type::App(TypeCon::Str, _)) => Ok(()),
+            _ => {
+                let msg = format!(
+                    "Cannot use a compound assignment on a value of type `{}`",
+                    ty.print(ctx.symbols())
+                );
+
+                ctx.error(msg, kind.span);
+                Err(())
+            }
+        }
+    }
 }
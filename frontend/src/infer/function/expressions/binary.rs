@@ -44,7 +44,17 @@ impl Infer {
                 )
             }
 
-            Op::Plus | Op::Slash | Op::Star | Op::Minus | Op::Modulo | Op::Exponential => {
+            // There are no instructions for these yet so reject them here rather than
+            // letting codegen find out
+            Op::Modulo | Op::Exponential => {
+                let op = if op.value == Op::Modulo { "%" } else { "^" };
+                let msg = format!("The `{}` operator is not supported", op);
+
+                ctx.error(msg, whole_span);
+                return Err(());
+            }
+
+            Op::Plus | Op::Slash | Op::Star | Op::Minus => {
                 match self.unify(&lhs.value.ty, &rhs.value.ty, span, ctx) {
                     Ok(()) => (),
                     Err(_) => {
@@ -0,0 +1,40 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+use crate::infer::{Infer, InferResult};
+use syntax::ast::{Expression, Statement};
+use util::pos::{Span, Spanned};
+
+impl Infer {
+    pub(crate) fn infer_block(
+        &mut self,
+        statements: Vec<Spanned<Statement>>,
+        value: Spanned<Expression>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        ctx.begin_scope();
+
+        let mut typed_statements = Vec::with_capacity(statements.len());
+
+        for statement in statements {
+            typed_statements.push(self.infer_statement(statement, ctx)?);
+        }
+
+        let value = self.infer_expr(value, ctx)?;
+
+        ctx.end_scope();
+
+        let ty = value.value.ty.clone();
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(
+                    t::Expression::Block(typed_statements, value),
+                    whole_span,
+                )),
+                ty,
+            },
+            whole_span,
+        ))
+    }
+}
@@ -1,34 +1,27 @@
 use crate::ast as t;
 use crate::ctx::CompileCtx;
+use crate::infer::env::VarEntry;
 use crate::infer::types;
-use crate::infer::{Infer, InferResult};
+use crate::infer::{Infer, InferResult, Signature};
 use std::collections::HashMap;
-use syntax::ast::{Call, Expression, Type};
+use syntax::ast::{Expression, NamedArg, Type};
 use util::pos::{Span, Spanned};
+use util::symbol::Symbol;
 
 impl Infer {
     pub(crate) fn infer_call(
         &mut self,
         callee: Spanned<Expression>,
-        args: Vec<Spanned<Expression>>,
+        mut args: Vec<Spanned<Expression>>,
+        named: Vec<NamedArg>,
         types: Spanned<Vec<Spanned<Type>>>,
         whole_span: Span,
         ctx: &mut CompileCtx,
     ) -> InferResult<Spanned<t::TypedExpression>> {
-        match callee.value {
-            Expression::Call(call) => {
-                let whole_span = call.span;
-
-                match call.value {
-                    Call {
-                        types,
-                        callee,
-                        args,
-                    } => self.infer_call(*callee, args, types, whole_span, ctx),
-                }
-            }
+        let callee_span = callee.span;
 
-            Expression::Var(ref symbol) => {
+        match callee.value {
+            Expression::Var(symbol) => {
                 let func = if let Some(func) = ctx.look_var(symbol.value).cloned() {
                     func
                 } else {
@@ -39,91 +32,73 @@ impl Infer {
                     return Err(());
                 };
 
-                match func.get_ty() {
-                    types::Type::Generic(ref typevars, ref ty) => match **ty {
-                        types::Type::App(types::TypeCon::Arrow, ref func_types) => {
-                            if func_types.len() - 1 != args.len() {
-                                // minus one because the return type is stored along with the argument types
+                let ty = match func {
+                    VarEntry::Fun { ty } => ty,
+                    VarEntry::Var(ty) => {
+                        if function_types(ty).is_none() {
+                            let msg = format!("`{}` is not callable", ctx.name(symbol.value));
 
-                                let msg = format!(
-                                    "Expected `{}` args found `{}` ",
-                                    func_types.len() - 1,
-                                    args.len()
-                                );
-                                ctx.error(msg, whole_span);
-                                return Err(());
-                            }
+                            ctx.error(msg, callee_span);
+                            return Err(());
+                        }
 
-                            let mut mappings = HashMap::new();
+                        positional_only(&named, ctx)?;
 
-                            let mut arg_types = Vec::new();
+                        // A function stored in a variable or passed as a param
+                        let callee = Spanned::new(Expression::Var(symbol), callee_span);
+                        return self.infer_call_value(callee, args, types, whole_span, ctx);
+                    }
+                };
 
-                            for (ty, type_var) in types.value.into_iter().zip(typevars.iter()) {
-                                mappings.insert(*type_var, self.trans_type(&ty, ctx)?);
-                            }
+                let (typevars, mut func_types) = function_types(ty).unwrap(); // Only other possible generic types are structs. Structs are stored in a different environment and they it cannot be a struct
 
-                            struct CallExpression {
-                                expr: Spanned<t::TypedExpression>,
-                                ty: types::Type,
-                            };
+                let signature = self.signatures.get(&symbol.value).cloned().unwrap_or_default();
+                let params = func_types.len() - 1; // the return type is stored with the params
 
-                            for arg in args {
-                                let span = arg.span;
-                                let typed_expr = self.infer_expr(arg, ctx)?;
-                                let ty = typed_expr.value.ty.clone();
+                if !named.is_empty() {
+                    args = order_named_args(&symbol, &signature, args, named, ctx)?;
+                }
 
-                                arg_types.push(Spanned {
-                                    value: CallExpression {
-                                        expr: typed_expr,
-                                        ty,
-                                    },
-                                    span,
-                                });
-                            }
+                let defaults = signature.defaults;
 
-                            for (call_expression, def_type) in arg_types.iter_mut().zip(func_types)
-                            {
-                                self.unify(
-                                    &self.subst(def_type, &mut mappings),
-                                    &self.subst(&call_expression.value.ty, &mut mappings),
-                                    call_expression.span,
-                                    ctx,
-                                )?;
+                // Trailing params with a default value can be left out of the call
+                let missing = params.saturating_sub(args.len());
+                let missing = if missing <= defaults.len() { missing } else { 0 };
 
-                                call_expression.value.ty =
-                                    self.subst(&call_expression.value.ty, &mut mappings);
-                            }
+                func_types.drain(params - missing..params);
 
-                            Ok(Spanned {
-                                value: t::TypedExpression {
-                                    expr: Box::new(Spanned {
-                                        value: t::Expression::Call(
-                                            symbol.value,
-                                            arg_types
-                                                .into_iter()
-                                                .map(|arg| arg.value.expr)
-                                                .collect(),
-                                        ),
-                                        span: whole_span,
-                                    }),
-                                    ty: self.subst(func_types.last().unwrap(), &mut mappings),
-                                },
-                                span: whole_span,
-                            })
-                        }
+                // The arguments after the fixed params are collected into an array
+                let rest = if signature.rest && args.len() + 1 >= params {
+                    let rest_ty = func_types.remove(params - 1);
+                    Some((rest_ty, args.split_off(params - 1)))
+                } else {
+                    None
+                };
 
-                        _ => unreachable!(), // Only other possible generic types are structs. Structs are stored in a different environment and they it cannot be a struct
-                    },
-                    _ => {
-                        let msg = format!("`{}` is not callable", ctx.name(symbol.value));
+                let (mut args, returns) =
+                    self.infer_args(&typevars, &func_types, args, types, whole_span, ctx)?;
 
-                        ctx.error(msg, callee.span);
-                        Err(())
-                    }
+                args.extend(defaults[defaults.len() - missing..].iter().cloned());
+
+                if let Some((rest_ty, rest)) = rest {
+                    args.push(self.infer_rest_args(rest_ty, rest, whole_span, ctx)?);
                 }
+
+                Ok(Spanned {
+                    value: t::TypedExpression {
+                        expr: Box::new(Spanned {
+                            value: t::Expression::Call(symbol.value, args),
+                            span: whole_span,
+                        }),
+                        ty: returns,
+                    },
+                    span: whole_span,
+                })
             }
 
             Expression::Get { object, property } => {
+                positional_only(&named, ctx)?;
+
                 let expression = self.infer_get(*object, property, whole_span, ctx)?;
 
                 match expression.value.ty {
@@ -190,9 +165,12 @@ impl Infer {
                                     method_name,
                                     method,
                                 } => match method.value.expr.value {
-                                    t::Expression::Var(_, types::Type::Generic(_, ref ty)) => {
+                                    t::Expression::Var(receiver, types::Type::Generic(_, ref ty)) => {
                                         match **ty {
-                                            types::Type::Class(class_name, _, _, _) => {
+                                            // Called on the class itself rather than on an instance
+                                            types::Type::Class(class_name, _, _, _)
+                                                if receiver == class_name =>
+                                            {
                                                 Ok(Spanned {
                                                     value: t::TypedExpression {
                                                         expr: Box::new(Spanned {
@@ -276,35 +254,261 @@ impl Infer {
                 }
             }
 
-            Expression::Closure(function) => {
-                let closure = self.infer_function(*function, ctx)?;
-                let mut params: Vec<types::Type> = closure
-                    .params
-                    .iter()
-                    .map(|param| param.ty.clone())
-                    .collect();
-                params.push(closure.returns.clone());
-                let ty = types::Type::Generic(
-                    vec![],
-                    Box::new(types::Type::App(types::TypeCon::Arrow, params)),
-                );
+            callee => {
+                positional_only(&named, ctx)?;
 
-                Ok(Spanned {
-                    value: t::TypedExpression {
-                        expr: Box::new(Spanned::new(
-                            t::Expression::Closure(Box::new(closure)),
-                            whole_span,
-                        )),
-                        ty,
-                    },
-                    span: whole_span,
-                })
+                let callee = Spanned::new(callee, callee_span);
+                self.infer_call_value(callee, args, types, whole_span, ctx)
             }
+        }
+    }
+
+    /// Infers a call where the callee is an expression that evaluates to a function
+    fn infer_call_value(
+        &mut self,
+        callee: Spanned<Expression>,
+        args: Vec<Spanned<Expression>>,
+        types: Spanned<Vec<Spanned<Type>>>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let callee = self.infer_expr(callee, ctx)?;
 
-            _ => {
+        let (typevars, func_types) = match function_types(callee.value.ty.clone()) {
+            Some(function_types) => function_types,
+            None => {
                 ctx.error("Not callable", whole_span);
-                Err(())
+                return Err(());
+            }
+        };
+
+        let (args, returns) =
+            self.infer_args(&typevars, &func_types, args, types, whole_span, ctx)?;
+
+        Ok(Spanned {
+            value: t::TypedExpression {
+                expr: Box::new(Spanned {
+                    value: t::Expression::CallValue(callee, args),
+                    span: whole_span,
+                }),
+                ty: returns,
+            },
+            span: whole_span,
+        })
+    }
+
+    /// Infers the arguments passed to a function with the type `fn(func_types)`.
+    /// Returns the typed arguments and the return type of the call
+    /// Infers the arguments passed to a rest param building the array they are collected into
+    fn infer_rest_args(
+        &mut self,
+        rest_ty: types::Type,
+        args: Vec<Spanned<Expression>>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let item_ty = match rest_ty {
+            types::Type::App(types::TypeCon::Array(ref item_ty), _) => (**item_ty).clone(),
+            _ => unreachable!(), // rest params are always arrays
+        };
+
+        let span = match (args.first(), args.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => whole_span,
+        };
+
+        let mut items = Vec::with_capacity(args.len());
+
+        for arg in args {
+            let span = arg.span;
+            let item = self.infer_expr(arg, ctx)?;
+
+            self.unify(&item_ty, &item.value.ty, span, ctx)?;
+            items.push(item);
+        }
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(t::Expression::Array(items), span)),
+                ty: rest_ty,
+            },
+            span,
+        ))
+    }
+
+    fn infer_args(
+        &mut self,
+        typevars: &[types::TypeVar],
+        func_types: &[types::Type],
+        args: Vec<Spanned<Expression>>,
+        types: Spanned<Vec<Spanned<Type>>>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<(Vec<Spanned<t::TypedExpression>>, types::Type)> {
+        if func_types.len() - 1 != args.len() {
+            // minus one because the return type is stored along with the argument types
+
+            let msg = format!(
+                "Expected `{}` args found `{}` ",
+                func_types.len() - 1,
+                args.len()
+            );
+            ctx.error(msg, whole_span);
+            return Err(());
+        }
+
+        let mut mappings = HashMap::new();
+
+        let mut arg_types = Vec::new();
+
+        for (ty, type_var) in types.value.into_iter().zip(typevars.iter()) {
+            mappings.insert(*type_var, self.trans_type(&ty, ctx)?);
+        }
+
+        struct CallExpression {
+            expr: Spanned<t::TypedExpression>,
+            ty: types::Type,
+        };
+
+        for arg in args {
+            let span = arg.span;
+            let typed_expr = self.infer_expr(arg, ctx)?;
+            let ty = typed_expr.value.ty.clone();
+
+            arg_types.push(Spanned {
+                value: CallExpression {
+                    expr: typed_expr,
+                    ty,
+                },
+                span,
+            });
+        }
+
+        for (call_expression, def_type) in arg_types.iter().zip(func_types) {
+            bind_typevars(def_type, &call_expression.value.ty, typevars, &mut mappings);
+        } // type params that were not given explicitly are taken from the arguments
+
+        for (call_expression, def_type) in arg_types.iter_mut().zip(func_types) {
+            self.unify(
+                &self.subst(def_type, &mut mappings),
+                &self.subst(&call_expression.value.ty, &mut mappings),
+                call_expression.span,
+                ctx,
+            )?;
+
+            call_expression.value.ty = self.subst(&call_expression.value.ty, &mut mappings);
+        }
+
+        Ok((
+            arg_types.into_iter().map(|arg| arg.value.expr).collect(),
+            self.subst(func_types.last().unwrap(), &mut mappings),
+        ))
+    }
+}
+
+/// Moves the arguments passed by name into the position of their param
+fn order_named_args(
+    function: &Spanned<Symbol>,
+    signature: &Signature,
+    args: Vec<Spanned<Expression>>,
+    named: Vec<NamedArg>,
+    ctx: &mut CompileCtx,
+) -> InferResult<Vec<Spanned<Expression>>> {
+    let mut slots: Vec<Option<Spanned<Expression>>> = args.into_iter().map(Some).collect();
+
+    if slots.len() < signature.params.len() {
+        slots.resize(signature.params.len(), None);
+    }
+
+    for arg in named {
+        match signature.params.iter().position(|param| *param == arg.name.value) {
+            Some(position) if slots[position].is_none() => slots[position] = Some(arg.value),
+            Some(_) => {
+                let msg = format!("`{}` is given more than once", ctx.name(arg.name.value));
+                ctx.error(msg, arg.name.span);
+                return Err(());
+            }
+            None => {
+                let msg = format!(
+                    "`{}` has no param named `{}`",
+                    ctx.name(function.value),
+                    ctx.name(arg.name.value)
+                );
+                ctx.error(msg, arg.name.span);
+                return Err(());
+            }
+        }
+    }
+
+    // Params with a default value or a rest param can be left out if nothing follows them
+    let optional = signature.defaults.len() + usize::from(signature.rest);
+    let required = signature.params.len() - optional;
+    let given = slots.iter().rposition(Option::is_some).map_or(0, |last| last + 1);
+
+    for (param, slot) in signature.params.iter().zip(&slots).take(required.max(given)) {
+        if slot.is_none() {
+            let msg = format!("Missing an argument for `{}`", ctx.name(*param));
+            ctx.error(msg, function.span);
+            return Err(());
+        }
+    }
+
+    Ok(slots.into_iter().flatten().collect())
+}
+
+/// Reports an error if arguments were passed by name to something other than a declared function
+fn positional_only(named: &[NamedArg], ctx: &mut CompileCtx) -> InferResult<()> {
+    match named.first() {
+        Some(arg) => {
+            let msg = "Arguments can only be passed by name to a declared function";
+            ctx.error(msg, arg.name.span);
+            Err(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Splits the type of a function into its type params and the types of
+/// its params and return type. Returns `None` if the type is not a function
+fn function_types(ty: types::Type) -> Option<(Vec<types::TypeVar>, Vec<types::Type>)> {
+    match ty {
+        types::Type::Generic(typevars, ty) => match *ty {
+            types::Type::App(types::TypeCon::Arrow, func_types) => Some((typevars, func_types)),
+            _ => None,
+        },
+        types::Type::App(types::TypeCon::Arrow, func_types) => Some((vec![], func_types)),
+        _ => None,
+    }
+}
+
+/// Binds the type params of a generic function that were not given explicitly
+/// by matching the declared type of a param against the type of the argument
+fn bind_typevars(
+    def_type: &types::Type,
+    arg_type: &types::Type,
+    typevars: &[types::TypeVar],
+    mappings: &mut HashMap<types::TypeVar, types::Type>,
+) {
+    match (def_type, arg_type) {
+        (types::Type::Var(typevar), _) if typevars.contains(typevar) => {
+            mappings.entry(*typevar).or_insert_with(|| arg_type.clone());
+        }
+
+        (_, types::Type::Generic(_, arg_type)) => {
+            bind_typevars(def_type, arg_type, typevars, mappings)
+        }
+
+        (
+            types::Type::App(types::TypeCon::Array(def_type), _),
+            types::Type::App(types::TypeCon::Array(arg_type), _),
+        ) => bind_typevars(def_type, arg_type, typevars, mappings),
+
+        (types::Type::App(_, def_types), types::Type::App(_, arg_types)) => {
+            for (def_type, arg_type) in def_types.iter().zip(arg_types) {
+                bind_typevars(def_type, arg_type, typevars, mappings)
             }
         }
+
+        _ => (),
     }
 }
@@ -42,6 +42,39 @@ impl Infer {
                 types::Type::App(types::TypeCon::Int, _),
             ) => {}
 
+            (
+                types::Type::App(types::TypeCon::Char, _),
+                types::Type::App(types::TypeCon::Int, _),
+            ) => {}
+
+            (
+                types::Type::App(types::TypeCon::Int, _),
+                types::Type::App(types::TypeCon::Char, _),
+            ) => {}
+
+            (
+                types::Type::App(types::TypeCon::Char, _),
+                types::Type::App(types::TypeCon::Str, _),
+            ) => {}
+
+            // checked at runtime when the value could be truncated
+            (from, types::Type::App(types::TypeCon::SizedInt(_), _)) if from.is_integer() => {}
+
+            (
+                types::Type::App(types::TypeCon::SizedInt(_), _),
+                types::Type::App(types::TypeCon::Int, _),
+            ) => {}
+
+            (
+                types::Type::App(types::TypeCon::SizedInt(_), _),
+                types::Type::App(types::TypeCon::Float, _),
+            ) => {}
+
+            (
+                types::Type::App(types::TypeCon::SizedInt(_), _),
+                types::Type::App(types::TypeCon::Str, _),
+            ) => {}
+
             (lhs, rhs) => {
                 let msg = format!(
                     "Cannot cast `{}` to type `{}`",
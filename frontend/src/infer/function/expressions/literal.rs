@@ -17,6 +17,8 @@ impl Infer {
 
             Literal::Str(_) => Type::App(TypeCon::Str, vec![]),
 
+            Literal::Char(_) => Type::App(TypeCon::Char, vec![]),
+
             Literal::Nil => Type::Nil, // Nil is given the type void as only statements return Nil
 
             Literal::Int(_) => Type::App(TypeCon::Int, vec![]),
@@ -0,0 +1,83 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+use crate::infer::types::Type;
+use crate::infer::{Infer, InferResult, LoopScope};
+use syntax::ast::{Expression, Statement};
+use util::pos::{Span, Spanned};
+use util::symbol::Symbol;
+
+impl Infer {
+    pub(crate) fn begin_loop(&mut self, label: Option<Symbol>, is_loop: bool) {
+        self.loops.push(LoopScope {
+            label,
+            is_loop,
+            value: None,
+        });
+    }
+
+    /// A `loop` has the type of the values given to its `break`s
+    pub(crate) fn infer_loop(
+        &mut self,
+        body: Spanned<Statement>,
+        label: Option<Spanned<Symbol>>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let label = label.map(|l| l.value);
+
+        self.begin_loop(label, true);
+
+        let body = self.infer_statement(body, ctx)?;
+
+        let ty = self.loops.pop().unwrap().value.unwrap_or(Type::Nil);
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(t::Expression::Loop(body, label), whole_span)),
+                ty,
+            },
+            whole_span,
+        ))
+    }
+
+    /// Infers the value given to a `break` checking it against the other `break`s of the loop
+    pub(crate) fn infer_break(
+        &mut self,
+        label: Option<Symbol>,
+        value: Option<Spanned<Expression>>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Option<Spanned<t::TypedExpression>>> {
+        let index = self
+            .loops
+            .iter()
+            .rposition(|l| label.is_none() || l.label == label);
+
+        let index = match (index, value.is_some()) {
+            (Some(index), _) if self.loops[index].is_loop => index,
+            (_, false) => return Ok(None),
+            (_, true) => {
+                ctx.error("Only a `loop` can break with a value", whole_span);
+                return Err(());
+            }
+        };
+
+        let (value, ty, span) = match value {
+            Some(value) => {
+                let span = value.span;
+                let value = self.infer_expr(value, ctx)?;
+                let ty = value.value.ty.clone();
+
+                (Some(value), ty, span)
+            }
+            None => (None, Type::Nil, whole_span),
+        };
+
+        match self.loops[index].value.clone() {
+            Some(expected) => self.unify(&expected, &ty, span, ctx)?,
+            None => self.loops[index].value = Some(ty),
+        }
+
+        Ok(value)
+    }
+}
@@ -1,16 +1,21 @@
 mod array;
 mod assign;
 mod binary;
+mod block;
 mod call;
 mod cast;
 mod class_literal;
 mod get;
 mod grouping;
 mod literal;
+mod loops;
 mod pattern;
 mod set;
+mod set_subscript;
 mod subscript;
 mod ternary;
+mod try_op;
+mod tuple;
 mod unary;
 mod var;
 mod variant;
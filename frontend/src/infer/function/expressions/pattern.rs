@@ -1,9 +1,10 @@
 use crate::ast as t;
 use crate::ctx::CompileCtx;
 
-use crate::infer::types::Type;
+use crate::infer::env::VarEntry;
+use crate::infer::types::{Type, TypeCon};
 use crate::infer::{Infer, InferResult};
-use syntax::ast::{Expression, MatchArm};
+use syntax::ast::{Expression, MatchArm, Pattern};
 use util::pos::{Span, Spanned};
 
 impl Infer {
@@ -21,72 +22,50 @@ impl Infer {
         let mut return_type = Type::Nil; // Default return type is nill
 
         let mut typed_arms = Vec::new();
-        let arms_span = arms.span;
-        let mut arms = arms.value;
-
-        if !arms.is_empty() {
-            let first = arms.remove(0);
-            let first_span = first.span;
-
-            if first.value.pattern.is_none() {
-                let first_body = self.infer_statement(first.value.body, ctx)?;
-                return_type = first_body.value.ty.clone();
-
-                typed_arms.push(Spanned::new(
-                    t::MatchArm {
-                        pattern: None,
-                        body: first_body,
-                        is_all: true,
-                    },
-                    first_span,
-                ));
-            } else {
-                let first_pattern = self.infer_expr(first.value.pattern.unwrap(), ctx)?;
-                let first_body = self.infer_statement(first.value.body, ctx)?;
 
-                return_type = first_body.value.ty.clone();
+        for (i, arm) in arms.value.into_iter().enumerate() {
+            ctx.begin_scope(); // a binding pattern is only visible in its own arm
 
-                typed_arms.push(Spanned::new(
-                    t::MatchArm {
-                        pattern: Some(first_pattern),
-                        body: first_body,
-                        is_all: false,
-                    },
-                    first_span,
-                ));
+            let pattern = self.infer_pattern(arm.value.pattern, &pattern_type, ctx)?;
+            let guard = self.infer_guard(arm.value.guard, ctx)?;
+            let body = self.infer_statement(arm.value.body, ctx)?;
+
+            ctx.end_scope();
+
+            if i == 0 {
+                return_type = body.value.ty.clone();
+            } else {
+                self.unify(&body.value.ty, &return_type, body.span, ctx)?;
             }
 
-            for arm in arms {
-                let span = arm.span;
-                let is_all = arm.value.is_all;
-
-                if is_all {
-                    typed_arms.push(Spanned::new(
-                        t::MatchArm {
-                            pattern: None,
-                            body: self.infer_statement(arm.value.body, ctx)?,
-                            is_all: true,
-                        },
-                        span,
-                    ));
-                } else {
-                    let lhs = self.infer_expr(arm.value.pattern.unwrap(), ctx)?; // unwrap
-
-                    self.unify(&lhs.value.ty, &pattern_type, lhs.span, ctx)?;
-
-                    let rhs = self.infer_statement(arm.value.body, ctx)?;
-
-                    self.unify(&rhs.value.ty, &return_type, rhs.span, ctx)?;
-
-                    typed_arms.push(Spanned::new(
-                        t::MatchArm {
-                            pattern: Some(lhs),
-                            body: rhs,
-                            is_all: false,
-                        },
-                        span,
-                    ));
-                }
+            typed_arms.push(Spanned::new(
+                t::MatchArm {
+                    pattern,
+                    guard,
+                    body,
+                    is_all: arm.value.is_all,
+                },
+                arm.span,
+            ));
+        }
+
+        if let Some((name, variants)) = cond.value.ty.as_enum() {
+            let mut missing = variants
+                .iter()
+                .filter(|&(_, variant)| !typed_arms.iter().any(|arm| covers(arm, variant.tag)))
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                missing.sort_by_key(|&(_, variant)| variant.tag);
+
+                let missing = missing
+                    .iter()
+                    .map(|&(variant, _)| format!("`{}::{}`", ctx.name(name), ctx.name(*variant)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                ctx.error(format!("Non-exhaustive match, missing {}", missing), whole_span);
+                return Err(());
             }
         }
 
@@ -95,7 +74,7 @@ impl Infer {
                 expr: Box::new(Spanned::new(
                     t::Expression::Match {
                         cond,
-                        arms: Spanned::new(typed_arms, arms_span),
+                        arms: Spanned::new(typed_arms, arms.span),
                     },
                     whole_span,
                 )),
@@ -104,4 +83,168 @@ impl Infer {
             whole_span,
         ))
     }
+
+    fn infer_pattern(
+        &mut self,
+        pattern: Spanned<Pattern>,
+        ty: &Type,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::Pattern>> {
+        let typed = match pattern.value {
+            Pattern::Expr(expr) => {
+                let expr = self.infer_expr(expr, ctx)?;
+
+                self.unify(&expr.value.ty, ty, expr.span, ctx)?;
+
+                t::Pattern::Expr(expr)
+            }
+
+            Pattern::Range(start, end) => {
+                if !ty.is_integer() {
+                    let msg = format!("Range patterns can't match `{}`", ty.print(ctx.symbols()));
+                    ctx.error(msg, pattern.span);
+                    return Err(());
+                }
+
+                let start = self.infer_expr(start, ctx)?;
+                let end = self.infer_expr(end, ctx)?;
+
+                self.unify(&start.value.ty, ty, start.span, ctx)?;
+                self.unify(&end.value.ty, ty, end.span, ctx)?;
+
+                t::Pattern::Range(start, end)
+            }
+
+            Pattern::Or(patterns) => {
+                let mut typed = Vec::with_capacity(patterns.len());
+
+                for pattern in patterns {
+                    typed.push(self.infer_pattern(pattern, ty, ctx)?);
+                }
+
+                t::Pattern::Or(typed)
+            }
+
+            Pattern::Wildcard => t::Pattern::Wildcard,
+
+            Pattern::Bind(name) => {
+                ctx.add_var(name.value, VarEntry::Var(ty.clone()));
+
+                t::Pattern::Bind(name.value)
+            }
+
+            Pattern::Variant {
+                enum_name,
+                variant,
+                inner,
+            } => {
+                let variants = match ty.as_enum() {
+                    Some((name, variants)) if name == enum_name.value => variants,
+                    _ => {
+                        let msg = format!(
+                            "Expected a pattern of type `{}` found a `{}` variant",
+                            ty.print(ctx.symbols()),
+                            ctx.name(enum_name.value)
+                        );
+                        ctx.error(msg, pattern.span);
+                        return Err(());
+                    }
+                };
+
+                let variant_ty = match variants.get(&variant.value) {
+                    Some(variant_ty) => variant_ty.clone(),
+                    None => {
+                        let msg = format!("Unknown enum variant `{}`", ctx.name(variant.value));
+                        ctx.error(msg, variant.span);
+                        return Err(());
+                    }
+                };
+
+                let inner = match (inner, variant_ty.inner) {
+                    (Some(inner), Some(inner_ty)) => {
+                        Some(Box::new(self.infer_pattern(*inner, &inner_ty, ctx)?))
+                    }
+                    (None, None) => None,
+                    (Some(_), None) => {
+                        let msg = format!(
+                            "The variant `{}` dosen't store any data",
+                            ctx.name(variant.value)
+                        );
+                        ctx.error(msg, pattern.span);
+                        return Err(());
+                    }
+                    (None, Some(inner_ty)) => {
+                        let msg = format!(
+                            "The variant `{}` should store the data of type {}",
+                            ctx.name(variant.value),
+                            inner_ty.print(ctx.symbols())
+                        );
+                        ctx.error(msg, pattern.span);
+                        return Err(());
+                    }
+                };
+
+                t::Pattern::Variant {
+                    tag: variant_ty.tag,
+                    inner,
+                }
+            }
+        };
+
+        Ok(Spanned::new(typed, pattern.span))
+    }
+
+    fn infer_guard(
+        &mut self,
+        guard: Option<Spanned<Expression>>,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Option<Spanned<t::TypedExpression>>> {
+        match guard {
+            Some(guard) => {
+                let span = guard.span;
+                let guard = self.infer_expr(guard, ctx)?;
+
+                self.unify(&Type::App(TypeCon::Bool, vec![]), &guard.value.ty, span, ctx)?;
+
+                Ok(Some(guard))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether the arm matches every value of the variant with the given tag
+fn covers(arm: &Spanned<t::MatchArm>, tag: u32) -> bool {
+    if arm.value.guard.is_some() {
+        return false;
+    }
+
+    arm.value.is_all || pattern_covers(&arm.value.pattern, tag)
+}
+
+fn pattern_covers(pattern: &Spanned<t::Pattern>, tag: u32) -> bool {
+    match pattern.value {
+        t::Pattern::Expr(ref expr) => match expr.value.expr.value {
+            t::Expression::VariantNoData {
+                tag: pattern_tag, ..
+            } => pattern_tag == tag,
+            _ => false,
+        },
+        t::Pattern::Range(_, _) => false,
+        t::Pattern::Wildcard | t::Pattern::Bind(_) => true,
+        t::Pattern::Or(ref patterns) => patterns.iter().any(|pattern| pattern_covers(pattern, tag)),
+        t::Pattern::Variant {
+            tag: pattern_tag,
+            ref inner,
+        } => pattern_tag == tag && inner.as_ref().map_or(true, |inner| irrefutable(inner)),
+    }
+}
+
+/// Whether the pattern matches every value of its type
+fn irrefutable(pattern: &Spanned<t::Pattern>) -> bool {
+    match pattern.value {
+        t::Pattern::Wildcard | t::Pattern::Bind(_) => true,
+        t::Pattern::Or(ref patterns) => patterns.iter().any(irrefutable),
+        _ => false,
+    }
 }
@@ -3,7 +3,7 @@ use crate::ctx::CompileCtx;
 
 use crate::infer::types::Type;
 use crate::infer::{Infer, InferResult};
-use syntax::ast::Expression;
+use syntax::ast::{AssignOperator, Expression};
 use util::pos::{Span, Spanned};
 use util::symbol::Symbol;
 
@@ -12,95 +12,84 @@ impl Infer {
         &mut self,
         object: Spanned<Expression>,
         property: Spanned<Symbol>,
+        kind: Spanned<AssignOperator>,
         value: Spanned<Expression>,
         whole_span: Span,
         ctx: &mut CompileCtx,
     ) -> InferResult<Spanned<t::TypedExpression>> {
         let class_instance = self.infer_expr(object, ctx)?;
 
-        match class_instance.value.ty.clone() {
-            Type::Class(ref name, _, _, _) => {
-                if let Some(ty) = ctx.look_type(*name).cloned() {
-                    match ty {
-                        Type::Class(_, ref propertys, ref methods, _) => {
-                            let value_span = value.span;
-                            let value_ty = self.infer_expr(value, ctx)?;
-
-                            for property_type in propertys {
-                                if property_type.name == property.value {
-                                    self.unify(
-                                        &value_ty.value.ty,
-                                        &property_type.ty,
-                                        value_span,
-                                        ctx,
-                                    )?;
-                                    return Ok(Spanned::new(
-                                        t::TypedExpression {
-                                            expr: Box::new(Spanned::new(
-                                                t::Expression::Set(
-                                                    property.value,
-                                                    class_instance,
-                                                    value_ty,
-                                                ),
-                                                whole_span,
-                                            )),
-                                            ty: property_type.ty.clone(),
-                                        },
-                                        whole_span,
-                                    ));
-                                }
-                            }
-
-                            for method_type in methods {
-                                if method_type.name == property.value {
-                                    self.unify(
-                                        &value_ty.value.ty,
-                                        &method_type.ty,
-                                        value_span,
-                                        ctx,
-                                    )?;
-                                    return Ok(Spanned::new(
-                                        t::TypedExpression {
-                                            expr: Box::new(Spanned::new(
-                                                t::Expression::Set(
-                                                    property.value,
-                                                    class_instance,
-                                                    value_ty,
-                                                ),
-                                                whole_span,
-                                            )),
-                                            ty: method_type.ty.clone(),
-                                        },
-                                        whole_span,
-                                    ));
-                                }
-                            }
-                        }
-                        _ => unreachable!(),
-                    }
-                }
+        let class_name = match class_instance.value.ty {
+            Type::Class(name, _, _, _) => Some(name),
+            Type::Generic(_, ref ty) => match **ty {
+                Type::Class(name, _, _, _) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        };
 
-                let msg = format!(
-                    "class `{}` doesn't have a field/method named `{}`",
-                    ctx.name(*name),
-                    ctx.name(property.value)
-                );
-
-                ctx.error(msg, whole_span);
-
-                Err(())
-            }
-
-            ref other_ty => {
+        let class_name = match class_name {
+            Some(name) => name,
+            None => {
                 let msg = format!(
                     "Type {} dosen't have the method/field {}",
-                    other_ty.print(ctx.symbols()),
+                    class_instance.value.ty.print(ctx.symbols()),
                     ctx.name(property.value)
                 );
 
                 ctx.error(msg, property.span);
-                Err(())
+                return Err(());
             }
-        }
+        };
+
+        // We look at the canical type within the environment due to constructor functions not having  the right type informantion as methods are missing
+        let (propertys, methods) = match ctx.look_type(class_name).cloned() {
+            Some(Type::Generic(_, ref ty)) => match **ty {
+                Type::Class(_, ref propertys, ref methods, _) => {
+                    (propertys.clone(), methods.clone())
+                }
+                _ => unreachable!(),
+            },
+            Some(Type::Class(_, propertys, methods, _)) => (propertys, methods),
+            _ => unreachable!(),
+        };
+
+        let value_span = value.span;
+        let value_ty = self.infer_expr(value, ctx)?;
+
+        let ty = if let Some(property_type) = propertys
+            .iter()
+            .find(|property_type| property_type.name == property.value)
+        {
+            property_type.ty.clone()
+        } else if let Some(method_type) = methods
+            .iter()
+            .find(|method_type| method_type.name == property.value)
+        {
+            method_type.ty.clone()
+        } else {
+            let msg = format!(
+                "class `{}` doesn't have a field/method named `{}`",
+                ctx.name(class_name),
+                ctx.name(property.value)
+            );
+
+            ctx.error(msg, whole_span);
+            return Err(());
+        };
+
+        self.unify(&value_ty.value.ty, &ty, value_span, ctx)?;
+        self.check_compound_assign(&kind, &ty, ctx)?;
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(
+                    t::Expression::Set(property.value, kind.value, class_instance, value_ty),
+                    whole_span,
+                )),
+                ty,
+            },
+            whole_span,
+        ))
     }
 }
@@ -0,0 +1,64 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+
+use crate::infer::types::{Type, TypeCon};
+use crate::infer::{Infer, InferResult};
+use syntax::ast::{AssignOperator, Expression};
+use util::pos::{Span, Spanned};
+
+impl Infer {
+    pub(crate) fn infer_set_subscript(
+        &mut self,
+        target: Spanned<Expression>,
+        index: Spanned<Expression>,
+        kind: Spanned<AssignOperator>,
+        value: Spanned<Expression>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let target_span = target.span;
+        let index_span = index.span;
+        let value_span = value.span;
+
+        let target = self.infer_expr(target, ctx)?;
+        let index = self.infer_expr(index, ctx)?;
+
+        self.unify(
+            &index.value.ty,
+            &Type::App(TypeCon::Int, vec![]), // what ever is in the brace has to be an int
+            index_span,
+            ctx,
+        )?;
+
+        let ty = match target.value.ty.clone() {
+            Type::App(TypeCon::Array(ty), _) => *ty,
+
+            Type::App(TypeCon::Str, _) => {
+                ctx.error("Strings are immutable and can't be assigned to", target_span);
+                return Err(());
+            }
+
+            other_ty => {
+                let msg = format!(" Cannot index type `{}` ", other_ty.print(ctx.symbols()));
+                ctx.error(msg, target_span);
+                return Err(());
+            }
+        };
+
+        let value = self.infer_expr(value, ctx)?;
+
+        self.unify(&value.value.ty, &ty, value_span, ctx)?;
+        self.check_compound_assign(&kind, &ty, ctx)?;
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(
+                    t::Expression::SetIndex(target, index, kind.value, value),
+                    whole_span,
+                )),
+                ty,
+            },
+            whole_span,
+        ))
+    }
+}
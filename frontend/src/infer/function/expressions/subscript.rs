@@ -55,17 +55,17 @@ impl Infer {
                         let var = Spanned::new(
                             t::TypedExpression {
                                 expr: Box::new(Spanned::new(
-                                    t::Expression::Var(symbol.value, index_ty.value.ty.clone()),
+                                    t::Expression::Var(symbol.value, target_ty.clone()),
                                     target_span,
                                 )),
-                                ty: index_ty.value.ty.clone(),
+                                ty: target_ty.clone(),
                             },
                             target_span,
                         );
 
                         (
                             Spanned::new(t::Expression::Index(var, index_ty), whole_span),
-                            Type::App(TypeCon::Str, vec![]),
+                            Type::App(TypeCon::Char, vec![]),
                         )
                     }
 
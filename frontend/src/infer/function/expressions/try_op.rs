@@ -0,0 +1,69 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+use crate::infer::types::Type;
+use crate::infer::{Infer, InferResult};
+use syntax::ast::Expression;
+use util::pos::{Span, Spanned};
+
+impl Infer {
+    pub(crate) fn infer_try(
+        &mut self,
+        expr: Spanned<Expression>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let span = expr.span;
+        let value = self.infer_expr(expr, ctx)?;
+
+        let (name, variants) = match value.value.ty.as_enum() {
+            Some((name, variants)) => (name, variants.clone()),
+            None => {
+                let msg = format!(
+                    "`?` cannot be used on type `{}`",
+                    value.value.ty.print(ctx.symbols())
+                );
+                ctx.error(msg, span);
+                return Err(());
+            }
+        };
+
+        let (success, failure) = match ctx.name(name).as_str() {
+            "Option" => ("Some", "None"),
+            "Result" => ("Ok", "Err"),
+            _ => ("", ""),
+        };
+
+        let success = variants.iter().find(|&(variant, _)| ctx.name(*variant) == success);
+        let failure = variants.iter().find(|&(variant, _)| ctx.name(*variant) == failure);
+
+        let (ty, tag) = match (success, failure) {
+            (Some((_, success)), Some((_, failure))) => {
+                (success.inner.clone().unwrap_or(Type::Nil), failure.tag)
+            }
+            _ => {
+                ctx.error("`?` can only be used on an `Option` or a `Result`", span);
+                return Err(());
+            }
+        };
+
+        match self.returns.as_enum() {
+            Some((returns, _)) if returns == name => (),
+            _ => {
+                let msg = format!(
+                    "`?` can only be used in a function that returns `{}`",
+                    ctx.name(name)
+                );
+                ctx.error(msg, whole_span);
+                return Err(());
+            }
+        }
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(t::Expression::Try { value, tag }, whole_span)),
+                ty,
+            },
+            whole_span,
+        ))
+    }
+}
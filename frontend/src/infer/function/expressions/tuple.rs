@@ -0,0 +1,76 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+
+use crate::infer::types::{Type, TypeCon};
+use crate::infer::{Infer, InferResult};
+use syntax::ast::Expression;
+use util::pos::{Span, Spanned};
+
+impl Infer {
+    pub(crate) fn infer_tuple(
+        &mut self,
+        items: Vec<Spanned<Expression>>,
+        span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let mut nitems = Vec::with_capacity(items.len());
+        let mut types = Vec::with_capacity(items.len());
+
+        for item in items {
+            let ty_expr = self.infer_expr(item, ctx)?;
+
+            types.push(ty_expr.value.ty.clone());
+            nitems.push(ty_expr);
+        }
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(t::Expression::Tuple(nitems), span)),
+                ty: Type::App(TypeCon::Tuple, types),
+            },
+            span,
+        ))
+    }
+
+    pub(crate) fn infer_tuple_index(
+        &mut self,
+        tuple: Spanned<Expression>,
+        index: Spanned<usize>,
+        whole_span: Span,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<Spanned<t::TypedExpression>> {
+        let tuple = self.infer_expr(tuple, ctx)?;
+
+        let ty = match tuple.value.ty {
+            Type::App(TypeCon::Tuple, ref types) => match types.get(index.value) {
+                Some(ty) => ty.clone(),
+                None => {
+                    let msg = format!(
+                        "The tuple `{}` has no item {}",
+                        tuple.value.ty.print(ctx.symbols()),
+                        index.value
+                    );
+                    ctx.error(msg, index.span);
+                    return Err(());
+                }
+            },
+
+            ref other => {
+                let msg = format!("Expected a tuple found `{}`", other.print(ctx.symbols()));
+                ctx.error(msg, tuple.span);
+                return Err(());
+            }
+        };
+
+        Ok(Spanned::new(
+            t::TypedExpression {
+                expr: Box::new(Spanned::new(
+                    t::Expression::TupleIndex(tuple, index.value as u8),
+                    whole_span,
+                )),
+                ty,
+            },
+            whole_span,
+        ))
+    }
+}
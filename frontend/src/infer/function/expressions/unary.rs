@@ -22,7 +22,7 @@ impl Infer {
                 Type::App(TypeCon::Bool, vec![]),
             ),
             UnaryOp::Minus => {
-                if !expr.value.ty.is_int() && !expr.value.ty.is_float() {
+                if !expr.value.ty.is_integer() && !expr.value.ty.is_float() {
                     let msg = format!(
                         "Cannot use `-` operator on type `{}`",
                         expr.value.ty.print(ctx.symbols())
@@ -5,12 +5,90 @@ mod statements;
 use crate::ast as t;
 use crate::ctx::CompileCtx;
 use crate::infer::env::VarEntry;
+use crate::infer::reachability;
 use crate::infer::types::{Type, TypeCon, TypeVar};
-use crate::infer::{Infer, InferResult};
-use syntax::ast::Function;
+use crate::infer::{Infer, InferResult, Signature};
+use syntax::ast::{Function, FunctionParam};
 use util::pos::Spanned;
 
 impl Infer {
+    /// Adds the signature of `function` to the scope so that it can be called by any function,
+    /// including the ones declared before it
+    pub fn declare_function(
+        &mut self,
+        function: &Spanned<Function>,
+        ctx: &mut CompileCtx,
+    ) -> InferResult<()> {
+        ctx.begin_scope(); // the type parameters are only in scope for the signature
+
+        let mut poly_tvs = Vec::with_capacity(function.value.name.value.type_params.len());
+
+        for ident in &function.value.name.value.type_params {
+            let tv = TypeVar::new();
+
+            ctx.add_type(ident.value, Type::Var(tv));
+            poly_tvs.push(tv);
+        }
+
+        let mut signature = Vec::with_capacity(function.value.params.value.len() + 1);
+        let mut defaults = Vec::new();
+
+        for param in function.value.params.value.iter() {
+            let ty = self.param_type(&param.value, ctx)?;
+
+            if let Some(ref default) = param.value.default {
+                let span = default.span;
+                let default = self.infer_expr(default.clone(), ctx)?;
+
+                self.unify(&ty, &default.value.ty, span, ctx)?;
+                defaults.push(default);
+            }
+
+            signature.push(ty);
+        }
+
+        if let Some(ref ty) = function.value.returns {
+            signature.push(self.trans_type(ty, ctx)?);
+        } else {
+            signature.push(Type::Nil);
+        }
+
+        ctx.end_scope();
+
+        let name = function.value.name.value.name.value;
+
+        ctx.add_var(
+            name,
+            VarEntry::Fun {
+                ty: Type::Generic(poly_tvs, Box::new(Type::App(TypeCon::Arrow, signature))),
+            },
+        );
+
+        let params = &function.value.params.value;
+
+        self.signatures.insert(
+            name,
+            Signature {
+                params: params.iter().map(|param| param.value.name.value).collect(),
+                defaults,
+                rest: params.iter().any(|param| param.value.rest),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The type of a param, a rest param is an array of its declared type
+    fn param_type(&mut self, param: &FunctionParam, ctx: &mut CompileCtx) -> InferResult<Type> {
+        let ty = self.trans_type(&param.ty, ctx)?;
+
+        if param.rest {
+            Ok(Type::App(TypeCon::Array(Box::new(ty)), vec![]))
+        } else {
+            Ok(ty)
+        }
+    }
+
     pub fn infer_function(
         &mut self,
         function: Spanned<Function>,
@@ -35,7 +113,7 @@ impl Infer {
         let mut env_types = Vec::with_capacity(function.value.params.value.len()); // types stored in token
 
         for param in function.value.params.value.iter() {
-            let ty = self.trans_type(&param.value.ty, ctx)?;
+            let ty = self.param_type(&param.value, ctx)?;
 
             env_types.push(ty.clone());
             param_types.push(t::FunctionParam {
@@ -56,6 +134,9 @@ impl Infer {
             },
         );
 
+        let enclosing = ::std::mem::replace(&mut self.returns, returns.clone());
+        let enclosing_loops = ::std::mem::replace(&mut self.loops, Vec::new()); // for closures
+
         ctx.begin_scope();
 
         for param in param_types.iter() {
@@ -65,8 +146,13 @@ impl Infer {
         let mut span = function.value.body.span;
         let mut body = self.infer_statement(function.value.body, ctx)?;
 
+        reachability::check_statement(&body, ctx);
+
         ctx.end_scope();
 
+        self.returns = enclosing;
+        self.loops = enclosing_loops;
+
         self.unify(&returns, &self.body, span, ctx)?;
 
         if &ctx.name(function.value.name.value.name.value) == "main" {
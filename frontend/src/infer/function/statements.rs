@@ -52,9 +52,17 @@ impl Infer {
                     Type::Nil,
                 )
             }
-            Statement::Break => (Spanned::new(t::Statement::Break, statement.span), Type::Nil),
-            Statement::Continue => (
-                Spanned::new(t::Statement::Continue, statement.span),
+            Statement::Break(label, value) => {
+                let label = label.map(|l| l.value);
+                let value = self.infer_break(label, value, statement.span, ctx)?;
+
+                (
+                    Spanned::new(t::Statement::Break(label, value), statement.span),
+                    Type::Nil,
+                )
+            }
+            Statement::Continue(label) => (
+                Spanned::new(t::Statement::Continue(label.map(|l| l.value)), statement.span),
                 Type::Nil,
             ),
             Statement::Expr(expr) => {
@@ -72,6 +80,7 @@ impl Infer {
                 cond,
                 incr,
                 body,
+                label,
             } => {
                 if init.is_none() && cond.is_none() && incr.is_none() {
                     let body = self.infer_statement(*body, ctx)?;
@@ -80,13 +89,18 @@ impl Infer {
 
                 let mut block = vec![];
                 let span = body.span;
+                let label = label.map(|l| l.value);
 
                 if let Some(init) = init {
                     block.push(self.infer_statement(*init, ctx)?);
                 }
 
+                self.begin_loop(label, false);
+
                 let mut while_block = vec![self.infer_statement(*body, ctx)?];
 
+                self.loops.pop();
+
                 if let Some(incr) = incr {
                     let incr_span = incr.span;
                     let typed_expr = self.infer_expr(incr, ctx)?;
@@ -141,6 +155,7 @@ impl Infer {
                                         },
                                         span,
                                     ),
+                                    label,
                                 ),
                                 span,
                             )),
@@ -173,6 +188,7 @@ impl Infer {
                                         },
                                         span,
                                     ),
+                                    label,
                                 ),
                                 span,
                             )),
@@ -221,6 +237,14 @@ impl Infer {
                 )
             }
 
+            Statement::Defer(body) => (
+                Spanned::new(
+                    t::Statement::Defer(self.infer_statement(*body, ctx)?),
+                    statement.span,
+                ),
+                Type::Nil,
+            ),
+
             Statement::Print(expr) => {
                 let type_expr = self.infer_expr(expr, ctx)?;
 
@@ -230,21 +254,40 @@ impl Infer {
                 )
             }
 
-            Statement::While { cond, body } => {
+            Statement::EPrint(expr) => {
+                let type_expr = self.infer_expr(expr, ctx)?;
+
+                (
+                    Spanned::new(t::Statement::EPrint(type_expr), statement.span),
+                    Type::Nil,
+                )
+            }
+
+            Statement::While { cond, body, label } => {
                 let span = cond.span;
                 let expr = self.infer_expr(cond, ctx)?;
                 self.unify(&Type::App(TypeCon::Bool, vec![]), &expr.value.ty, span, ctx)?;
 
+                let label = label.map(|l| l.value);
+
+                self.begin_loop(label, false);
+
+                let body = self.infer_statement(*body, ctx)?;
+
+                self.loops.pop();
+
                 (
-                    Spanned::new(
-                        t::Statement::While(expr, self.infer_statement(*body, ctx)?),
-                        statement.span,
-                    ),
+                    Spanned::new(t::Statement::While(expr, body, label), statement.span),
                     Type::Nil,
                 )
             }
 
-            Statement::VarDeclaration { ident, ty, expr } => {
+            Statement::VarDeclaration {
+                ident,
+                ty,
+                expr,
+                constant,
+            } => {
                 if let Some(expr) = expr {
                     let expr_tyexpr = self.infer_expr(expr, ctx)?;
 
@@ -262,6 +305,7 @@ impl Infer {
                                         ident: ident.value,
                                         ty: t,
                                         expr: Some(expr_tyexpr),
+                                        constant,
                                     },
                                     statement.span,
                                 )),
@@ -279,6 +323,7 @@ impl Infer {
                                 ident: ident.value,
                                 ty: expr_tyexpr.value.ty.clone(),
                                 expr: Some(expr_tyexpr),
+                                constant,
                             },
                             statement.span,
                         ),
@@ -297,6 +342,7 @@ impl Infer {
                                         ident: ident.value,
                                         ty,
                                         expr: None,
+                                        constant,
                                     },
                                     statement.span,
                                 )),
@@ -314,6 +360,7 @@ impl Infer {
                                 ident: ident.value,
                                 ty: Type::Nil,
                                 expr: None,
+                                constant,
                             },
                             statement.span,
                         ),
@@ -322,6 +369,73 @@ impl Infer {
                 }
             }
 
+            Statement::LetTuple { idents, expr } => {
+                let expr_span = expr.span;
+                let expr = self.infer_expr(expr, ctx)?;
+
+                let types = match expr.value.ty {
+                    Type::App(TypeCon::Tuple, ref types) if types.len() == idents.len() => {
+                        types.clone()
+                    }
+
+                    ref other => {
+                        let msg = format!(
+                            "Expected a tuple of {} items found `{}`",
+                            idents.len(),
+                            other.print(ctx.symbols())
+                        );
+                        ctx.error(msg, expr_span);
+                        return Err(());
+                    }
+                };
+
+                for (ident, ty) in idents.iter().zip(types) {
+                    ctx.add_var(ident.value, VarEntry::Var(ty));
+                }
+
+                (
+                    Spanned::new(
+                        t::Statement::LetTuple {
+                            idents: idents.into_iter().map(|ident| ident.value).collect(),
+                            expr,
+                        },
+                        statement.span,
+                    ),
+                    Type::Nil,
+                )
+            }
+
+            Statement::LetArray { idents, expr } => {
+                let expr_span = expr.span;
+                let expr = self.infer_expr(expr, ctx)?;
+
+                let ty = match expr.value.ty {
+                    Type::App(TypeCon::Array(ref ty), _) => (**ty).clone(),
+
+                    ref other => {
+                        let msg =
+                            format!("Expected an array found `{}`", other.print(ctx.symbols()));
+                        ctx.error(msg, expr_span);
+                        return Err(());
+                    }
+                };
+
+                for ident in &idents {
+                    ctx.add_var(ident.value, VarEntry::Var(ty.clone()));
+                }
+
+                (
+                    Spanned::new(
+                        t::Statement::LetArray {
+                            idents: idents.into_iter().map(|ident| ident.value).collect(),
+                            expr,
+                        },
+                        statement.span,
+                    ),
+                    Type::Nil,
+                )
+            }
+
             Statement::Return(expr) => {
                 let type_expr = self.infer_expr(expr, ctx)?;
                 let ty = type_expr.value.ty.clone();
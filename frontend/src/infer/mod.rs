@@ -6,6 +6,7 @@ pub(crate) mod env;
 mod alias;
 mod class;
 mod function;
+mod reachability;
 pub(crate) mod subst;
 mod sum;
 pub(crate) mod types;
@@ -14,21 +15,58 @@ mod user_types;
 
 pub(crate) type InferResult<T> = Result<T, ()>;
 // pub use self::resolver::Resolver;
+use crate::ast as t;
 use crate::infer::types::Type;
+use crate::native::Native;
+use fnv::FnvHashMap;
 use std::rc::Rc;
+use util::pos::Spanned;
 use util::symbol::Symbol;
 
 #[derive(Debug)]
 pub struct Infer {
     body: Type,
+    /// The return type of the function currently being inferred
+    returns: Type,
+    /// What each function's params accept beyond their types
+    signatures: FnvHashMap<Symbol, Signature>,
+    /// The loops enclosing the statement being inferred, innermost last
+    loops: Vec<LoopScope>,
     main: Option<Symbol>,
+    /// The functions the vm will be given by the host
+    natives: Vec<Native>,
+}
+
+/// A loop that a `break` can refer to
+#[derive(Debug, Clone)]
+pub(crate) struct LoopScope {
+    label: Option<Symbol>,
+    /// If the loop is a `loop` expression which is given a value by `break`
+    is_loop: bool,
+    /// The type of the values given by the `break`s seen so far
+    value: Option<Type>,
+}
+
+/// The parts of a function's params that its type doesn't capture
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Signature {
+    /// The names of the params in order
+    params: Vec<Symbol>,
+    /// The default values of the trailing params
+    defaults: Vec<Spanned<t::TypedExpression>>,
+    /// If the last param is a rest param
+    rest: bool,
 }
 
 impl Default for Infer {
     fn default() -> Self {
         Self {
             body: Type::Nil,
+            returns: Type::Nil,
+            signatures: FnvHashMap::default(),
+            loops: Vec::new(),
             main: None,
+            natives: Vec::new(),
         }
     }
 }
@@ -38,6 +76,12 @@ impl Infer {
         Self::default()
     }
 
+    /// Lets the program call `natives` which must be registered with the vm before it runs
+    pub fn with_natives(mut self, natives: Vec<Native>) -> Self {
+        self.natives = natives;
+        self
+    }
+
     /// Runs type inference returns a version of the ast which has the type of each operation
     pub fn infer(
         &mut self,
@@ -47,6 +91,10 @@ impl Infer {
     ) -> InferResult<super::ast::Program> {
         let mut ctx = crate::ctx::CompileCtx::new(strings, reporter);
 
+        for native in self.natives.iter() {
+            ctx.add_native(native);
+        }
+
         let mut new_program = super::ast::Program {
             functions: Vec::new(),
             classes: Vec::new(),
@@ -64,6 +112,10 @@ impl Infer {
             new_program.classes.push(self.infer_class(class, &mut ctx)?);
         }
 
+        for function in program.functions.iter() {
+            self.declare_function(function, &mut ctx)?;
+        }
+
         for function in program.functions {
             new_program
                 .functions
@@ -0,0 +1,197 @@
+use crate::ast as t;
+use crate::ctx::CompileCtx;
+use util::pos::Spanned;
+
+/// Warns about statements after a `return`, `break` or `continue` and about match arms after an
+/// arm that matches everything.
+/// Returns true if the statement never finishes i.e every path through it returns
+pub(crate) fn check_statement(
+    statement: &Spanned<t::TypedStatement>,
+    ctx: &mut CompileCtx,
+) -> bool {
+    match statement.value.statement.value {
+        t::Statement::Block(ref statements) => {
+            let mut diverges = false;
+
+            for statement in statements {
+                if diverges {
+                    ctx.warn("Unreachable statement", statement.span);
+                    break;
+                }
+
+                diverges = check_statement(statement, ctx);
+            }
+
+            diverges
+        }
+
+        t::Statement::Break(_, ref value) => {
+            if let Some(ref value) = *value {
+                check_expression(value, ctx);
+            }
+
+            true
+        }
+
+        t::Statement::Continue(_) => true,
+
+        // The statements after a `defer` run before the deferred one
+        t::Statement::Defer(ref statement) => {
+            check_statement(statement, ctx);
+            false
+        }
+
+        t::Statement::Return(ref expr) => {
+            check_expression(expr, ctx);
+            true
+        }
+
+        t::Statement::Expr(ref expr)
+        | t::Statement::Print(ref expr)
+        | t::Statement::EPrint(ref expr) => {
+            check_expression(expr, ctx);
+            false
+        }
+
+        t::Statement::If {
+            ref cond,
+            ref then,
+            ref otherwise,
+        } => {
+            check_expression(cond, ctx);
+
+            let then = check_statement(then, ctx);
+
+            match *otherwise {
+                Some(ref otherwise) => check_statement(otherwise, ctx) && then,
+                None => false,
+            }
+        }
+
+        t::Statement::While(ref cond, ref body, _) => {
+            check_expression(cond, ctx);
+            check_statement(body, ctx);
+            false // a `break` only leaves the loop
+        }
+
+        t::Statement::Let { ref expr, .. } => {
+            if let Some(ref expr) = *expr {
+                check_expression(expr, ctx);
+            }
+
+            false
+        }
+
+        t::Statement::LetTuple { ref expr, .. } | t::Statement::LetArray { ref expr, .. } => {
+            check_expression(expr, ctx);
+            false
+        }
+    }
+}
+
+fn check_expression(expr: &Spanned<t::TypedExpression>, ctx: &mut CompileCtx) {
+    match expr.value.expr.value {
+        t::Expression::Match { ref cond, ref arms } => {
+            check_expression(cond, ctx);
+
+            let mut seen_catch_all = false;
+
+            for arm in arms.value.iter() {
+                if seen_catch_all {
+                    ctx.warn("Unreachable pattern, a previous arm matches everything", arm.span);
+                }
+
+                check_statement(&arm.value.body, ctx);
+
+                seen_catch_all |= arm.value.is_all && arm.value.guard.is_none();
+            }
+        }
+
+        t::Expression::Array(ref items) | t::Expression::Tuple(ref items) => {
+            check_expressions(items, ctx)
+        }
+
+        t::Expression::Call(_, ref args) => check_expressions(args, ctx),
+
+        t::Expression::StaticMethodCall { ref params, .. } => check_expressions(params, ctx),
+
+        t::Expression::CallValue(ref callee, ref args) => {
+            check_expression(callee, ctx);
+            check_expressions(args, ctx);
+        }
+
+        t::Expression::InstanceMethodCall {
+            ref instance,
+            ref params,
+            ..
+        } => {
+            check_expression(instance, ctx);
+            check_expressions(params, ctx);
+        }
+
+        t::Expression::ClassLiteral { ref properties, .. } => {
+            for property in properties {
+                check_expression(&property.value.expr, ctx);
+            }
+        }
+
+        t::Expression::Assign(_, _, ref expr)
+        | t::Expression::Cast(ref expr, _)
+        | t::Expression::GetProperty {
+            property: ref expr, ..
+        }
+        | t::Expression::GetMethod {
+            method: ref expr, ..
+        }
+        | t::Expression::Grouping(ref expr)
+        | t::Expression::Try { value: ref expr, .. }
+        | t::Expression::TupleIndex(ref expr, _)
+        | t::Expression::Unary(_, ref expr)
+        | t::Expression::VariantWithData {
+            inner: ref expr, ..
+        } => check_expression(expr, ctx),
+
+        t::Expression::Loop(ref body, _) => {
+            check_statement(body, ctx);
+        }
+
+        t::Expression::Block(ref statements, ref value) => {
+            for statement in statements {
+                check_statement(statement, ctx);
+            }
+
+            check_expression(value, ctx);
+        }
+
+        t::Expression::Binary(ref lhs, _, ref rhs)
+        | t::Expression::Index(ref lhs, ref rhs)
+        | t::Expression::Set(_, _, ref lhs, ref rhs) => {
+            check_expression(lhs, ctx);
+            check_expression(rhs, ctx);
+        }
+
+        t::Expression::SetIndex(ref target, ref index, _, ref value) => {
+            check_expression(target, ctx);
+            check_expression(index, ctx);
+            check_expression(value, ctx);
+        }
+
+        t::Expression::Ternary(ref cond, ref if_true, ref if_false) => {
+            check_expression(cond, ctx);
+            check_expression(if_true, ctx);
+            check_expression(if_false, ctx);
+        }
+
+        // A closure's body is checked when the closure is inferred
+        t::Expression::Closure(_)
+        | t::Expression::Literal(_)
+        | t::Expression::Var(_, _)
+        | t::Expression::VariantNoData { .. } => (),
+    }
+}
+
+fn check_expressions(exprs: &[Spanned<t::TypedExpression>], ctx: &mut CompileCtx) {
+    for expr in exprs {
+        check_expression(expr, ctx);
+    }
+}
@@ -1,11 +1,21 @@
 use super::Infer;
-use crate::infer::types::{Method, Property, Type, TypeVar, Variant};
+use crate::infer::types::{Method, Property, Type, TypeCon, TypeVar, Variant};
 use std::collections::HashMap;
 
 impl Infer {
     /// Deals with the subsitution of type variables
     pub fn subst(&self, ty: &Type, substions: &mut HashMap<TypeVar, Type>) -> Type {
         match *ty {
+            Type::App(TypeCon::Array(ref inner), ref types) => Type::App(
+                TypeCon::Array(Box::new(self.subst(inner, substions))),
+                types.iter().map(|ty| self.subst(ty, substions)).collect(),
+            ),
+
+            Type::App(TypeCon::Nullable(ref inner), ref types) => Type::App(
+                TypeCon::Nullable(Box::new(self.subst(inner, substions))),
+                types.iter().map(|ty| self.subst(ty, substions)).collect(),
+            ),
+
             Type::App(ref tycon, ref types) => Type::App(
                 tycon.clone(),
                 types.iter().map(|ty| self.subst(ty, substions)).collect(),
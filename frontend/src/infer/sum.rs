@@ -2,7 +2,7 @@ use crate::ctx::CompileCtx;
 
 use crate::infer::types::{Type, TypeVar, Variant};
 use crate::infer::{Infer, InferResult};
-use std::collections::HashMap;
+use fnv::FnvHashMap;
 use syntax::ast::Enum;
 use util::pos::Spanned;
 
@@ -26,12 +26,12 @@ impl Infer {
                 generic_type_vars.clone(),
                 Box::new(Type::Enum {
                     name: _enum.value.name.value.name.value,
-                    variants: HashMap::new(),
+                    variants: FnvHashMap::default(),
                 }),
             ),
         ); // For recursive types we need to add the empty enum
 
-        let mut variants = HashMap::with_capacity(_enum.value.variants.len());
+        let mut variants = FnvHashMap::default();
 
         for (i, variant) in _enum.value.variants.into_iter().enumerate() {
             let inner = if let Some(ref ty) = variant.inner {
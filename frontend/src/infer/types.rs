@@ -1,6 +1,9 @@
-use std::collections::HashMap;
+use fnv::FnvHashMap;
 use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::mem;
 use util::symbol::{Symbol, Symbols};
+use vm::IntKind;
 
 static mut TYPEVAR_COUNT: u32 = 0;
 static mut UNIQUE_COUNT: u32 = 0;
@@ -13,15 +16,22 @@ pub struct TypeVar(pub u32);
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Unique(pub u32);
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub enum TypeCon {
     Arrow,
     Array(Box<Type>),
     Bool,
+    Char,
     Float,
     Int,
+    /// An int that is truncated to a smaller size i.e `u8`
+    SizedInt(IntKind),
     Str,
     Void,
+    /// A value of the inner type or nil
+    Nullable(Box<Type>),
+    /// The types of the items are the types applied to it
+    Tuple,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -33,7 +43,9 @@ pub enum Type {
     Var(TypeVar),
     Enum {
         name: Symbol,
-        variants: HashMap<Symbol, Variant>,
+        /// Uses a deterministic hasher so the order the variants are visited in never changes
+        /// between runs. Anything shown to the user should still be ordered by tag
+        variants: FnvHashMap<Symbol, Variant>,
     },
 }
 
@@ -44,19 +56,19 @@ pub enum Type {
 ///     inner:None // if it dosen't have an inner type i.e Ok(foo)
 ///  }
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Variant {
     pub tag: u32,
     pub inner: Option<Type>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Property {
     pub name: Symbol,
     pub ty: Type,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Method {
     pub name: Symbol,
     pub ty: Type,
@@ -104,12 +116,36 @@ impl Type {
         }
     }
 
+    pub fn is_nullable(&self) -> bool {
+        match *self {
+            Type::App(TypeCon::Nullable(_), _) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the type is an int or a sized int
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            Type::App(TypeCon::Int, _) | Type::App(TypeCon::SizedInt(_), _) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_float(&self) -> bool {
         match *self {
             Type::App(TypeCon::Float, _) => true,
             _ => false,
         }
     }
+
+    /// The name and variants of an enum, looking through any type parameters
+    pub fn as_enum(&self) -> Option<(Symbol, &FnvHashMap<Symbol, Variant>)> {
+        match *self {
+            Type::Generic(_, ref ty) => ty.as_enum(),
+            Type::Enum { name, ref variants } => Some((name, variants)),
+            _ => None,
+        }
+    }
 }
 
 impl Type {
@@ -137,6 +173,12 @@ impl Type {
                     return fmt_string;
                 }
 
+                if let TypeCon::Tuple = *tycon {
+                    let items: Vec<String> = types.iter().map(|ty| ty.print(symbols)).collect();
+
+                    return format!("({})", items.join(","));
+                }
+
                 fmt_string.push_str(&format!("{}", tycon));
 
                 for (i, ty) in types.iter().enumerate() {
@@ -205,16 +247,55 @@ impl Type {
     }
 }
 
+/// Type vars and class uniques are fresh on every inference so their ids aren't hashed.
+/// This keeps the hash of a typed ast the same each time the same source is compiled
+impl Hash for Type {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+
+        match *self {
+            Type::App(ref tycon, ref types) => {
+                tycon.hash(state);
+                types.hash(state);
+            }
+            Type::Class(ref name, ref properties, ref methods, _) => {
+                name.hash(state);
+                properties.hash(state);
+                methods.hash(state);
+            }
+            Type::Generic(ref vars, ref ty) => {
+                vars.len().hash(state);
+                ty.hash(state);
+            }
+            Type::Nil | Type::Var(_) => (),
+            Type::Enum {
+                ref name,
+                ref variants,
+            } => {
+                name.hash(state);
+
+                let mut variants: Vec<_> = variants.iter().collect();
+                variants.sort_by_key(|(_, variant)| variant.tag);
+                variants.hash(state);
+            }
+        }
+    }
+}
+
 impl Display for TypeCon {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             TypeCon::Arrow => write!(f, "->"),
-            TypeCon::Array(ref inner) => write!(f, "{}", inner),
+            TypeCon::Array(ref inner) => write!(f, "[{}]", inner),
             TypeCon::Bool => write!(f, "bool"),
+            TypeCon::Char => write!(f, "char"),
             TypeCon::Float => write!(f, "float"),
             TypeCon::Int => write!(f, "int"),
+            TypeCon::SizedInt(kind) => write!(f, "{}", kind),
             TypeCon::Str => write!(f, "str"),
             TypeCon::Void => write!(f, "nil"),
+            TypeCon::Nullable(ref inner) => write!(f, "{}?", inner),
+            TypeCon::Tuple => write!(f, "tuple"),
         }
     }
 }
@@ -239,6 +320,12 @@ impl Display for Type {
                     write!(f, "{}", types.last().unwrap())?;
                 }
 
+                if let TypeCon::Tuple = *tycon {
+                    let items: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+
+                    return write!(f, "({})", items.join(","));
+                }
+
                 write!(f, "{}", tycon)?;
 
                 for (i, ty) in types.iter().enumerate() {
@@ -279,8 +366,10 @@ impl Display for Type {
 
                 if !variants.is_empty() {
                     write!(f, "<")?;
+                    let mut variants: Vec<_> = variants.values().collect();
+                    variants.sort_by_key(|variant| variant.tag);
+
                     for (i, variant) in variants.iter().enumerate() {
-                        let variant = variant.1;
 
                         if i + 1 == variants.len() {
                             if let Some(ref inner) = variant.inner {
@@ -333,3 +422,50 @@ impl Display for TypeVar {
         write!(f, "tv{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Type, TypeVar, Variant};
+    use fnv::FnvHashMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use util::symbol::Symbol;
+
+    fn hash(ty: &Type) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        ty.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn enum_type(order: &[u64]) -> Type {
+        let mut variants = FnvHashMap::default();
+
+        for &variant in order {
+            let tag = variant as u32;
+            let inner = if tag == 0 { None } else { Some(Type::Nil) };
+
+            variants.insert(Symbol(variant + 10), Variant { tag, inner });
+        }
+
+        Type::Enum {
+            name: Symbol(0),
+            variants,
+        }
+    }
+
+    #[test]
+    fn enum_variants_display_in_tag_order() {
+        let expected = "enum Symbol 0<0,1:nil,2:nil>";
+
+        assert_eq!(enum_type(&[0, 1, 2]).to_string(), expected);
+        assert_eq!(enum_type(&[2, 0, 1]).to_string(), expected);
+        assert_eq!(enum_type(&[1, 2, 0]).to_string(), expected);
+    }
+
+    #[test]
+    fn hash_ignores_variant_order_and_type_var_ids() {
+        assert_eq!(hash(&enum_type(&[0, 1, 2])), hash(&enum_type(&[2, 0, 1])));
+        assert_eq!(hash(&Type::Var(TypeVar(1))), hash(&Type::Var(TypeVar(2))));
+        assert_ne!(hash(&enum_type(&[0, 1])), hash(&enum_type(&[0, 1, 2])));
+    }
+}
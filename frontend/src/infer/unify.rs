@@ -39,6 +39,17 @@ impl Infer {
                 Ok(())
             }
 
+            // nil can only be given to a nullable type
+            (&Type::App(TypeCon::Nullable(_), _), &Type::Nil)
+            | (&Type::Nil, &Type::App(TypeCon::Nullable(_), _)) => Ok(()),
+
+            (&Type::App(TypeCon::Nullable(ref inner), _), ty)
+            | (ty, &Type::App(TypeCon::Nullable(ref inner), _))
+                if !ty.is_nullable() =>
+            {
+                self.unify(inner, ty, span, ctx)
+            }
+
             (&Type::App(TypeCon::Void, _), &Type::Class(_, _, _, _)) => Ok(()),
             (&Type::Class(_, _, _, _), &Type::App(TypeCon::Void, _)) => Ok(()),
 
@@ -65,7 +76,7 @@ impl Infer {
             }
 
             (&Type::App(ref tycon1, ref types1), &Type::App(ref tycon2, ref types2)) => {
-                if tycon1 != tycon2 {
+                if tycon1 != tycon2 || types1.len() != types2.len() {
                     let msg = format!(
                         "Cannot unify `{}` vs `{}`",
                         lhs.print(ctx.symbols()),
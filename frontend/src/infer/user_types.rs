@@ -28,6 +28,19 @@ impl Infer {
                 TypeCon::Array(Box::new(self.trans_type(s, ctx)?)),
                 vec![],
             )),
+            astType::Nullable(ref s) => match self.trans_type(s, ctx)? {
+                nullable @ Type::App(TypeCon::Nullable(_), _) => Ok(nullable), // `int??` is `int?`
+                ty => Ok(Type::App(TypeCon::Nullable(Box::new(ty)), vec![])),
+            },
+            astType::Tuple(ref types) => {
+                let mut trans_types = Vec::with_capacity(types.len());
+
+                for ty in types {
+                    trans_types.push(self.trans_type(ty, ctx)?)
+                }
+
+                Ok(Type::App(TypeCon::Tuple, trans_types))
+            }
             astType::Func(ref params, ref returns) => {
                 let mut trans_types = Vec::with_capacity(params.len());
 
@@ -114,7 +127,16 @@ impl Infer {
                                 variants: variants.clone(),
                             })
                         }
-                        _ => unreachable!(), // Polymorphic functions are not stored as types they are stored as vars
+                        // Polymorphic functions are stored as vars so this is a generic alias
+                        ref ty => {
+                            let mut mappings = HashMap::new();
+
+                            for (tvar, ty) in tvars.iter().zip(types) {
+                                mappings.insert(*tvar, self.trans_type(ty, ctx)?);
+                            }
+
+                            Ok(self.subst(ty, &mut mappings))
+                        }
                     },
                     _ => {
                         let msg = format!("Type `{}` is not polymorphic", ctx.name(symbol.value));
@@ -0,0 +1,334 @@
+//! Replaces calls to small functions with the body of the function.
+//! Runs over the compiled bytecode so only functions whose body is a straight line of
+//! instructions ending in a `RETURN` are inlined. Their params and locals are moved into unused
+//! local slots of the caller
+use fnv::FnvHashMap;
+use opcode;
+use std::rc::Rc;
+use util::symbol::Symbol;
+use vm::{instruction_len, Chunk, Function, Program};
+
+/// Functions with a body larger than this many bytes are always called
+const MAX_INLINE_SIZE: usize = 32;
+
+/// The most local slots a chunk can address with a single byte operand
+const MAX_OPERAND: usize = 256;
+
+/// The body of a function that can be spliced into its callers
+#[derive(Debug)]
+struct Inlinable {
+    /// The body up to but not including the `RETURN`
+    code: Vec<u8>,
+    params: usize,
+    /// The number of local slots used by the body
+    slots: usize,
+}
+
+/// Inlines every call to a small leaf function in `program`.
+/// A leaf function never calls another function with `CALL` so recursive functions are never
+/// inlined
+pub fn inline(program: &mut Program) {
+    let inlinable: FnvHashMap<Symbol, Inlinable> = program
+        .functions
+        .iter()
+        .filter_map(|(name, function)| inlinable_body(function).map(|body| (*name, body)))
+        .collect();
+
+    if inlinable.is_empty() {
+        return;
+    }
+
+    // a function shared with a compile cache is copied rather than inlined in place
+    for function in program.functions.values_mut() {
+        inline_calls(Rc::make_mut(function), &inlinable);
+    }
+
+    for class in program.classes.values_mut() {
+        for method in class.methods.values_mut() {
+            inline_calls(Rc::make_mut(method), &inlinable);
+        }
+    }
+}
+
+/// The offset of each instruction in `code`.
+/// Returns `None` if the code contains an unknown or truncated instruction
+fn instructions(code: &[u8]) -> Option<Vec<usize>> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+
+    while offset < code.len() {
+        offsets.push(offset);
+        offset += instruction_len(code, offset).ok()?;
+    }
+
+    Some(offsets)
+}
+
+/// The number of local slots used by the instructions in `code`
+fn slots(code: &[u8], offsets: &[usize]) -> usize {
+    offsets
+        .iter()
+        .filter(|&&offset| code[offset] == opcode::GETLOCAL || code[offset] == opcode::SETLOCAL)
+        .map(|&offset| code[offset + 1] as usize + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+fn is_jump(byte: u8) -> bool {
+    match byte {
+        opcode::JUMP | opcode::JUMPIF | opcode::JUMPNOT | opcode::LOOP => true,
+        _ => false,
+    }
+}
+
+/// Returns the body of `function` if it is small enough and never branches or calls another
+/// function before its first `RETURN`
+fn inlinable_body(function: &Function) -> Option<Inlinable> {
+    let code = &function.body.code;
+
+    if code.len() > MAX_INLINE_SIZE {
+        return None;
+    }
+
+    let offsets = instructions(code)?;
+
+    // Without any jumps everything after the first return is unreachable
+    let end = offsets
+        .iter()
+        .position(|&offset| code[offset] == opcode::RETURN)?;
+
+    let offsets = &offsets[..end];
+
+    if offsets.iter().any(|&offset| {
+        let byte = code[offset];
+        byte == opcode::CALL || byte == opcode::HLT || is_jump(byte)
+    }) {
+        return None;
+    }
+
+    let len = offsets.last().map_or(0, |&offset| {
+        offset + instruction_len(code, offset).expect("Decoded above")
+    });
+
+    Some(Inlinable {
+        code: code[..len].to_vec(),
+        params: function.params.len(),
+        slots: slots(code, offsets),
+    })
+}
+
+/// Writes the body of `callee` to `chunk` in place of a `CALL` on `line`.
+/// The arguments on the stack are stored in the locals starting at `base`.
+/// Returns false without writing anything if the caller would run out of slots
+fn splice(chunk: &mut Chunk, callee: &Inlinable, base: usize, line: u32) -> bool {
+    if base + callee.params + callee.slots > MAX_OPERAND {
+        return false;
+    }
+
+    // The last argument is on top of the stack
+    for param in (0..callee.params).rev() {
+        chunk.write(opcode::SETLOCAL, line);
+        chunk.write((base + param) as u8, line);
+        chunk.write(opcode::POP, line);
+    }
+
+    let code = &callee.code;
+    let mut offset = 0;
+
+    while offset < code.len() {
+        let len = instruction_len(code, offset).expect("Decoded when checked for inlining");
+
+        let remapped = match code[offset] {
+            opcode::GETPARAM => Some((opcode::GETLOCAL, base)),
+            opcode::SETPARAM => Some((opcode::SETLOCAL, base)),
+            opcode::GETLOCAL | opcode::SETLOCAL => Some((code[offset], base + callee.params)),
+            _ => None,
+        };
+
+        match remapped {
+            Some((op, start)) => {
+                chunk.write(op, line);
+                chunk.write((start + code[offset + 1] as usize) as u8, line);
+            }
+
+            None => {
+                for byte in &code[offset..offset + len] {
+                    chunk.write(*byte, line);
+                }
+            }
+        }
+
+        offset += len;
+    }
+
+    true
+}
+
+/// Rewrites the body of `function` replacing each call to an inlinable function with its body.
+/// The function is left unchanged if its jumps can't be fixed up afterwards
+fn inline_calls(function: &mut Function, inlinable: &FnvHashMap<Symbol, Inlinable>) {
+    let chunk = &function.body;
+    let code = &chunk.code;
+
+    let offsets = match instructions(code) {
+        Some(offsets) => offsets,
+        None => return,
+    };
+
+    let mut base = slots(code, &offsets);
+    let mut inlined = Chunk {
+        code: Vec::with_capacity(code.len()),
+        lines: Vec::with_capacity(chunk.lines.len()),
+    };
+
+    // The new offset of each instruction
+    let mut moved = vec![0; code.len() + 1];
+    // The old and new offset of each jump
+    let mut jumps = Vec::new();
+    let mut changed = false;
+
+    for (i, &offset) in offsets.iter().enumerate() {
+        let next = offsets.get(i + 1).cloned().unwrap_or(code.len());
+        let line = match chunk.line_at(offset) {
+            Some(line) => line,
+            None => return,
+        };
+
+        moved[offset] = inlined.code.len();
+
+        if code[offset] == opcode::CALL {
+            let callee = Symbol(u64::from(code[offset + 1]));
+            let argc = code[offset + 2] as usize;
+
+            if let Some(callee) = inlinable.get(&callee) {
+                if callee.params == argc && splice(&mut inlined, callee, base, line) {
+                    base += callee.params + callee.slots;
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+
+        if is_jump(code[offset]) {
+            jumps.push((offset, inlined.code.len()));
+        }
+
+        for byte in &code[offset..next] {
+            inlined.write(*byte, line);
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    moved[code.len()] = inlined.code.len();
+
+    for (old, new) in jumps {
+        let distance = ((code[old + 1] as usize) << 8) | code[old + 2] as usize;
+
+        // Jumps are relative to the end of the jump instruction
+        let distance = if code[old] == opcode::LOOP {
+            new + 3 - moved[old + 3 - distance]
+        } else {
+            moved[old + 3 + distance] - (new + 3)
+        };
+
+        if distance > usize::from(u16::max_value()) {
+            return;
+        }
+
+        inlined.code[new + 1] = ((distance >> 8) & 0xff) as u8;
+        inlined.code[new + 2] = (distance & 0xff) as u8;
+    }
+
+    function.body = inlined;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::driver::compile_str;
+    use opcode;
+    use util::symbol::Symbols;
+    use vm::Program;
+
+    /// The driver inlines the program after compiling it
+    fn compile_inlined(input: &str) -> (Program, Symbols<()>) {
+        let compiled = compile_str(input).0.unwrap();
+
+        (compiled.program, compiled.symbols)
+    }
+
+    /// Counts the calls to `callee` left in `caller`
+    fn count_calls(
+        program: &Program,
+        symbols: &mut Symbols<()>,
+        caller: &str,
+        callee: &str,
+    ) -> usize {
+        let callee = symbols.symbol(callee).0 as u8;
+        let code = &program.functions[&symbols.symbol(caller)].body.code;
+
+        code.windows(2)
+            .filter(|window| window[0] == opcode::CALL && window[1] == callee)
+            .count()
+    }
+
+    #[test]
+    fn inlines_one_line_helper() {
+        let input = "
+            fn double(n:int) -> int {
+                return n * 2;
+            }
+
+            fn main() {
+                print double(21);
+            }
+        ";
+
+        let (program, mut symbols) = compile_inlined(input);
+
+        assert_eq!(count_calls(&program, &mut symbols, "main", "double"), 0);
+    }
+
+    #[test]
+    fn keeps_recursive_call() {
+        let input = "
+            fn fact(n:int) -> int {
+                if n < 2 {
+                    return 1;
+                }
+
+                return n * fact(n - 1);
+            }
+
+            fn main() {
+                print fact(5);
+            }
+        ";
+
+        let (program, mut symbols) = compile_inlined(input);
+
+        assert_eq!(count_calls(&program, &mut symbols, "main", "fact"), 1);
+        assert_eq!(count_calls(&program, &mut symbols, "fact", "fact"), 1);
+    }
+
+    #[test]
+    fn inlined_program_runs() {
+        let input = "
+            fn add(a:int, b:int) -> int {
+                return a - b;
+            }
+
+            fn main() {
+                let x = 10;
+                while x > 0 {
+                    print add(x, 3);
+                    x = x - 4;
+                }
+            }
+        ";
+
+        assert_eq!(crate::run_str(input).unwrap(), "7\n3\n-1\n");
+    }
+}
@@ -11,7 +11,13 @@ extern crate vm;
 mod ast;
 mod codegen;
 mod ctx;
+mod driver;
 mod infer;
+mod inline;
+mod native;
 
-pub use crate::codegen::compile;
+pub use crate::codegen::{compile, compile_incremental, CompiledCache};
+pub use crate::driver::{compile_str, run_str, Compiled};
 pub use crate::infer::Infer;
+pub use crate::inline::inline;
+pub use crate::native::{Native, NativeType};
@@ -0,0 +1,46 @@
+//! Functions provided by the program embedding tox.
+//! The compiler only needs their signature, the vm is given the implementation with
+//! `VM::register_native`
+use crate::infer::types::{Type, TypeCon};
+
+/// The types a native function can take and return
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NativeType {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Char,
+    Nil,
+}
+
+/// The signature of a function registered with the vm
+#[derive(Debug, Clone)]
+pub struct Native {
+    pub name: String,
+    pub params: Vec<NativeType>,
+    pub returns: NativeType,
+}
+
+impl NativeType {
+    pub(crate) fn ty(self) -> Type {
+        match self {
+            NativeType::Int => Type::App(TypeCon::Int, vec![]),
+            NativeType::Float => Type::App(TypeCon::Float, vec![]),
+            NativeType::Bool => Type::App(TypeCon::Bool, vec![]),
+            NativeType::Str => Type::App(TypeCon::Str, vec![]),
+            NativeType::Char => Type::App(TypeCon::Char, vec![]),
+            NativeType::Nil => Type::Nil,
+        }
+    }
+}
+
+impl Native {
+    pub fn new(name: &str, params: Vec<NativeType>, returns: NativeType) -> Self {
+        Native {
+            name: name.into(),
+            params,
+            returns,
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Compiles arbitrary source code.
+//! Invalid source should be reported as diagnostics
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = ::std::str::from_utf8(data) {
+        let _ = frontend::compile_str(source);
+    }
+});
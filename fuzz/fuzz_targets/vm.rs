@@ -0,0 +1,41 @@
+//! Runs arbitrary bytes as the body of `main`.
+//! Bytecode that passes the verifier should either run to completion or fail with an error
+#![no_main]
+use fnv::FnvHashMap;
+use libfuzzer_sys::fuzz_target;
+use std::io;
+use std::rc::Rc;
+use util::symbol::Symbol;
+use vm::{verify, Chunk, Function, Program, VM};
+
+fuzz_target!(|data: &[u8]| {
+    let chunk = Chunk {
+        code: data.to_vec(),
+        lines: vec![(0, data.len())],
+    };
+
+    if verify(&chunk).is_err() {
+        return;
+    }
+
+    let mut functions = FnvHashMap::default();
+    functions.insert(
+        Symbol(0),
+        Rc::new(Function {
+            name: Symbol(0),
+            body: chunk,
+            params: FnvHashMap::default(),
+        }),
+    );
+
+    let program = Program {
+        functions,
+        classes: FnvHashMap::default(),
+        names: FnvHashMap::default(),
+        constants: Vec::new(),
+    };
+
+    if let Ok(vm) = VM::new(Symbol(0), &program, ::std::ptr::null_mut()) {
+        let _ = vm.with_output(io::sink()).run();
+    }
+});
@@ -1,3 +1,4 @@
 mod op;
 
 pub use self::op::opcode::*;
+pub use self::op::{OpCode, UnknownOpcode};
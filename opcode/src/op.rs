@@ -1,136 +1,420 @@
 //! Instruction in the VM;
 //! Each Instruction in the VM is 8 bits
-pub mod opcode {
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+/// An instruction in the VM.
+/// Values are numbered contiguously from 0
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpCode {
     /// ILLEGAL INST
-    pub const IGL: u8 = 0;
+    Igl = 0,
     /// HLT
     /// Stops the running of the vm
-    pub const HLT: u8 = 1;
+    Hlt = 1,
     /// RETURN
     /// Returns the value on the top of the stack
-    pub const RETURN: u8 = 2;
+    Return = 2,
     /// CONSTANT
     /// Allocates a constant into the constant pool
-    pub const CONSTANT: u8 = 3;
+    Constant = 3,
     /// PRINT
     /// Prints the value on the top of the stack
-    pub const PRINT: u8 = 4;
+    Print = 4,
     /// NEGATEF $x
     /// Returns -$x .FLOATS ONLY
-    pub const NEGATEF: u8 = 5;
+    NegateF = 5,
     /// NEGATE $x
     /// Returns -$x
-    pub const NEGATE: u8 = 6;
+    Negate = 6,
     /// NIL
     /// Places nil onto the stack
-    pub const NIL: u8 = 7;
+    Nil = 7,
     /// TRUE
     /// Places true onto the stack
-    pub const TRUE: u8 = 8;
+    True = 8,
     /// FALSE
     /// Places false onto the stack
-    pub const FALSE: u8 = 9;
+    False = 9,
     /// NOT $x
     /// Returns !$x
-    pub const NOT: u8 = 10;
+    Not = 10,
     /// EQUAl $x $y
     /// Returns $x == $y
-    pub const EQUAL: u8 = 11;
+    Equal = 11,
     /// GREATER $x $y
     /// Returns $x > $y
-    pub const GREATER: u8 = 12;
-    pub const GREATERF: u8 = 13;
+    Greater = 12,
+    GreaterF = 13,
     /// LESS $x $y
     /// Returns $x < $y
-    pub const LESS: u8 = 14;
-    pub const LESSF: u8 = 15;
+    Less = 14,
+    LessF = 15,
     /// ADD $x $y
-    pub const ADD: u8 = 16;
+    Add = 16,
     /// ADD Float
-    pub const ADDF: u8 = 17;
+    AddF = 17,
     /// SUB $x $y
-    pub const SUB: u8 = 18;
+    Sub = 18,
     /// SUB Float
-    pub const SUBF: u8 = 19;
+    SubF = 19,
     /// MUL $x $y
-    pub const MUL: u8 = 20;
+    Mul = 20,
     /// MUL Float $x $y
-    pub const MULF: u8 = 21;
+    MulF = 21,
     /// DIV $x $y
-    pub const DIV: u8 = 22;
+    Div = 22,
     /// DIV Float $x $y
-    pub const DIVF: u8 = 23;
+    DivF = 23,
     /// JUMP $LOC
-    pub const JUMP: u8 = 24;
+    Jump = 24,
     /// GETLOCAL $slot
-    pub const GETLOCAL: u8 = 25;
+    GetLocal = 25,
     /// SETLOCAL $slot
-    pub const SETLOCAL: u8 = 26;
+    SetLocal = 26,
     /// CALL $func $num_args
-    pub const CALL: u8 = 27;
+    Call = 27,
     /// Jump to a location if true
-    pub const JUMPIF: u8 = 28;
+    JumpIf = 28,
     /// jumps to a location if false
-    pub const JUMPNOT: u8 = 29;
+    JumpNot = 29,
     /// LOOP $offset:u16
     /// decrease the ip by offset
-    pub const LOOP: u8 = 30;
+    Loop = 30,
     /// POP
     /// Remove the value from the stack
-    pub const POP: u8 = 31;
+    Pop = 31,
     /// CONCAT $x:str, $y:str
-    pub const CONCAT: u8 = 32;
+    Concat = 32,
     /// GETPARAM $slot
-    pub const GETPARAM: u8 = 33;
+    GetParam = 33,
     /// SETPARAM $slot
-    pub const SETPARAM: u8 = 34;
-    /// CALLCLOSURE $args
-    pub const CALLCLOSURE: u8 = 35;
+    SetParam = 34,
+    /// CALLVALUE $num_args
+    /// Pops a callable off the stack and calls it with $num_args
+    CallValue = 35,
     /// ARRAY
     /// Create an array object and put it on the stack
-    pub const ARRAY: u8 = 36;
+    Array = 36,
     /// INDEXARRAY
     /// Index an array
-    pub const INDEXARRAY: u8 = 37;
+    IndexArray = 37,
     /// INDEXSTRING
     /// index a string
-    pub const INDEXSTRING: u8 = 38;
+    IndexString = 38,
     /// GETPROPERTY $id
     /// Get the property of class with $id
-    pub const GETPROPERTY: u8 = 39;
+    GetProperty = 39,
     /// SETPROPERTY $id
     /// Set the property of class with $id
-    pub const SETPROPERTY: u8 = 40;
+    SetProperty = 40,
     /// GETMETHOD $id
     /// Get the class method with the id of $id
-    pub const GETMETHOD: u8 = 41;
+    GetMethod = 41,
     /// CLASSINSTANCE $id $num_properties
     /// Create a new ClassInstance object with the methods
     /// from the class $id and $num_properties
-    pub const CLASSINSTANCE: u8 = 42;
+    ClassInstance = 42,
     /// CALLINSTANCEMETHOD $id $arg_count
     /// Call the functions $id which takes $arg_count
-    pub const CALLINSTANCEMETHOD: u8 = 43;
+    CallInstanceMethod = 43,
     /// CALLSTATICMETHOD $id $arg_count
     /// Call the static class methods $id which takes $arg_count
-    pub const CALLSTATICMETHOD: u8 = 44;
-
-    pub const CALLNATIVE: u8 = 45;
-
+    CallStaticMethod = 44,
+    CallNative = 45,
     /// converts an int(i64)  to float(f64)
-    pub const INT2FLOAT: u8 = 46;
+    Int2Float = 46,
     /// converts an float(f64)  to int(i64)
     /// results in a loss of precession
-    pub const FLOAT2INT: u8 = 47;
+    Float2Int = 47,
     /// converts a boolean to int(i64)
-    pub const BOOL2INT: u8 = 49;
+    Bool2Int = 48,
+    Int2Str = 49,
+    Float2Str = 50,
+    Enum = 51,
+    /// declare an enum with associated data
+    EnumData = 52,
+    /// DUP
+    /// Pushes a copy of the value on the top of the stack
+    Dup = 53,
+    /// SWAP
+    /// Exchanges the top two values on the stack
+    Swap = 54,
+    /// DUP2
+    /// Pushes a copy of the top two values on the stack keeping their order
+    Dup2 = 55,
+    /// SETINDEXARRAY $array $index $value
+    /// Stores $value into the array at $index
+    SetIndexArray = 56,
+    /// LESSEQUALF $x $y
+    /// Returns $x <= $y .FLOATS ONLY
+    LessEqualF = 57,
+    /// GREATEREQUALF $x $y
+    /// Returns $x >= $y .FLOATS ONLY
+    GreaterEqualF = 58,
+    /// GETFUNCTION $id
+    /// Places the function $id onto the stack as a value
+    GetFunction = 59,
+    /// WRAPINT $kind $x
+    /// Truncates the int $x to the sized int $kind wrapping around on overflow
+    WrapInt = 60,
+    /// CHECKINT $kind $x
+    /// Errors if the int $x doesn't fit in the sized int $kind
+    CheckInt = 61,
+    /// converts a char to its code point as an int(i64)
+    Char2Int = 62,
+    /// converts an int(i64) code point to a char
+    /// errors if the int isn't a valid code point
+    Int2Char = 63,
+    Char2Str = 64,
+    /// ENUMTAG $enum
+    /// Pushes the tag of the enum $enum as an int
+    EnumTag = 65,
+    /// ENUMINNER $enum
+    /// Pushes the data stored in the enum $enum or nil if it has none
+    EnumInner = 66,
+    /// EPRINT
+    /// Prints the value on top of the stack to the error output
+    EPrint = 67,
+    /// LESSSTR $x $y
+    /// Returns $x < $y comparing the strings lexicographically
+    LessStr = 68,
+    /// GREATERSTR $x $y
+    /// Returns $x > $y comparing the strings lexicographically
+    GreaterStr = 69,
+    /// TUPLE $len
+    /// Create a tuple object from the top $len values and put it on the stack
+    Tuple = 70,
+    /// INDEXTUPLE $index
+    /// Pushes the item at $index of the tuple
+    IndexTuple = 71,
+    /// CHECKLEN $len
+    /// Errors if the array on top of the stack doesn't have $len items
+    CheckLen = 72,
+}
+
+/// A byte that isn't the value of any `OpCode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownOpcode(pub u8);
+
+impl TryFrom<u8> for OpCode {
+    type Error = UnknownOpcode;
+
+    fn try_from(byte: u8) -> Result<OpCode, UnknownOpcode> {
+        use self::opcode::*;
+
+        let opcode = match byte {
+            IGL => OpCode::Igl,
+            HLT => OpCode::Hlt,
+            RETURN => OpCode::Return,
+            CONSTANT => OpCode::Constant,
+            PRINT => OpCode::Print,
+            NEGATEF => OpCode::NegateF,
+            NEGATE => OpCode::Negate,
+            NIL => OpCode::Nil,
+            TRUE => OpCode::True,
+            FALSE => OpCode::False,
+            NOT => OpCode::Not,
+            EQUAL => OpCode::Equal,
+            GREATER => OpCode::Greater,
+            GREATERF => OpCode::GreaterF,
+            LESS => OpCode::Less,
+            LESSF => OpCode::LessF,
+            ADD => OpCode::Add,
+            ADDF => OpCode::AddF,
+            SUB => OpCode::Sub,
+            SUBF => OpCode::SubF,
+            MUL => OpCode::Mul,
+            MULF => OpCode::MulF,
+            DIV => OpCode::Div,
+            DIVF => OpCode::DivF,
+            JUMP => OpCode::Jump,
+            GETLOCAL => OpCode::GetLocal,
+            SETLOCAL => OpCode::SetLocal,
+            CALL => OpCode::Call,
+            JUMPIF => OpCode::JumpIf,
+            JUMPNOT => OpCode::JumpNot,
+            LOOP => OpCode::Loop,
+            POP => OpCode::Pop,
+            CONCAT => OpCode::Concat,
+            GETPARAM => OpCode::GetParam,
+            SETPARAM => OpCode::SetParam,
+            CALLVALUE => OpCode::CallValue,
+            ARRAY => OpCode::Array,
+            INDEXARRAY => OpCode::IndexArray,
+            INDEXSTRING => OpCode::IndexString,
+            GETPROPERTY => OpCode::GetProperty,
+            SETPROPERTY => OpCode::SetProperty,
+            GETMETHOD => OpCode::GetMethod,
+            CLASSINSTANCE => OpCode::ClassInstance,
+            CALLINSTANCEMETHOD => OpCode::CallInstanceMethod,
+            CALLSTATICMETHOD => OpCode::CallStaticMethod,
+            CALLNATIVE => OpCode::CallNative,
+            INT2FLOAT => OpCode::Int2Float,
+            FLOAT2INT => OpCode::Float2Int,
+            BOOL2INT => OpCode::Bool2Int,
+            INT2STR => OpCode::Int2Str,
+            FLOAT2STR => OpCode::Float2Str,
+            ENUM => OpCode::Enum,
+            ENUMDATA => OpCode::EnumData,
+            DUP => OpCode::Dup,
+            SWAP => OpCode::Swap,
+            DUP2 => OpCode::Dup2,
+            SETINDEXARRAY => OpCode::SetIndexArray,
+            LESSEQUALF => OpCode::LessEqualF,
+            GREATEREQUALF => OpCode::GreaterEqualF,
+            GETFUNCTION => OpCode::GetFunction,
+            WRAPINT => OpCode::WrapInt,
+            CHECKINT => OpCode::CheckInt,
+            CHAR2INT => OpCode::Char2Int,
+            INT2CHAR => OpCode::Int2Char,
+            CHAR2STR => OpCode::Char2Str,
+            ENUMTAG => OpCode::EnumTag,
+            ENUMINNER => OpCode::EnumInner,
+            EPRINT => OpCode::EPrint,
+            LESSSTR => OpCode::LessStr,
+            GREATERSTR => OpCode::GreaterStr,
+            TUPLE => OpCode::Tuple,
+            INDEXTUPLE => OpCode::IndexTuple,
+            CHECKLEN => OpCode::CheckLen,
+            _ => return Err(UnknownOpcode(byte)),
+        };
+
+        Ok(opcode)
+    }
+}
+
+impl Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown opcode {}", self.0)
+    }
+}
+
+/// The value of each `OpCode` as a byte.
+/// Used where instructions are read from or written into raw bytecode
+pub mod opcode {
+    use super::OpCode;
+
+    pub const IGL: u8 = OpCode::Igl as u8;
+    pub const HLT: u8 = OpCode::Hlt as u8;
+    pub const RETURN: u8 = OpCode::Return as u8;
+    pub const CONSTANT: u8 = OpCode::Constant as u8;
+    pub const PRINT: u8 = OpCode::Print as u8;
+    pub const NEGATEF: u8 = OpCode::NegateF as u8;
+    pub const NEGATE: u8 = OpCode::Negate as u8;
+    pub const NIL: u8 = OpCode::Nil as u8;
+    pub const TRUE: u8 = OpCode::True as u8;
+    pub const FALSE: u8 = OpCode::False as u8;
+    pub const NOT: u8 = OpCode::Not as u8;
+    pub const EQUAL: u8 = OpCode::Equal as u8;
+    pub const GREATER: u8 = OpCode::Greater as u8;
+    pub const GREATERF: u8 = OpCode::GreaterF as u8;
+    pub const LESS: u8 = OpCode::Less as u8;
+    pub const LESSF: u8 = OpCode::LessF as u8;
+    pub const ADD: u8 = OpCode::Add as u8;
+    pub const ADDF: u8 = OpCode::AddF as u8;
+    pub const SUB: u8 = OpCode::Sub as u8;
+    pub const SUBF: u8 = OpCode::SubF as u8;
+    pub const MUL: u8 = OpCode::Mul as u8;
+    pub const MULF: u8 = OpCode::MulF as u8;
+    pub const DIV: u8 = OpCode::Div as u8;
+    pub const DIVF: u8 = OpCode::DivF as u8;
+    pub const JUMP: u8 = OpCode::Jump as u8;
+    pub const GETLOCAL: u8 = OpCode::GetLocal as u8;
+    pub const SETLOCAL: u8 = OpCode::SetLocal as u8;
+    pub const CALL: u8 = OpCode::Call as u8;
+    pub const JUMPIF: u8 = OpCode::JumpIf as u8;
+    pub const JUMPNOT: u8 = OpCode::JumpNot as u8;
+    pub const LOOP: u8 = OpCode::Loop as u8;
+    pub const POP: u8 = OpCode::Pop as u8;
+    pub const CONCAT: u8 = OpCode::Concat as u8;
+    pub const GETPARAM: u8 = OpCode::GetParam as u8;
+    pub const SETPARAM: u8 = OpCode::SetParam as u8;
+    pub const CALLVALUE: u8 = OpCode::CallValue as u8;
+    pub const ARRAY: u8 = OpCode::Array as u8;
+    pub const INDEXARRAY: u8 = OpCode::IndexArray as u8;
+    pub const INDEXSTRING: u8 = OpCode::IndexString as u8;
+    pub const GETPROPERTY: u8 = OpCode::GetProperty as u8;
+    pub const SETPROPERTY: u8 = OpCode::SetProperty as u8;
+    pub const GETMETHOD: u8 = OpCode::GetMethod as u8;
+    pub const CLASSINSTANCE: u8 = OpCode::ClassInstance as u8;
+    pub const CALLINSTANCEMETHOD: u8 = OpCode::CallInstanceMethod as u8;
+    pub const CALLSTATICMETHOD: u8 = OpCode::CallStaticMethod as u8;
+    pub const CALLNATIVE: u8 = OpCode::CallNative as u8;
+    pub const INT2FLOAT: u8 = OpCode::Int2Float as u8;
+    pub const FLOAT2INT: u8 = OpCode::Float2Int as u8;
+    pub const BOOL2INT: u8 = OpCode::Bool2Int as u8;
+    pub const INT2STR: u8 = OpCode::Int2Str as u8;
+    pub const FLOAT2STR: u8 = OpCode::Float2Str as u8;
+    pub const ENUM: u8 = OpCode::Enum as u8;
+    pub const ENUMDATA: u8 = OpCode::EnumData as u8;
+    pub const DUP: u8 = OpCode::Dup as u8;
+    pub const SWAP: u8 = OpCode::Swap as u8;
+    pub const DUP2: u8 = OpCode::Dup2 as u8;
+    pub const SETINDEXARRAY: u8 = OpCode::SetIndexArray as u8;
+    pub const LESSEQUALF: u8 = OpCode::LessEqualF as u8;
+    pub const GREATEREQUALF: u8 = OpCode::GreaterEqualF as u8;
+    pub const GETFUNCTION: u8 = OpCode::GetFunction as u8;
+    pub const WRAPINT: u8 = OpCode::WrapInt as u8;
+    pub const CHECKINT: u8 = OpCode::CheckInt as u8;
+    pub const CHAR2INT: u8 = OpCode::Char2Int as u8;
+    pub const INT2CHAR: u8 = OpCode::Int2Char as u8;
+    pub const CHAR2STR: u8 = OpCode::Char2Str as u8;
+    pub const ENUMTAG: u8 = OpCode::EnumTag as u8;
+    pub const ENUMINNER: u8 = OpCode::EnumInner as u8;
+    pub const EPRINT: u8 = OpCode::EPrint as u8;
+    pub const LESSSTR: u8 = OpCode::LessStr as u8;
+    pub const GREATERSTR: u8 = OpCode::GreaterStr as u8;
+    pub const TUPLE: u8 = OpCode::Tuple as u8;
+    pub const INDEXTUPLE: u8 = OpCode::IndexTuple as u8;
+    pub const CHECKLEN: u8 = OpCode::CheckLen as u8;
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OpCode, UnknownOpcode};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn unknown_opcode() {
+        assert_eq!(OpCode::try_from(0xFE), Err(UnknownOpcode(0xFE)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut defined = 0;
+
+        for byte in 0..=255u8 {
+            if let Ok(opcode) = OpCode::try_from(byte) {
+                assert_eq!(opcode as u8, byte);
+                defined += 1;
+            }
+        }
+
+        assert_eq!(defined, 73);
+    }
+
+    #[test]
+    fn unique_and_contiguous() {
+        use super::opcode::*;
 
-    pub const INT2STR: u8 = 50;
+        let mut opcodes = vec![
+            IGL, HLT, RETURN, CONSTANT, PRINT, NEGATEF, NEGATE, NIL, TRUE, FALSE, NOT, EQUAL,
+            GREATER, GREATERF, LESS, LESSF, ADD, ADDF, SUB, SUBF, MUL, MULF, DIV, DIVF, JUMP,
+            GETLOCAL, SETLOCAL, CALL, JUMPIF, JUMPNOT, LOOP, POP, CONCAT, GETPARAM, SETPARAM,
+            CALLVALUE, ARRAY, INDEXARRAY, INDEXSTRING, GETPROPERTY, SETPROPERTY, GETMETHOD,
+            CLASSINSTANCE, CALLINSTANCEMETHOD, CALLSTATICMETHOD, CALLNATIVE, INT2FLOAT, FLOAT2INT,
+            BOOL2INT, INT2STR, FLOAT2STR, ENUM, ENUMDATA, DUP, SWAP, DUP2, SETINDEXARRAY,
+            LESSEQUALF, GREATEREQUALF, GETFUNCTION, WRAPINT, CHECKINT, CHAR2INT, INT2CHAR,
+            CHAR2STR, ENUMTAG, ENUMINNER, EPRINT, LESSSTR, GREATERSTR, TUPLE, INDEXTUPLE,
+            CHECKLEN,
+        ];
 
-    pub const FLOAT2STR: u8 = 51;
+        opcodes.sort();
 
-    pub const ENUM: u8 = 52;
+        let expected: Vec<u8> = (0..opcodes.len() as u8).collect();
 
-    // declare an enum with associated data
-    pub const ENUMDATA: u8 = 53;
+        assert_eq!(opcodes, expected);
+    }
 }
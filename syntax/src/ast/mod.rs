@@ -1,3 +1,5 @@
+use std::hash::{Hash, Hasher};
+use std::mem;
 use util::pos::Spanned;
 use util::symbol::Symbol;
 
@@ -52,14 +54,19 @@ pub struct TypeAlias {
 #[derive(Debug, Clone)]
 pub enum Statement {
     Block(Vec<Spanned<Statement>>),
-    Break,
-    Continue,
+    /// Breaks out of the innermost loop or the loop with the label.
+    /// Breaking out of a `loop` can give it a value
+    Break(Option<Spanned<Symbol>>, Option<Spanned<Expression>>),
+    Continue(Option<Spanned<Symbol>>),
+    /// A statement that runs when the enclosing block exits
+    Defer(Box<Spanned<Statement>>),
     Expr(Spanned<Expression>),
     For {
         init: Option<Box<Spanned<Statement>>>,
         cond: Option<Spanned<Expression>>,
         incr: Option<Spanned<Expression>>,
         body: Box<Spanned<Statement>>,
+        label: Option<Spanned<Symbol>>,
     },
 
     If {
@@ -70,15 +77,34 @@ pub enum Statement {
 
     Print(Spanned<Expression>),
 
+    EPrint(Spanned<Expression>),
+
     While {
         cond: Spanned<Expression>,
         body: Box<Spanned<Statement>>,
+        /// Used by `break` and `continue` to refer to an outer loop i.e `outer: while true {}`
+        label: Option<Spanned<Symbol>>,
     },
 
     VarDeclaration {
         ident: Spanned<Symbol>,
         ty: Option<Spanned<Type>>,
         expr: Option<Spanned<Expression>>,
+        /// Declared with `const` so it can't be assigned to
+        constant: bool,
+    },
+
+    /// `let (a, b) = pair;` binds each item of a tuple to a new variable
+    LetTuple {
+        idents: Vec<Spanned<Symbol>>,
+        expr: Spanned<Expression>,
+    },
+
+    /// `let [a, b] = array;` binds each item of an array to a new variable.
+    /// The array must have exactly as many items as there are variables
+    LetArray {
+        idents: Vec<Spanned<Symbol>>,
+        expr: Spanned<Expression>,
     },
 
     Return(Spanned<Expression>),
@@ -94,6 +120,10 @@ pub struct Field {
 pub struct FunctionParam {
     pub name: Spanned<Symbol>,
     pub ty: Spanned<Type>,
+    /// The value used when a call leaves out this param
+    pub default: Option<Spanned<Expression>>,
+    /// A rest param collects any extra arguments into an array i.e `...xs:int`
+    pub rest: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +141,11 @@ pub enum Expression {
         op: Spanned<Op>,
         rhs: Box<Spanned<Expression>>,
     },
+    /// A block that evaluates to its last expression i.e `{ let a = 1; a + 1 }`
+    Block {
+        statements: Vec<Spanned<Statement>>,
+        value: Box<Spanned<Expression>>,
+    },
 
     Call(Spanned<Call>),
 
@@ -144,10 +179,24 @@ pub enum Expression {
 
     Literal(Literal),
 
+    /// Runs the body until a `break` which gives the value of the loop
+    Loop {
+        body: Box<Spanned<Statement>>,
+        label: Option<Spanned<Symbol>>,
+    },
+
     Set {
         object: Box<Spanned<Expression>>,
         name: Spanned<Symbol>,
         value: Box<Spanned<Expression>>,
+        kind: Spanned<AssignOperator>,
+    },
+
+    SetSubScript {
+        target: Box<Spanned<Expression>>,
+        index: Box<Spanned<Expression>>,
+        value: Box<Spanned<Expression>>,
+        kind: Spanned<AssignOperator>,
     },
 
     Ternary {
@@ -155,6 +204,19 @@ pub enum Expression {
         then_branch: Box<Spanned<Expression>>,
         else_branch: Box<Spanned<Expression>>,
     },
+    /// `(1, "a", true)`
+    Tuple {
+        items: Vec<Spanned<Expression>>,
+    },
+    /// `pair.0` gets the item at the index of the tuple
+    TupleIndex {
+        tuple: Box<Spanned<Expression>>,
+        index: Spanned<usize>,
+    },
+    /// `expr?` returns early from the function if `expr` is a `None` or an `Err`
+    Try {
+        expr: Box<Spanned<Expression>>,
+    },
     Unary {
         op: Spanned<UnaryOp>,
         expr: Box<Spanned<Expression>>,
@@ -169,10 +231,33 @@ pub enum Expression {
     },
 }
 
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches a value equal to the expression
+    Expr(Spanned<Expression>),
+    /// `start..end` matches an int from start up to but not including end
+    Range(Spanned<Expression>, Spanned<Expression>),
+    /// `a | b` matches if any of the patterns match
+    Or(Vec<Spanned<Pattern>>),
+    /// `_` matches anything without binding it
+    Wildcard,
+    /// `name` matches anything and binds it to `name`
+    Bind(Spanned<Symbol>),
+    /// `Enum::Variant(pattern)` matches the variant if its data matches the inner pattern
+    Variant {
+        enum_name: Spanned<Symbol>,
+        variant: Spanned<Symbol>,
+        inner: Option<Box<Spanned<Pattern>>>,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct MatchArm {
-    pub pattern: Option<Spanned<Expression>>,
+    pub pattern: Spanned<Pattern>,
+    /// The condition in `pattern if cond => body`
+    pub guard: Option<Spanned<Expression>>,
     pub body: Spanned<Statement>,
+    /// Whether the pattern matches every value
     pub is_all: bool,
 }
 
@@ -203,9 +288,17 @@ pub struct ClassLiteral {
 #[derive(Debug, Clone)]
 pub struct Call {
     pub args: Vec<Spanned<Expression>>,
+    /// Arguments passed by param name i.e `f(y: 2, x: 1)`. These always follow the positional ones
+    pub named: Vec<NamedArg>,
     pub callee: Box<Spanned<Expression>>,
     pub types: Spanned<Vec<Spanned<Type>>>,
 }
+
+#[derive(Debug, Clone)]
+pub struct NamedArg {
+    pub name: Spanned<Symbol>,
+    pub value: Spanned<Expression>,
+}
 #[derive(Debug, Clone)]
 pub enum Type {
     /// Type that is an identifier i.e bool,int,float
@@ -219,6 +312,10 @@ pub enum Type {
     Nil,
     /// Type of a generic type i.e List<i32> Foo<List<List<i32>>>
     Generic(Spanned<Symbol>, Vec<Spanned<Type>>),
+    /// Type that can also be nil i.e int?
+    Nullable(Box<Spanned<Type>>),
+    /// Type of a tuple i.e (int,str)
+    Tuple(Vec<Spanned<Type>>),
 }
 
 #[derive(Debug, Clone)]
@@ -227,12 +324,13 @@ pub enum Literal {
     Float(f64),
     Int(i64),
     Str(String),
+    Char(char),
     True(bool),
     False(bool),
     Nil,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub enum Op {
     // The possible operators for the binary and unary expression
     BangEqual,
@@ -266,3 +364,19 @@ pub enum UnaryOp {
     Bang,
     Minus,
 }
+
+/// Floats can't derive `Hash` so a float literal is hashed by its bits
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+
+        match *self {
+            Literal::Float(float) => float.to_bits().hash(state),
+            Literal::Int(int) => int.hash(state),
+            Literal::Str(ref string) => string.hash(state),
+            Literal::Char(character) => character.hash(state),
+            Literal::True(boolean) | Literal::False(boolean) => boolean.hash(state),
+            Literal::Nil => (),
+        }
+    }
+}
@@ -18,6 +18,17 @@ impl<'a> Parser<'a> {
     pub(crate) fn next_token(&mut self) -> ParserResult<Spanned<Token<'a>>> {
         while let Some((start, ch)) = self.advance() {
             return match ch {
+                '.' if self.peek(char::is_numeric) && !self.after_value() => self.number(start),
+                '.' if self.peek(|ch| ch == '.') => {
+                    self.advance();
+
+                    if self.peek(|ch| ch == '.') {
+                        self.advance();
+                        Ok(spans(TokenType::DOTDOTDOT, start, start.shift('.').shift('.')))
+                    } else {
+                        Ok(spans(TokenType::DOTDOT, start, start.shift('.')))
+                    }
+                }
                 '.' => Ok(span(TokenType::DOT, start)),
                 '?' => Ok(span(TokenType::QUESTION, start)),
                 ';' => Ok(span(TokenType::SEMICOLON, start)),
@@ -28,6 +39,7 @@ impl<'a> Parser<'a> {
                 '(' => Ok(span(TokenType::LPAREN, start)),
                 ')' => Ok(span(TokenType::RPAREN, start)),
                 ',' => Ok(span(TokenType::COMMA, start)),
+                '_' if self.peek(is_letter_ch) => Ok(self.identifier(start)),
                 '_' => Ok(span(TokenType::UNDERSCORE, start)),
                 '|' => Ok(span(TokenType::BAR, start)),
                 '^' => Ok(span(TokenType::EXPONENTIAL, start)),
@@ -107,6 +119,13 @@ impl<'a> Parser<'a> {
                     }
                 }
 
+                '\'' => match self.char_literal(start) {
+                    Ok(token) => Ok(token),
+                    Err(_) => {
+                        continue; // error is reported in the function
+                    }
+                },
+
                 '"' => match self.string_literal(start) {
                     Ok(token) => Ok(token),
                     Err(_) => {
@@ -130,6 +149,13 @@ impl<'a> Parser<'a> {
                     }
                 }
 
+                'r' if self.peek(|ch| ch == '"' || ch == '#') => match self.raw_string(start) {
+                    Ok(token) => Ok(token),
+                    Err(_) => {
+                        continue; // error is reported in the function
+                    }
+                },
+
                 ch if ch.is_numeric() => self.number(start),
                 ch if is_letter_ch(ch) => Ok(self.identifier(start)),
                 ch if ch.is_whitespace() => continue,
@@ -218,11 +244,6 @@ impl<'a> Parser<'a> {
         self.reporter.error(msg.into(), span)
     }
 
-    /// Reporter an error with the given span
-    pub(crate) fn span_warn<T: Into<String>>(&mut self, msg: T, span: Span) {
-        self.reporter.warn(msg.into(), span)
-    }
-
     /// Method that handles a line comment
     pub(crate) fn line_comment(&mut self, start: Position) {
         let (_, _) = self.take_whilst(start, |ch| ch != '\n');
@@ -258,17 +279,29 @@ impl<'a> Parser<'a> {
     pub(crate) fn string_literal(&mut self, start: Position) -> ParserResult<Spanned<Token<'a>>> {
         let mut string = String::new();
         let mut last = None; // placement value
+        let mut valid = true; // an invalid escape has already been reported
 
         while let Some((next, ch)) = self.advance() {
             match ch {
                 '"' => {
                     let end = next.shift(ch);
 
-                    string.push('\0');
+                    if !valid {
+                        return Err(());
+                    }
 
                     return Ok(spans(TokenType::STRING(string), start, end));
                 }
 
+                '\\' => {
+                    last = Some(next);
+
+                    match self.escape(next) {
+                        Some(ch) => string.push(ch),
+                        None => valid = false,
+                    }
+                }
+
                 ch => {
                     last = Some(next); // the last thing in the string
                     string.push(ch)
@@ -281,46 +314,306 @@ impl<'a> Parser<'a> {
         Err(())
     }
 
-    /// Handles number,both ints and floats
-    pub(crate) fn number(&mut self, start: Position) -> ParserResult<Spanned<Token<'a>>> {
-        let (end, int) = self.take_whilst(start, char::is_numeric);
+    /// Handles a raw string i.e `r"C:\path"` or `r#"say "hi""#`.
+    /// Escapes are not processed and the string ends at a quote followed by as many `#` as it
+    /// started with
+    pub(crate) fn raw_string(&mut self, start: Position) -> ParserResult<Spanned<Token<'a>>> {
+        let mut hashes = 0;
 
-        let (token, start, end) = match self.lookahead {
-            Some((_, '.')) => {
-                self.advance();
+        while self.peek(|ch| ch == '#') {
+            self.advance();
+            hashes += 1;
+        }
+
+        let mut last = match self.advance() {
+            Some((next, '"')) => next,
+            Some((next, ch)) => {
+                let msg = format!("Expected `\"` found `{}`", ch);
+                self.error(msg, next);
+                return Err(());
+            }
+            None => {
+                self.error("Unclosed string", start);
+                return Err(());
+            }
+        };
+
+        let mut string = String::new();
+
+        while let Some((next, ch)) = self.advance() {
+            last = next;
+
+            if ch != '"' {
+                string.push(ch);
+                continue;
+            }
+
+            let mut end = next.shift(ch);
+            let mut closing = 0;
+
+            while closing < hashes && self.peek(|ch| ch == '#') {
+                let (next, ch) = self.advance().unwrap();
+                end = next.shift(ch);
+                closing += 1;
+            }
+
+            if closing == hashes {
+                return Ok(spans(TokenType::STRING(string), start, end));
+            }
+
+            // a quote with too few `#` is part of the string
+            string.push('"');
+
+            for _ in 0..closing {
+                string.push('#');
+            }
+        }
 
-                let (end, float) = self.take_whilst(start, char::is_numeric);
+        self.error("Unclosed string", last);
 
-                match self.lookahead {
-                    Some((pos, ch)) if ch.is_alphabetic() => {
-                        let msg = format!("Unexpected char {}", ch);
-                        self.error(msg, pos);
-                        return Err(()); // Rejects floats like 10.k
+        Err(())
+    }
+
+    /// Handles a char i.e `'a'` or `'\n'`
+    pub(crate) fn char_literal(&mut self, start: Position) -> ParserResult<Spanned<Token<'a>>> {
+        let ch = match self.advance() {
+            Some((next, '\\')) => match self.escape(next) {
+                Some(ch) => ch,
+                None => {
+                    if self.peek(|ch| ch == '\'') {
+                        self.advance();
                     }
 
-                    _ => (TokenType::FLOAT(float.parse().unwrap()), start, end),
+                    return Err(());
                 }
+            },
+            Some((next, '\'')) => {
+                self.spanned_error("Empty char literal", start, next.shift('\''));
+                return Err(());
             }
+            Some((_, ch)) => ch,
+            None => {
+                self.error("Unclosed char literal", start);
+                return Err(());
+            }
+        };
 
-            Some((pos, ch)) if ch.is_alphabetic() => {
-                let msg = format!("Unexpected char {}", ch);
-                self.error(msg, pos);
-                return Err(()); // Rejects number like 1k
+        match self.advance() {
+            Some((next, '\'')) => Ok(spans(TokenType::CHAR(ch), start, next.shift('\''))),
+            Some((next, _)) => {
+                // skip to the closing quote so the rest of the literal isn't lexed
+                let (end, _) = self.take_whilst(next, |ch| ch != '\'' && ch != '\n');
+
+                if self.peek(|ch| ch == '\'') {
+                    self.advance();
+                }
+
+                self.spanned_error("A char literal can only contain one character", start, end);
+                Err(())
             }
-            None | Some(_) => {
-                if let Ok(val) = int.parse() {
-                    (TokenType::INT(val), start, end)
-                } else {
-                    let msg = format!("`{}` cannot fit into a int.", int);
-                    self.spanned_error(msg, start, end);
-                    return Err(());
+            None => {
+                self.error("Unclosed char literal", start);
+                Err(())
+            }
+        }
+    }
+
+    /// Handles an escape sequence within a string.
+    /// Returns `None` if the escape is invalid
+    pub(crate) fn escape(&mut self, start: Position) -> Option<char> {
+        let (pos, ch) = self.advance()?; // an unclosed string is reported by the caller
+
+        match ch {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'u' => self.unicode_escape(start),
+            ch => {
+                let msg = format!("Unknown escape sequence `\\{}`", ch);
+                self.spanned_error(msg, start, pos.shift(ch));
+                None
+            }
+        }
+    }
+
+    /// Handles a unicode escape i.e `\u{1F600}`
+    pub(crate) fn unicode_escape(&mut self, start: Position) -> Option<char> {
+        let mut digits = String::new();
+
+        if !self.peek(|ch| ch == '{') {
+            let end = self.lookahead.map_or(self.end, |(pos, _)| pos);
+            self.spanned_error("Expected `{` after `\\u`", start, end);
+            return None;
+        }
+
+        self.advance();
+
+        loop {
+            match self.lookahead {
+                Some((end, '}')) => {
+                    self.advance();
+
+                    let escaped = u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(::std::char::from_u32);
+
+                    if escaped.is_none() {
+                        let msg = format!("Invalid unicode escape `\\u{{{}}}`", digits);
+                        self.spanned_error(msg, start, end.shift('}'));
+                    }
+
+                    return escaped;
                 }
+
+                Some((_, ch)) if ch.is_digit(16) && digits.len() < 6 => {
+                    self.advance();
+                    digits.push(ch);
+                }
+
+                Some((end, _)) => {
+                    self.spanned_error("Unclosed unicode escape", start, end);
+                    return None;
+                }
+
+                None => return None,
             }
+        }
+    }
+
+    /// Handles number,both ints and floats.
+    /// Digits can be separated by `_` i.e `1_000_000`.
+    /// Floats can be written as `1.5`, `.5`, `5.` or with an exponent i.e `1.5e-3`
+    pub(crate) fn number(&mut self, start: Position) -> ParserResult<Spanned<Token<'a>>> {
+        if self.input[start.absolute..].starts_with('0') {
+            match self.lookahead {
+                Some((_, 'x')) => return self.radix_int(start, 16),
+                Some((_, 'o')) => return self.radix_int(start, 8),
+                Some((_, 'b')) => return self.radix_int(start, 2),
+                _ => (),
+            }
+        }
+
+        let mut is_float = self.input[start.absolute..].starts_with('.'); // i.e `.5`
+        let (mut end, _) = self.take_whilst(start, is_digit);
+
+        let is_range = self
+            .lookahead
+            .map_or(false, |(pos, _)| self.input[pos.absolute..].starts_with("..")); // i.e `1..5`
+
+        // The `0` in `pair.0.1` is a tuple index and not the start of a float
+        let is_index = self
+            .past_tokens
+            .back()
+            .map_or(false, |token| token.value.token == TokenType::DOT);
+
+        if !is_float && !is_range && !is_index && self.peek(|ch| ch == '.') {
+            self.advance();
+            end = self.take_whilst(start, is_digit).0;
+            is_float = true;
+        }
+
+        if self.peek(|ch| ch == 'e' || ch == 'E') {
+            self.advance();
+
+            if self.peek(|ch| ch == '+' || ch == '-') {
+                self.advance();
+            }
+
+            if !self.peek(char::is_numeric) {
+                let end = self.lookahead.map_or(self.end, |(pos, _)| pos);
+                let msg = format!(
+                    "Expected digits in the exponent of `{}`",
+                    self.slice(start, end)
+                );
+                self.spanned_error(msg, start, end);
+                return Err(());
+            }
+
+            end = self.take_whilst(start, is_digit).0;
+            is_float = true;
+        }
+
+        if let Some((pos, ch)) = self.lookahead {
+            if ch.is_alphabetic() {
+                let msg = format!("Unexpected char {}", ch);
+                self.error(msg, pos);
+                return Err(()); // Rejects numbers like 1k or 10.k
+            }
+        }
+
+        let literal = self.slice(start, end);
+        let digits = literal.replace('_', "");
+
+        let token = if is_float {
+            TokenType::FLOAT(digits.parse().unwrap())
+        } else if let Ok(val) = digits.parse() {
+            TokenType::INT(val)
+        } else {
+            let msg = format!("`{}` cannot fit into a int.", literal);
+            self.spanned_error(msg, start, end);
+            return Err(());
         };
 
         Ok(spans(token, start, end))
     }
 
+    /// Handles an int written in hex, octal or binary i.e `0xFF`, `0o77` or `0b1010`
+    pub(crate) fn radix_int(
+        &mut self,
+        start: Position,
+        radix: u32,
+    ) -> ParserResult<Spanned<Token<'a>>> {
+        let (prefix, _) = self.advance().unwrap(); // Eats the 'x','o' or 'b'
+
+        let (end, digits) = match self.lookahead {
+            Some((next, _)) => self.take_whilst(next, |ch| ch.is_alphanumeric() || ch == '_'),
+            None => (self.end, ""),
+        };
+
+        let literal = self.slice(start, end);
+        let digits = digits.replace('_', "");
+
+        if digits.is_empty() {
+            let msg = format!("Expected digits after `{}`", literal);
+            self.spanned_error(msg, start, prefix.shift('x'));
+            return Err(());
+        }
+
+        if let Some(invalid) = digits.chars().find(|ch| !ch.is_digit(radix)) {
+            let msg = format!("Invalid digit `{}` in `{}`", invalid, literal);
+            self.spanned_error(msg, start, end);
+            return Err(());
+        }
+
+        match i64::from_str_radix(&digits, radix) {
+            Ok(val) => Ok(spans(TokenType::INT(val), start, end)),
+            Err(_) => {
+                let msg = format!("`{}` cannot fit into a int.", literal);
+                self.spanned_error(msg, start, end);
+                Err(())
+            }
+        }
+    }
+
+    /// Whether the last token lexed ends a value so a `.` followed by digits is a tuple index
+    /// i.e `pair.0` and not the float `.0`
+    fn after_value(&self) -> bool {
+        match self.past_tokens.back() {
+            Some(token) => match token.value.token {
+                TokenType::IDENTIFIER(_)
+                | TokenType::INT(_)
+                | TokenType::RPAREN
+                | TokenType::RBRACKET => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
     /// Handles any identifier.
     // Newkeywords should be added to the look_up_identifier function
     pub(crate) fn identifier(&mut self, start: Position) -> Spanned<Token<'a>> {
@@ -350,6 +643,11 @@ fn spans(token: TokenType, start: Position, end: Position) -> Spanned<Token> {
     }
 }
 
+#[inline]
+fn is_digit(ch: char) -> bool {
+    ch.is_numeric() || ch == '_'
+}
+
 #[inline]
 fn is_letter_ch(ch: char) -> bool {
     ch.is_alphanumeric() || ch == '_'
@@ -362,6 +660,7 @@ fn look_up_identifier(id: &str) -> TokenType {
         "class" => TokenType::CLASS,
         "extends" => TokenType::EXTENDS,
         "print" => TokenType::PRINT,
+        "eprint" => TokenType::EPRINT,
         "type" => TokenType::TYPE,
         "as" => TokenType::AS,
         "match" => TokenType::MATCH,
@@ -369,11 +668,14 @@ fn look_up_identifier(id: &str) -> TokenType {
         // Functions and vars
         "fn" => TokenType::FUNCTION,
         "let" => TokenType::LET,
+        "const" => TokenType::CONST,
+        "defer" => TokenType::DEFER,
         // Control Flow
         "if" => TokenType::IF,
         "else" => TokenType::ELSE,
         "for" => TokenType::FOR,
         "while" => TokenType::WHILE,
+        "loop" => TokenType::LOOP,
         "return" => TokenType::RETURN,
         "break" => TokenType::BREAK,
         "continue" => TokenType::CONTINUE,
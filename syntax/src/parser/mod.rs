@@ -30,6 +30,8 @@ pub struct Parser<'a> {
     parsing_cond: bool,
     /// Flag that manages whetere we are in a match_arm
     parsing_match_arm: bool,
+    /// The labels of the loops being parsed
+    labels: Vec<Symbol>,
 }
 
 impl<'a> Parser<'a> {
@@ -49,6 +51,7 @@ impl<'a> Parser<'a> {
             symbols,
             parsing_cond: false,
             parsing_match_arm: false,
+            labels: Vec::new(),
         };
 
         past_tokens.push_back(parser.next().unwrap());
@@ -102,6 +105,19 @@ impl<'a> Parser<'a> {
                     }
                 }
             } else {
+                let unexpected = self
+                    .past_tokens
+                    .front()
+                    .map(|token| (token.value.token.to_string(), token.span));
+
+                if let Some((token, span)) = unexpected {
+                    let msg = format!(
+                        "Expected `fn`, `class`, `enum` or `type` but instead found `{}`",
+                        token
+                    );
+                    self.span_error(msg, span);
+                }
+
                 self.synchronize()?;
                 had_error = true;
             }
@@ -258,13 +274,72 @@ impl<'a> Parser<'a> {
     }
 
     fn recognise(&mut self, expected: TokenType) -> bool {
-        match self.past_tokens.back() {
+        match self.past_tokens.front() {
             Some(token) => token.value.token == expected,
             None => false,
         }
         // Ok(self.next()?.value.token == token)
     }
 
+    fn recognise_identifier(&mut self) -> bool {
+        match self.past_tokens.front() {
+            Some(Spanned {
+                value:
+                    Token {
+                        token: TokenType::IDENTIFIER(_),
+                    },
+                ..
+            }) => true,
+            _ => false,
+        }
+    }
+
+    /// The token after the next token.
+    /// Lexes the token ahead of time if it hasn't been seen yet
+    fn second(&mut self) -> ParserResult<&TokenType<'a>> {
+        if self.past_tokens.len() < 2 {
+            let token = self.next_token()?;
+            self.past_tokens.push_back(token);
+        }
+
+        Ok(&self.past_tokens[1].value.token)
+    }
+
+    /// Checks if the token after the next token is `token`
+    fn second_is(&mut self, token: &TokenType<'a>) -> ParserResult<bool> {
+        Ok(self.second()? == token)
+    }
+
+    /// Checks if the token after the next token ends a match arm's pattern
+    fn second_ends_pattern(&mut self) -> ParserResult<bool> {
+        Ok(match *self.second()? {
+            TokenType::MATCHARROW | TokenType::IF | TokenType::RPAREN | TokenType::ASSIGN => true,
+            _ => false,
+        })
+    }
+
+    /// Checks if the token after the next token can start an expression
+    fn second_starts_expression(&mut self) -> ParserResult<bool> {
+        Ok(match *self.second()? {
+            TokenType::IDENTIFIER(_)
+            | TokenType::INT(_)
+            | TokenType::FLOAT(_)
+            | TokenType::STRING(_)
+            | TokenType::CHAR(_)
+            | TokenType::TRUE(_)
+            | TokenType::FALSE(_)
+            | TokenType::NIL
+            | TokenType::MINUS
+            | TokenType::BANG
+            | TokenType::LPAREN
+            | TokenType::LBRACKET
+            | TokenType::BAR
+            | TokenType::FUNCTION
+            | TokenType::MATCH => true,
+            _ => false,
+        })
+    }
+
     fn get_unary_op(&mut self) -> ParserResult<Spanned<UnaryOp>> {
         get_unary_op!(self,{
             BANG => Bang,
@@ -441,18 +516,52 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses a type followed by an optional `?` that makes it nullable
     fn parse_type(&mut self) -> ParserResult<Spanned<Type>> {
+        let ty = self.parse_single_type()?;
+
+        if self.recognise(TokenType::QUESTION) {
+            let close_span = self.consume_get_span(&TokenType::QUESTION, "Expected '?' ")?;
+
+            return Ok(Spanned {
+                span: ty.span.to(close_span),
+                value: Type::Nullable(Box::new(ty)),
+            });
+        }
+
+        Ok(ty)
+    }
+
+    fn parse_single_type(&mut self) -> ParserResult<Spanned<Type>> {
         if self.recognise(TokenType::NIL) {
             Ok(Spanned {
                 value: Type::Nil,
                 span: self.consume_get_span(&TokenType::NIL, "Expected 'nil' ")?,
             })
-        } else if self.recognise(TokenType::RPAREN) {
-            self.next()?;
+        } else if self.recognise(TokenType::LBRACKET) {
+            let open_span = self.consume_get_span(&TokenType::LBRACKET, "Expected '[' ")?;
             let ty = self.parse_type()?;
             Ok(Spanned {
                 value: Type::Arr(Box::new(ty)),
-                span: self.consume_get_span(&TokenType::RBRACKET, "Expected ']' ")?,
+                span: open_span.to(self.consume_get_span(&TokenType::RBRACKET, "Expected ']' ")?),
+            })
+        } else if self.recognise(TokenType::LPAREN) {
+            let open_span = self.consume_get_span(&TokenType::LPAREN, "Expected '(' ")?;
+            let mut types = Vec::new();
+
+            loop {
+                types.push(self.parse_type()?);
+
+                if self.recognise(TokenType::COMMA) {
+                    self.next()?;
+                } else {
+                    break;
+                }
+            }
+
+            Ok(Spanned {
+                value: Type::Tuple(types),
+                span: open_span.to(self.consume_get_span(&TokenType::RPAREN, "Expected ')' ")?),
             })
         } else if self.recognise(TokenType::FUNCTION) {
             let open_span = self.consume_get_span(&TokenType::FUNCTION, "Expected 'fun' ")?;
@@ -576,17 +685,56 @@ impl<'a> Parser<'a> {
 
                 let msg = format!("Expected a {} type name", kind);
 
+                let rest = kind == "function" && self.recognise(TokenType::DOTDOTDOT);
+
+                if rest {
+                    self.next()?;
+                }
+
                 let (open_span, name) = self.consume_get_symbol_and_span(&msg)?;
 
                 self.consume(&TokenType::COLON, "Expected a colon")?;
 
                 let ty = self.parse_type()?;
 
+                let mut span = open_span.to(ty.get_span());
+
+                let default = if kind == "function" && !rest && self.recognise(TokenType::ASSIGN) {
+                    self.next()?;
+
+                    let default = self.parse_expression()?;
+                    span = span.to(default.get_span());
+                    Some(default)
+                } else {
+                    None
+                };
+
+                let follows_default = params
+                    .iter()
+                    .any(|param: &Spanned<FunctionParam>| param.value.default.is_some());
+
+                if default.is_none() && follows_default {
+                    self.span_error("Params with a default value must come last", span);
+                }
+
                 params.push(Spanned {
-                    span: open_span.to(ty.get_span()),
-                    value: FunctionParam { name, ty },
+                    span,
+                    value: FunctionParam {
+                        name,
+                        ty,
+                        default,
+                        rest,
+                    },
                 });
 
+                if rest {
+                    if self.recognise(TokenType::COMMA) {
+                        self.span_error("A rest param must be the last param", span);
+                    }
+
+                    break;
+                }
+
                 if self.recognise(TokenType::COMMA) {
                     self.next()?;
                 } else {
@@ -606,7 +754,7 @@ impl<'a> Parser<'a> {
     pub fn parse_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         if self.recognise(TokenType::LBRACE) {
             self.parse_block()
-        } else if self.recognise(TokenType::LET) {
+        } else if self.recognise(TokenType::LET) || self.recognise(TokenType::CONST) {
             self.parse_var_declaration()
         } else if self.recognise(TokenType::BREAK) {
             self.parse_break_statement()
@@ -617,20 +765,38 @@ impl<'a> Parser<'a> {
         } else if self.recognise(TokenType::IF) {
             self.parse_if_statement()
         } else if self.recognise(TokenType::DO) {
-            self.parse_do_statement()
+            self.parse_do_statement(None)
         } else if self.recognise(TokenType::WHILE) {
-            self.parse_while_statement()
+            self.parse_while_statement(None)
         } else if self.recognise(TokenType::FOR) {
-            self.parse_for_statement()
+            self.parse_for_statement(None)
+        } else if self.recognise(TokenType::LOOP) {
+            self.parse_loop_statement(None)
+        } else if self.recognise_identifier() && self.second_is(&TokenType::COLON)? {
+            self.parse_labeled_statement()
         } else if self.recognise(TokenType::PRINT) {
             self.parse_print_statement()
+        } else if self.recognise(TokenType::EPRINT) {
+            self.parse_eprint_statement()
+        } else if self.recognise(TokenType::DEFER) {
+            self.parse_defer_statement()
         } else {
             self.parse_expression_statement()
         }
     }
 
     fn parse_var_declaration(&mut self) -> ParserResult<Spanned<Statement>> {
-        let open_span = self.consume_get_span(&TokenType::LET, "Expected 'var' ")?;
+        let constant = self.recognise(TokenType::CONST);
+
+        let open_span = if constant {
+            self.consume_get_span(&TokenType::CONST, "Expected 'const' ")?
+        } else {
+            self.consume_get_span(&TokenType::LET, "Expected 'var' ")?
+        };
+
+        if !constant && (self.recognise(TokenType::LPAREN) || self.recognise(TokenType::LBRACKET)) {
+            return self.parse_let_destructure(open_span);
+        }
 
         let ident = self.consume_get_symbol("Expected an IDENTIFIER after a 'var' ")?;
 
@@ -643,6 +809,10 @@ impl<'a> Parser<'a> {
         };
 
         let expr = if self.recognise(TokenType::SEMICOLON) {
+            if constant {
+                self.span_error("A const must be given a value", ident.span);
+            }
+
             None
         } else {
             self.consume(&TokenType::ASSIGN, "Expected '='")?;
@@ -653,7 +823,52 @@ impl<'a> Parser<'a> {
 
         Ok(Spanned {
             span: open_span.to(close_span),
-            value: Statement::VarDeclaration { ident, ty, expr },
+            value: Statement::VarDeclaration {
+                ident,
+                ty,
+                expr,
+                constant,
+            },
+        })
+    }
+
+    /// Parses the rest of `let (a, b) = pair;` or `let [a, b] = array;`
+    fn parse_let_destructure(&mut self, open_span: Span) -> ParserResult<Spanned<Statement>> {
+        let array = self.recognise(TokenType::LBRACKET);
+
+        self.next()?; // eat the `(` or `[`
+
+        let mut idents = Vec::new();
+
+        loop {
+            idents.push(self.consume_get_symbol("Expected an IDENTIFIER")?);
+
+            if self.recognise(TokenType::COMMA) {
+                self.next()?;
+            } else {
+                break;
+            }
+        }
+
+        if array {
+            self.consume(&TokenType::RBRACKET, "Expected ']'")?;
+        } else {
+            self.consume(&TokenType::RPAREN, "Expected ')'")?;
+        }
+
+        self.consume(&TokenType::ASSIGN, "Expected '='")?;
+
+        let expr = self.parse_expression()?;
+
+        let close_span = self.consume_get_span(&TokenType::SEMICOLON, "Expected ';'")?;
+
+        Ok(Spanned {
+            span: open_span.to(close_span),
+            value: if array {
+                Statement::LetArray { idents, expr }
+            } else {
+                Statement::LetTuple { idents, expr }
+            },
         })
     }
 
@@ -676,20 +891,127 @@ impl<'a> Parser<'a> {
 
     fn parse_break_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         self.consume_get_span(&TokenType::BREAK, "Expected a 'break' ")?;
+
+        // An identifier is only a label if a loop has it, otherwise it's the value of the `break`
+        let label = if self.recognise_label() {
+            Some(self.consume_get_symbol("Expected a label")?)
+        } else {
+            None
+        };
+
+        let value = if self.recognise(TokenType::SEMICOLON) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+
         Ok(Spanned {
-            value: Statement::Break,
+            value: Statement::Break(label, value),
             span: self.consume_get_span(&TokenType::SEMICOLON, "Expected ';' ")?,
         })
     }
 
     fn parse_continue_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         self.consume_get_span(&TokenType::CONTINUE, "Expected 'continue' ")?;
+
+        let label = if self.recognise_identifier() {
+            let label = self.consume_get_symbol("Expected a label")?;
+
+            if !self.labels.contains(&label.value) {
+                let msg = format!("Undefined label `{}`", self.symbols.name(label.value));
+                self.span_error(msg, label.span);
+                return Err(());
+            }
+
+            Some(label)
+        } else {
+            None
+        };
+
         Ok(Spanned {
-            value: Statement::Continue,
+            value: Statement::Continue(label),
             span: self.consume_get_span(&TokenType::SEMICOLON, "Expected ';' ")?,
         })
     }
 
+    /// Checks if the next token is the label of a loop being parsed
+    fn recognise_label(&mut self) -> bool {
+        let name = match self.past_tokens.front() {
+            Some(Spanned {
+                value:
+                    Token {
+                        token: TokenType::IDENTIFIER(name),
+                    },
+                ..
+            }) => *name,
+            _ => return false,
+        };
+
+        let symbol = self.symbols.symbol(name);
+
+        self.labels.contains(&symbol)
+    }
+
+    fn parse_labeled_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+        let label = self.consume_get_symbol("Expected a label")?;
+
+        self.consume(&TokenType::COLON, "Expected ':' after a label")?;
+
+        self.labels.push(label.value);
+
+        let statement = if self.recognise(TokenType::WHILE) {
+            self.parse_while_statement(Some(label))
+        } else if self.recognise(TokenType::DO) {
+            self.parse_do_statement(Some(label))
+        } else if self.recognise(TokenType::FOR) {
+            self.parse_for_statement(Some(label))
+        } else if self.recognise(TokenType::LOOP) {
+            self.parse_loop_statement(Some(label))
+        } else {
+            self.span_error("Only a loop can be given a label", label.span);
+            Err(())
+        };
+
+        self.labels.pop();
+
+        statement
+    }
+
+    /// Parses a `loop` used as a statement, the `;` after it is optional
+    fn parse_loop_statement(
+        &mut self,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Statement>> {
+        let open_span = self.consume_get_span(&TokenType::LOOP, "Expected 'loop' ")?;
+
+        let expr = self.parse_loop(open_span, label)?;
+
+        if self.recognise(TokenType::SEMICOLON) {
+            self.next()?;
+        }
+
+        Ok(Spanned {
+            span: expr.span,
+            value: Statement::Expr(expr),
+        })
+    }
+
+    fn parse_loop(
+        &mut self,
+        open_span: Span,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Expression>> {
+        let body = self.parse_statement()?;
+
+        Ok(Spanned {
+            span: open_span.to(body.span),
+            value: Expression::Loop {
+                body: Box::new(body),
+                label,
+            },
+        })
+    }
+
     pub fn parse_expression_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         let expr = self.parse_expression()?;
 
@@ -714,6 +1036,28 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_eprint_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+        let open_span = self.consume_get_span(&TokenType::EPRINT, "Expected 'eprint' ")?;
+
+        let expr = self.parse_expression()?;
+
+        Ok(Spanned {
+            span: open_span.to(self.consume_get_span(&TokenType::SEMICOLON, "Expected ';' ")?),
+            value: Statement::EPrint(expr),
+        })
+    }
+
+    fn parse_defer_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+        let open_span = self.consume_get_span(&TokenType::DEFER, "Expected 'defer' ")?;
+
+        let statement = self.parse_statement()?;
+
+        Ok(Spanned {
+            span: open_span.to(statement.span),
+            value: Statement::Defer(Box::new(statement)),
+        })
+    }
+
     fn parse_return_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         let open_span = self.consume_get_span(&TokenType::RETURN, "Expected 'return' ")?;
 
@@ -739,9 +1083,16 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_while_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+    fn parse_while_statement(
+        &mut self,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Statement>> {
         let open_span = self.consume_get_span(&TokenType::WHILE, "Expected 'while' ")?;
 
+        if self.recognise(TokenType::LET) {
+            return self.parse_while_let_statement(open_span, label);
+        }
+
         self.parsing_cond = true;
         let cond = self.parse_expression()?;
         self.parsing_cond = false;
@@ -753,11 +1104,49 @@ impl<'a> Parser<'a> {
             value: Statement::While {
                 cond,
                 body: Box::new(body),
+                label,
             },
         })
     }
 
-    fn parse_do_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+    /// `while let pattern = expr {}` is parsed as `while true {}` around a match on `expr`
+    /// that breaks out of the loop once the pattern stops matching
+    fn parse_while_let_statement(
+        &mut self,
+        open_span: Span,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Statement>> {
+        let (pattern, cond) = self.parse_let_condition()?;
+
+        let body = self.parse_statement()?;
+
+        let span = open_span.to(body.span);
+
+        let stop = Spanned {
+            span: cond.span,
+            value: Statement::Break(None, None),
+        };
+
+        Ok(Spanned {
+            span,
+            value: Statement::While {
+                cond: Spanned {
+                    span: open_span,
+                    value: Expression::Literal(Literal::True(true)),
+                },
+                body: Box::new(Spanned {
+                    span,
+                    value: Statement::Expr(let_match(pattern, cond, body, stop, span)),
+                }),
+                label,
+            },
+        })
+    }
+
+    fn parse_do_statement(
+        &mut self,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Statement>> {
         let open_span = self.consume_get_span(&TokenType::DO, "Expected 'do' ")?;
 
         let body = self.parse_statement()?;
@@ -771,6 +1160,7 @@ impl<'a> Parser<'a> {
             value: Statement::While {
                 cond,
                 body: Box::new(body),
+                label,
             },
         })
     }
@@ -778,6 +1168,10 @@ impl<'a> Parser<'a> {
     fn parse_if_statement(&mut self) -> ParserResult<Spanned<Statement>> {
         let open_span = self.consume_get_span(&TokenType::IF, "Expected 'if' ")?;
 
+        if self.recognise(TokenType::LET) {
+            return self.parse_if_let_statement(open_span);
+        }
+
         self.parsing_cond = true;
         let cond = self.parse_expression()?;
         self.parsing_cond = false;
@@ -804,7 +1198,55 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_for_statement(&mut self) -> ParserResult<Spanned<Statement>> {
+    /// `if let pattern = expr {} else {}` is parsed as a match on `expr`
+    /// with a wildcard arm for the else branch
+    fn parse_if_let_statement(&mut self, open_span: Span) -> ParserResult<Spanned<Statement>> {
+        let (pattern, cond) = self.parse_let_condition()?;
+
+        let then = self.parse_statement()?;
+
+        let otherwise = if self.recognise(TokenType::ELSE) {
+            self.next()?;
+
+            self.parse_statement()?
+        } else {
+            Spanned {
+                span: then.span,
+                value: Statement::Block(vec![]),
+            }
+        };
+
+        let span = open_span.to(otherwise.span);
+
+        Ok(Spanned {
+            span,
+            value: Statement::Expr(let_match(pattern, cond, then, otherwise, span)),
+        })
+    }
+
+    /// Parses the `let pattern = expr` of an `if let` or a `while let`
+    fn parse_let_condition(&mut self) -> ParserResult<(Spanned<Pattern>, Spanned<Expression>)> {
+        self.consume(&TokenType::LET, "Expected 'let' ")?;
+
+        let parsing_match_arm = self.parsing_match_arm;
+
+        self.parsing_match_arm = true; // stops the `=` being parsed as an assignment
+        let pattern = self.parse_pattern()?;
+        self.parsing_match_arm = parsing_match_arm;
+
+        self.consume(&TokenType::ASSIGN, "Expected '=' ")?;
+
+        self.parsing_cond = true;
+        let cond = self.parse_expression()?;
+        self.parsing_cond = false;
+
+        Ok((pattern, cond))
+    }
+
+    fn parse_for_statement(
+        &mut self,
+        label: Option<Spanned<Symbol>>,
+    ) -> ParserResult<Spanned<Statement>> {
         let open_span = self.consume_get_span(&TokenType::FOR, "Expected 'for' ")?;
 
         self.consume(&TokenType::LPAREN, "Expected '(' after 'for'")?;
@@ -844,6 +1286,7 @@ impl<'a> Parser<'a> {
                 cond,
                 incr,
                 body: Box::new(body),
+                label,
             },
         })
     }
@@ -901,6 +1344,22 @@ impl<'a> Parser<'a> {
                             object,
                             name: property,
                             value: Box::new(value),
+                            kind,
+                        },
+                    });
+                }
+
+                Spanned {
+                    span,
+                    value: Expression::SubScript { target, index },
+                } => {
+                    return Ok(Spanned {
+                        span: span.to(value.get_span()),
+                        value: Expression::SetSubScript {
+                            target,
+                            index,
+                            value: Box::new(value),
+                            kind,
                         },
                     });
                 }
@@ -1075,6 +1534,10 @@ impl<'a> Parser<'a> {
                     span: *span,
                     value: Expression::Literal(Literal::Str(s.clone())),
                 }),
+                TokenType::CHAR(ch) => Ok(Spanned {
+                    span: *span,
+                    value: Expression::Literal(Literal::Char(ch)),
+                }),
                 TokenType::INT(n) => Ok(Spanned {
                     span: *span,
                     value: Expression::Literal(Literal::Int(n)),
@@ -1086,14 +1549,44 @@ impl<'a> Parser<'a> {
 
                 TokenType::MATCH => self.parse_match(*span),
 
+                TokenType::IF => self.parse_if_expression(*span),
+
+                TokenType::LBRACE => self.parse_block_expression(*span),
+
+                TokenType::LOOP => self.parse_loop(*span, None),
+
                 TokenType::LPAREN => {
-                    let expr = Box::new(self.parse_expression()?);
+                    let expr = self.parse_expression()?;
+
+                    if !self.recognise(TokenType::COMMA) {
+                        let close_span =
+                            self.consume_get_span(&TokenType::RPAREN, "Expected ')'")?;
+
+                        return Ok(Spanned {
+                            span: span.to(close_span),
+                            value: Expression::Grouping {
+                                expr: Box::new(expr),
+                            },
+                        });
+                    }
+
+                    let mut items = vec![expr];
+
+                    while self.recognise(TokenType::COMMA) {
+                        self.next()?;
+
+                        if self.recognise(TokenType::RPAREN) || items.len() >= 32 {
+                            break; // allows a trailing comma i.e `(1,)`
+                        }
+
+                        items.push(self.parse_expression()?);
+                    }
 
                     let close_span = self.consume_get_span(&TokenType::RPAREN, "Expected ')'")?;
 
                     Ok(Spanned {
                         span: span.to(close_span),
-                        value: Expression::Grouping { expr },
+                        value: Expression::Tuple { items },
                     })
                 }
 
@@ -1154,58 +1647,115 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_match(&mut self, start_span: Span) -> ParserResult<Spanned<Expression>> {
+    /// Parses an `if` used as a value i.e `if cond { 1 } else { 2 }` into a ternary
+    fn parse_if_expression(&mut self, start_span: Span) -> ParserResult<Spanned<Expression>> {
         self.parsing_cond = true;
+        let condition = self.parse_expression()?;
+        self.parsing_cond = false;
 
-        let cond = self.parse_expression()?;
+        let then_branch = self.parse_branch()?;
 
-        self.parsing_cond = false;
+        self.consume(&TokenType::ELSE, "Expected 'else' as an `if` expression needs a value")?;
+
+        let else_branch = if self.recognise(TokenType::IF) {
+            let span = self.consume_get_span(&TokenType::IF, "Expected 'if' ")?;
+            self.parse_if_expression(span)?
+        } else {
+            self.parse_branch()?
+        };
 
+        Ok(Spanned {
+            span: start_span.to(else_branch.span),
+            value: Expression::Ternary {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            },
+        })
+    }
+
+    /// Parses a branch of an `if` expression i.e `{ expr }`
+    fn parse_branch(&mut self) -> ParserResult<Spanned<Expression>> {
         let open_span = self.consume_get_span(&TokenType::LBRACE, "Expected `{` ")?;
 
-        let mut arms = Vec::new();
-        // counter for how many times we've seen the `_` pattern
-        let mut seen_catch_all = 0;
+        self.parse_block_expression(open_span)
+    }
 
-        if !self.recognise(TokenType::RBRACE) {
-            loop {
-                self.parsing_match_arm = true;
+    /// Parses the rest of a block used as a value i.e `{ let a = 1; a + 1 }`
+    fn parse_block_expression(&mut self, open_span: Span) -> ParserResult<Spanned<Expression>> {
+        let mut statements = Vec::new();
 
-                if self.recognise(TokenType::UNDERSCORE) {
-                    seen_catch_all += 1;
+        loop {
+            if self.recognise(TokenType::RBRACE) {
+                let span = self.consume_get_span(&TokenType::RBRACE, "Expected `}` ")?;
+                self.span_error("Expected a block to end with an expression", span);
+                return Err(());
+            }
 
-                    let pattern = self.consume_get_span(&TokenType::UNDERSCORE, "Expected `_` ")?;
+            if self.recognise_statement() {
+                statements.push(self.parse_statement()?);
+                continue;
+            }
 
-                    self.consume(&TokenType::MATCHARROW, "Expected `=>` ")?;
+            let expr = self.parse_expression()?;
 
-                    let body = self.parse_statement()?;
+            if self.recognise(TokenType::SEMICOLON) {
+                let span = self.consume_get_span(&TokenType::SEMICOLON, "Expected ';' ")?;
 
-                    self.parsing_match_arm = false;
+                statements.push(Spanned {
+                    span: expr.span.to(span),
+                    value: Statement::Expr(expr),
+                });
 
-                    if seen_catch_all > 1 {
-                        self.span_warn("`_` pattern is allready present", pattern.to(body.span));
-                    }
+                continue;
+            }
 
-                    let span = pattern.to(body.span);
+            let close_span = self.consume_get_span(&TokenType::RBRACE, "Expected `}` ")?;
 
-                    arms.push(Spanned {
-                        value: MatchArm {
-                            pattern: None,
-                            body,
-                            is_all: true,
-                        },
-                        span,
-                    });
+            if statements.is_empty() {
+                return Ok(Spanned {
+                    span: open_span.to(close_span),
+                    value: expr.value,
+                });
+            }
 
-                    if self.recognise(TokenType::COMMA) {
-                        self.next()?;
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
+            return Ok(Spanned {
+                span: open_span.to(close_span),
+                value: Expression::Block {
+                    statements,
+                    value: Box::new(expr),
+                },
+            });
+        }
+    }
+
+    /// Checks if the next token starts a statement that isn't an expression statement
+    fn recognise_statement(&mut self) -> bool {
+        use self::TokenType::*;
+
+        self.matches(vec![
+            LBRACE, LET, CONST, BREAK, CONTINUE, RETURN, IF, DO, WHILE, FOR, PRINT, EPRINT, DEFER,
+        ])
+    }
+
+    fn parse_match(&mut self, start_span: Span) -> ParserResult<Spanned<Expression>> {
+        self.parsing_cond = true;
+
+        let cond = self.parse_expression()?;
 
-                let pattern = self.parse_expression()?;
+        self.parsing_cond = false;
+
+        let open_span = self.consume_get_span(&TokenType::LBRACE, "Expected `{` ")?;
+
+        let mut arms = Vec::new();
+
+        if !self.recognise(TokenType::RBRACE) {
+            loop {
+                self.parsing_match_arm = true;
+
+                let pattern = self.parse_pattern()?;
+
+                let guard = self.parse_match_guard()?;
 
                 self.consume(&TokenType::MATCHARROW, "Expected `=>` ")?;
 
@@ -1215,11 +1765,17 @@ impl<'a> Parser<'a> {
 
                 let span = pattern.span.to(body.span);
 
+                let is_all = match pattern.value {
+                    Pattern::Wildcard | Pattern::Bind(_) => true,
+                    _ => false,
+                };
+
                 arms.push(Spanned {
                     value: MatchArm {
-                        pattern: Some(pattern),
+                        pattern,
+                        guard,
                         body,
-                        is_all: false,
+                        is_all,
                     },
                     span,
                 });
@@ -1243,6 +1799,110 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_pattern(&mut self) -> ParserResult<Spanned<Pattern>> {
+        let first = self.parse_single_pattern()?;
+
+        if !self.recognise(TokenType::BAR) {
+            return Ok(first);
+        }
+
+        let mut span = first.span;
+        let mut patterns = vec![first];
+
+        while self.recognise(TokenType::BAR) {
+            self.next()?;
+
+            let pattern = self.parse_single_pattern()?;
+
+            span = span.to(pattern.span);
+            patterns.push(pattern);
+        }
+
+        Ok(Spanned {
+            span,
+            value: Pattern::Or(patterns),
+        })
+    }
+
+    fn parse_variant_pattern(&mut self) -> ParserResult<Spanned<Pattern>> {
+        let enum_name = self.consume_get_symbol("Expected an identifier")?;
+
+        self.consume(&TokenType::NAMESPACE, "Expected `::` ")?;
+
+        let variant = self.consume_get_symbol("Expected an identifier")?;
+
+        let mut span = enum_name.span.to(variant.span);
+        let mut inner = None;
+
+        if self.recognise(TokenType::LPAREN) {
+            self.next()?;
+            inner = Some(Box::new(self.parse_pattern()?));
+            span = span.to(self.consume_get_span(&TokenType::RPAREN, "Expected `)` ")?);
+        }
+
+        Ok(Spanned {
+            span,
+            value: Pattern::Variant {
+                enum_name,
+                variant,
+                inner,
+            },
+        })
+    }
+
+    fn parse_single_pattern(&mut self) -> ParserResult<Spanned<Pattern>> {
+        if self.recognise(TokenType::UNDERSCORE) {
+            let span = self.consume_get_span(&TokenType::UNDERSCORE, "Expected `_` ")?;
+
+            return Ok(Spanned {
+                span,
+                value: Pattern::Wildcard,
+            });
+        }
+
+        if self.recognise_identifier() && self.second_is(&TokenType::NAMESPACE)? {
+            return self.parse_variant_pattern();
+        }
+
+        if self.recognise_identifier() && self.second_ends_pattern()? {
+            let name = self.consume_get_symbol("Expected an identifier")?;
+
+            return Ok(Spanned {
+                span: name.span,
+                value: Pattern::Bind(name),
+            });
+        }
+
+        let start = self.parse_expression()?;
+
+        if self.recognise(TokenType::DOTDOT) {
+            self.next()?;
+
+            let end = self.parse_expression()?;
+
+            Ok(Spanned {
+                span: start.span.to(end.span),
+                value: Pattern::Range(start, end),
+            })
+        } else {
+            Ok(Spanned {
+                span: start.span,
+                value: Pattern::Expr(start),
+            })
+        }
+    }
+
+    /// Parses the optional `if cond` that comes after a match arm's pattern
+    fn parse_match_guard(&mut self) -> ParserResult<Option<Spanned<Expression>>> {
+        if self.recognise(TokenType::IF) {
+            self.next()?;
+
+            Ok(Some(self.parse_expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn parse_closure(&mut self, open_span: Span) -> ParserResult<Spanned<Function>> {
         let params = self.parse_params(open_span, "closure")?;
 
@@ -1346,6 +2006,7 @@ impl<'a> Parser<'a> {
                                     value:
                                         Call {
                                             args,
+                                            named,
                                             callee,
                                             .. // replace the old types with the new
                                         },
@@ -1363,6 +2024,7 @@ impl<'a> Parser<'a> {
                                         },
                                         callee,
                                         args,
+                                        named,
                                     },
                                 }),
                                 span: { whole_span.to(call.span) },
@@ -1427,9 +2089,43 @@ impl<'a> Parser<'a> {
                         index,
                     },
                 });
+            } else if self.recognise(TokenType::QUESTION) && !self.second_starts_expression()? {
+                let close_span = self.consume_get_span(&TokenType::QUESTION, "Expected '?' ")?;
+
+                expr = Spanned {
+                    span: expr.get_span().to(close_span),
+                    value: Expression::Try {
+                        expr: Box::new(expr),
+                    },
+                }
             } else if self.recognise(TokenType::DOT) {
                 self.next()?;
 
+                let index = match self.past_tokens.front() {
+                    Some(Spanned {
+                        value:
+                            Token {
+                                token: TokenType::INT(n),
+                            },
+                        ..
+                    }) => Some(*n),
+                    _ => None,
+                };
+
+                if let Some(n) = index {
+                    let index_span = self.next()?.span;
+
+                    expr = Spanned {
+                        span: expr.get_span().to(index_span),
+                        value: Expression::TupleIndex {
+                            tuple: Box::new(expr),
+                            index: Spanned::new(n as usize, index_span),
+                        },
+                    };
+
+                    continue;
+                }
+
                 let (close_span, property) =
                     self.consume_get_symbol_and_span("Expected an identifier")?;
 
@@ -1500,10 +2196,28 @@ impl<'a> Parser<'a> {
         self.consume(&TokenType::LPAREN, "Expected '(' ")?;
 
         let mut args = vec![];
+        let mut named = vec![];
 
         if !self.recognise(TokenType::RPAREN) {
             loop {
-                args.push(self.parse_expression()?);
+                if self.recognise_identifier() && self.second_is(&TokenType::COLON)? {
+                    let name = self.consume_get_symbol("Expected a param name")?;
+
+                    self.consume(&TokenType::COLON, "Expected a colon")?;
+
+                    let value = self.parse_expression()?;
+
+                    named.push(NamedArg { name, value });
+                } else {
+                    let arg = self.parse_expression()?;
+
+                    if !named.is_empty() {
+                        let msg = "Positional arguments must come before named ones";
+                        self.span_error(msg, arg.span);
+                    }
+
+                    args.push(arg);
+                }
 
                 if self.recognise(TokenType::COMMA) {
                     self.next()?;
@@ -1522,6 +2236,7 @@ impl<'a> Parser<'a> {
                 value: Call {
                     callee: Box::new(callee),
                     args,
+                    named,
                     types: Spanned::new(vec![], EMPTYSPAN),
                 },
             }),
@@ -1599,3 +2314,49 @@ impl<'a> Parser<'a> {
         })
     }
 }
+
+/// A match on `cond` that runs `then` if the value matches `pattern` and `otherwise` if it doesn't
+fn let_match(
+    pattern: Spanned<Pattern>,
+    cond: Spanned<Expression>,
+    then: Spanned<Statement>,
+    otherwise: Spanned<Statement>,
+    span: Span,
+) -> Spanned<Expression> {
+    let is_all = match pattern.value {
+        Pattern::Wildcard | Pattern::Bind(_) => true,
+        _ => false,
+    };
+
+    let arms = vec![
+        Spanned {
+            span: pattern.span.to(then.span),
+            value: MatchArm {
+                pattern,
+                guard: None,
+                body: then,
+                is_all,
+            },
+        },
+        Spanned {
+            span: otherwise.span,
+            value: MatchArm {
+                pattern: Spanned {
+                    span: otherwise.span,
+                    value: Pattern::Wildcard,
+                },
+                guard: None,
+                body: otherwise,
+                is_all: true,
+            },
+        },
+    ];
+
+    Spanned {
+        span,
+        value: Expression::Match {
+            cond: Box::new(cond),
+            arms: Spanned::new(arms, span),
+        },
+    }
+}
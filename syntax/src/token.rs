@@ -34,6 +34,8 @@ impl<'a> Display for TokenType<'a> {
             TokenType::MODULO => write!(f, "%"),
             TokenType::EXPONENTIAL => write!(f, "^"),
             TokenType::DOT => write!(f, "."),
+            TokenType::DOTDOT => write!(f, ".."),
+            TokenType::DOTDOTDOT => write!(f, "..."),
             TokenType::COLON => write!(f, ":"),
             TokenType::QUESTION => write!(f, "?"),
             TokenType::LESSTHAN => write!(f, "<"),       // <
@@ -43,6 +45,7 @@ impl<'a> Display for TokenType<'a> {
             TokenType::LESSTHANEQUAL => write!(f, "<="), // <=
             TokenType::GREATERTHANEQUAL => write!(f, "=>"), // =>
             TokenType::STRING(ref s) => write!(f, "{:?}", s),
+            TokenType::CHAR(ref ch) => write!(f, "{:?}", ch),
             TokenType::COMMA => write!(f, ","),     // ,
             TokenType::COMMENT => write!(f, "//"),  // //
             TokenType::SEMICOLON => write!(f, ";"), //
@@ -60,10 +63,13 @@ impl<'a> Display for TokenType<'a> {
             // Keywords,
             TokenType::FUNCTION => write!(f, "fun"),
             TokenType::PRINT => write!(f, "print"),
+            TokenType::EPRINT => write!(f, "eprint"),
             TokenType::TYPE => write!(f, "type"),
             TokenType::BREAK => write!(f, "break"),
             TokenType::CONTINUE => write!(f, "continue"),
             TokenType::LET => write!(f, "var"),
+            TokenType::CONST => write!(f, "const"),
+            TokenType::DEFER => write!(f, "defer"),
             TokenType::IF => write!(f, "if"),
             TokenType::DO => write!(f, "do"),
             TokenType::ELSE => write!(f, "else"),
@@ -73,6 +79,7 @@ impl<'a> Display for TokenType<'a> {
             TokenType::CLASS => write!(f, "class"),
             TokenType::FOR => write!(f, "for"),
             TokenType::WHILE => write!(f, "while"),
+            TokenType::LOOP => write!(f, "loop"),
             TokenType::AND => write!(f, "and"),
             TokenType::OR => write!(f, "or"),
             TokenType::NIL => write!(f, "nil"),
@@ -91,6 +98,7 @@ pub enum TokenType<'a> {
     INT(i64),
     FLOAT(f64),
     STRING(String),
+    CHAR(char),
 
     // Assignment
     ASSIGN,      // =
@@ -110,6 +118,8 @@ pub enum TokenType<'a> {
     // Puntuation
     FRETURN,    // ->
     DOT,        // .
+    DOTDOT,     // ..
+    DOTDOTDOT,  // ...
     QUESTION,   // ?
     COLON,      // :
     COMMA,      // ,
@@ -138,6 +148,8 @@ pub enum TokenType<'a> {
     BREAK,
     CONTINUE,
     LET,
+    CONST,
+    DEFER,
     IF,
     DO,
     ELSE,
@@ -145,12 +157,14 @@ pub enum TokenType<'a> {
     TRUE(bool),
     FALSE(bool),
     PRINT,
+    EPRINT,
     CLASS,
     MATCH,
     EXTENDS,
 
     FOR,
     WHILE,
+    LOOP,
     AND,
     OR,
     NIL,
@@ -11,14 +11,16 @@ extern crate vm;
 mod repl;
 
 use frontend::compile;
+use frontend::inline;
 use frontend::Infer;
 // use interpreter::{interpret, Environment};
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read};
 use std::rc::Rc;
 use structopt::StructOpt;
 use syntax::parser::Parser;
 use util::emmiter::Reporter;
+use util::print_err;
 use util::symbol::{SymbolFactory, Symbols};
 use vm::VM;
 
@@ -68,10 +70,7 @@ pub fn run(path: String) {
     let mut infer = Infer::new();
 
     let typed_ast = match infer.infer(ast, &strings, &mut reporter) {
-        Ok(ast) => {
-            reporter.emit(input); //emit warnings
-            ast
-        }
+        Ok(ast) => ast,
         Err(_) => {
             reporter.emit(input);
 
@@ -80,16 +79,23 @@ pub fn run(path: String) {
     };
 
     // if compile_vm {
-    let (program, objects) = match compile(&typed_ast, &symbols, &mut reporter) {
-        Ok(functions) => functions,
+    let (mut program, objects) = match compile(&typed_ast, &symbols, &mut reporter) {
+        Ok(functions) => {
+            reporter.emit(input); //emit warnings
+            functions
+        }
         Err(_) => {
             reporter.emit(input);
             ::std::process::exit(65)
         }
     };
 
-    let mut vm = VM::new(symbols.symbol("main"), &program, objects).unwrap();
-    vm.run();
+    inline(&mut program);
+
+    if let Err(err) = VM::run_program(&program, objects, &mut symbols, &mut io::stdout()) {
+        print_err(err.to_string());
+        ::std::process::exit(70)
+    }
     // }
 }
 
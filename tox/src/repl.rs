@@ -5,6 +5,7 @@ use syntax::ast::{Function, ItemName, Program};
 
 use syntax::parser::Parser;
 use util::emmiter::Reporter;
+use util::print_err;
 use util::pos::Spanned;
 use util::symbol::{SymbolFactory, Symbols};
 use vm::VM;
@@ -101,9 +102,10 @@ impl Repl {
                 }
             };
 
-            let mut vm = VM::new(symbols.symbol("main"), &program, objects).unwrap();
-
-            vm.run()
+            match VM::run_program(&program, objects, &mut symbols, &mut io::stdout()) {
+                Ok(value) => println!("{}", value.repr()),
+                Err(err) => print_err(err.to_string()),
+            }
         }
     }
 }
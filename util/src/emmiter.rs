@@ -7,14 +7,28 @@ use std::fmt::{self, Display};
 use std::iter::repeat;
 use std::rc::Rc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Diagnostic {
     msg: String,
     level: Level,
     span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+impl Diagnostic {
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Level {
     Warn,
     Error,
@@ -98,6 +112,21 @@ impl Reporter {
         })
     }
 
+    /// The messages of all the warnings reported so far
+    pub fn warnings(&self) -> Vec<String> {
+        self.diagnostics
+            .borrow()
+            .iter()
+            .filter(|diagnostic| diagnostic.level == Level::Warn)
+            .map(|diagnostic| diagnostic.msg.clone())
+            .collect()
+    }
+
+    /// All the diagnostics reported so far
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+
     pub fn emit(&self, input: &str) {
         for diagnostic in self.diagnostics.borrow().iter() {
             print(input, diagnostic)
@@ -17,14 +17,14 @@ pub struct BytePosition<'a> {
 }
 
 /// Represents a Span in the source file along with its value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Spanned<T> {
     pub span: Span,
     pub value: T,
 }
 
 /// A span between two locations in a source file
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Hash)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -43,7 +43,7 @@ pub const EMPTYSPAN: Span = Span {
     },
 };
 
-#[derive(Debug, Copy, PartialOrd, Clone, PartialEq, Eq, Ord)]
+#[derive(Debug, Copy, PartialOrd, Clone, PartialEq, Eq, Ord, Hash)]
 pub struct Position {
     pub line: u32,
     pub column: u32,
@@ -129,6 +129,30 @@ impl<T> Spanned<T> {
     pub fn get_span(&self) -> Span {
         self.span
     }
+
+    /// Transforms the value keeping the span it came from
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Spanned<U> {
+        Spanned {
+            span: self.span,
+            value: f(self.value),
+        }
+    }
+
+    /// Transforms the span keeping the value
+    pub fn map_span<F: FnOnce(Span) -> Span>(self, f: F) -> Spanned<T> {
+        Spanned {
+            span: f(self.span),
+            value: self.value,
+        }
+    }
+
+    /// Transforms a borrow of the value keeping the span it came from
+    pub fn map_ref<'a, U, F: FnOnce(&'a T) -> U>(&'a self, f: F) -> Spanned<U> {
+        Spanned {
+            span: self.span,
+            value: f(&self.value),
+        }
+    }
 }
 
 impl Position {
@@ -160,3 +184,48 @@ impl Position {
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Position, Span, Spanned, EMPTYSPAN};
+
+    fn span(start: u32, end: u32) -> Span {
+        Span {
+            start: Position {
+                line: 1,
+                column: start,
+                absolute: start as usize,
+            },
+            end: Position {
+                line: 1,
+                column: end,
+                absolute: end as usize,
+            },
+        }
+    }
+
+    #[test]
+    fn map_keeps_the_span() {
+        let mapped = Spanned::new(2, span(3, 7)).map(|n| n.to_string());
+
+        assert_eq!(mapped.value, "2");
+        assert_eq!(mapped.span, span(3, 7));
+    }
+
+    #[test]
+    fn map_span_keeps_the_value() {
+        let mapped = Spanned::new("a", span(3, 7)).map_span(|whole| whole.to(EMPTYSPAN));
+
+        assert_eq!(mapped.value, "a");
+        assert_eq!(mapped.span, span(3, 7).to(EMPTYSPAN));
+    }
+
+    #[test]
+    fn map_ref_keeps_the_span() {
+        let spanned = Spanned::new(vec![1, 2], span(1, 4));
+        let borrowed = spanned.map_ref(Vec::len);
+
+        assert_eq!(borrowed.value, 2);
+        assert_eq!(borrowed.span, spanned.span);
+    }
+}
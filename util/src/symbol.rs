@@ -8,6 +8,35 @@ use std::fmt::{Display, Formatter, Result};
 use std::rc::Rc;
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Hash, Copy)]
 pub struct Symbol(pub u64);
+
+// The names every `SymbolFactory` starts with. Both the compiler and the vm refer to these
+// without looking them up so they must match the ids given to them in `BUILTINS`
+pub const RANDOM: Symbol = Symbol(1);
+pub const CLOCK: Symbol = Symbol(2);
+pub const READ: Symbol = Symbol(3);
+pub const FOPEN: Symbol = Symbol(4);
+pub const MAP: Symbol = Symbol(5);
+pub const FILTER: Symbol = Symbol(6);
+pub const REDUCE: Symbol = Symbol(7);
+/// The method used to print a class instance
+pub const TO_STRING: Symbol = Symbol(8);
+/// The receiver of a method i.e `self` within a method body
+pub const SELF: Symbol = Symbol(9);
+pub const CLONE: Symbol = Symbol(10);
+
+const BUILTINS: [(Symbol, &str); 10] = [
+    (RANDOM, "random"),
+    (CLOCK, "clock"),
+    (READ, "read"),
+    (FOPEN, "fopen"),
+    (MAP, "map"),
+    (FILTER, "filter"),
+    (REDUCE, "reduce"),
+    (TO_STRING, "to_string"),
+    (SELF, "self"),
+    (CLONE, "clone"),
+];
+
 impl Display for Symbol {
     fn fmt(&self, f: &mut Formatter) -> Result {
         write!(f, "Symbol {}", self.0)
@@ -101,13 +130,19 @@ impl SymbolFactory {
 impl Default for SymbolFactory {
     fn default() -> Self {
         let mut map = FnvHashMap::default();
-        map.insert(Symbol(1), "random".into());
-        map.insert(Symbol(2), "clock".into());
-        map.insert(Symbol(3), "read".into());
-        map.insert(Symbol(4), "fopen".into());
+
+        for (index, &(symbol, name)) in BUILTINS.iter().enumerate() {
+            assert_eq!(
+                symbol,
+                Symbol(index as u64 + 1),
+                "`{}` is out of order",
+                name
+            );
+            map.insert(symbol, name.into());
+        }
 
         SymbolFactory {
-            next: RefCell::new(5),
+            next: RefCell::new(BUILTINS.len() as u64 + 1),
             mappings: RefCell::new(map),
         }
     }
@@ -115,7 +150,10 @@ impl Default for SymbolFactory {
 
 #[cfg(test)]
 mod test {
-    use crate::symbol::{Symbol, SymbolFactory, Symbols};
+    use crate::symbol::{
+        Symbol, SymbolFactory, Symbols, CLOCK, CLONE, FILTER, FOPEN, MAP, RANDOM, READ, REDUCE,
+        SELF, TO_STRING,
+    };
     use std::rc::Rc;
 
     #[test]
@@ -134,6 +172,23 @@ mod test {
         assert_eq!(Some(&"c".into()), map.look(Symbol(2)));
         map.end_scope();
         assert_eq!(Some(&"a".into()), map.look(Symbol(0)));
-        assert_eq!(map.symbol("c".into()), Symbol(5));
+        assert_eq!(map.symbol("c".into()), Symbol(11));
+    }
+
+    #[test]
+    fn builtins_are_interned_first() {
+        let strings = Rc::new(SymbolFactory::new());
+        let mut symbols: Symbols<()> = Symbols::new(strings);
+
+        assert_eq!(symbols.symbol("random"), RANDOM);
+        assert_eq!(symbols.symbol("clock"), CLOCK);
+        assert_eq!(symbols.symbol("read"), READ);
+        assert_eq!(symbols.symbol("fopen"), FOPEN);
+        assert_eq!(symbols.symbol("map"), MAP);
+        assert_eq!(symbols.symbol("filter"), FILTER);
+        assert_eq!(symbols.symbol("reduce"), REDUCE);
+        assert_eq!(symbols.symbol("to_string"), TO_STRING);
+        assert_eq!(symbols.symbol("self"), SELF);
+        assert_eq!(symbols.symbol("clone"), CLONE);
     }
 }
@@ -1,6 +1,6 @@
 use crate::value::Value;
-#[cfg(feature = "debug")]
-use opcode;
+use opcode::OpCode;
+use std::convert::TryFrom;
 
 type Line = u32;
 
@@ -8,8 +8,9 @@ type Line = u32;
 /// A wrapper around an array of bytes
 pub struct Chunk {
     pub code: Vec<u8>,
-    pub constants: Vec<Value>,
-    pub lines: Vec<Line>,
+    /// The line of each run of bytes written on the same line and the offset the run ends at.
+    /// The offsets only increase so the line of a byte can be binary searched
+    pub lines: Vec<(Line, usize)>,
 }
 
 impl Chunk {
@@ -17,137 +18,253 @@ impl Chunk {
         Self::default()
     }
 
-    pub fn add_constant(&mut self, value: Value) -> usize {
-        self.constants.push(value);
-        self.constants.len() - 1
+    /// A chunk with room for `code` bytes and `lines` runs of lines before it has to grow
+    pub fn with_capacity(code: usize, lines: usize) -> Self {
+        Chunk {
+            code: Vec::with_capacity(code),
+            lines: Vec::with_capacity(lines),
+        }
     }
 
     pub fn write(&mut self, byte: u8, line: Line) {
         self.code.push(byte);
-        self.lines.push(line)
+
+        let end = self.code.len();
+
+        match self.lines.last_mut() {
+            Some((last, run_end)) if *last == line => *run_end = end,
+            _ => self.lines.push((line, end)),
+        }
+    }
+
+    /// The line of the byte at `offset` or `None` if the offset is past the end of the chunk
+    pub fn line_at(&self, offset: usize) -> Option<Line> {
+        let run = match self.lines.binary_search_by(|&(_, end)| end.cmp(&offset)) {
+            // the run ending at `offset` doesn't include it so it's in the next one
+            Ok(run) => run + 1,
+            Err(run) => run,
+        };
+
+        self.lines.get(run).map(|&(line, _)| line)
+    }
+
+    pub fn disassemble(&self, name: &str, constants: &[Value]) {
+        print!("{}", self.disassembly(name, constants));
     }
 
-    #[cfg(feature = "debug")]
-    pub fn disassemble(&self, name: &str) {
-        println!("== {} ==\n", name);
+    /// The disassembly of every instruction in the chunk under a header with `name`.
+    /// `constants` is the pool of the program the chunk belongs to
+    pub fn disassembly(&self, name: &str, constants: &[Value]) -> String {
+        let mut text = format!("== {} ==\n\n", name);
 
         let mut i = 0;
 
         while i < self.code.len() {
-            i = self.disassemble_instruction(i);
+            let (instruction, next) = self.instruction_text(i, constants);
+            text.push_str(&instruction);
+            text.push('\n');
+            i = next;
         }
+
+        text
+    }
+
+    /// Prints the instruction at `offset` returning the offset of the next instruction
+    pub fn disassemble_instruction(&self, offset: usize, constants: &[Value]) -> usize {
+        let (text, next) = self.instruction_text(offset, constants);
+        println!("{}", text);
+        next
     }
 
-    #[cfg(feature = "debug")]
-    pub fn disassemble_instruction(&self, offset: usize) -> usize {
-        print!("{:04}", offset);
+    /// The offset, line and disassembly of the instruction at `offset` along with the offset of
+    /// the next instruction
+    pub fn instruction_text(&self, offset: usize, constants: &[Value]) -> (String, usize) {
+        let mut text = format!("{:04}", offset);
+
+        let line = self.line_at(offset);
 
-        if offset > 0 && self.lines[offset] == self.lines[offset - 1] {
-            print!("   | ")
-        } else {
-            print!("{:4} ", self.lines[offset])
+        match line {
+            _ if offset > 0 && line == self.line_at(offset - 1) => text.push_str("   | "),
+            Some(line) => text.push_str(&format!("{:4} ", line)),
+            None => text.push_str("   ? "),
         }
 
-        let instruction = self.code[offset];
-
-        match instruction {
-            opcode::IGL => simple_instruction("OPCODE::IGL", offset),
-            opcode::HLT => simple_instruction("OPCODE::HLT", offset),
-            opcode::RETURN => simple_instruction("OPCODE::RETURN", offset),
-            opcode::CONSTANT => self.constant_instruction("OPCODE::CONSTANT", offset),
-            opcode::PRINT => simple_instruction("OPCODE::PRINT", offset),
-            opcode::NEGATE => simple_instruction("OPCODE::NEGATE", offset),
-            opcode::NEGATEF => simple_instruction("OPCODE::NEGATEF", offset),
-            opcode::NIL => simple_instruction("OPCODE::NIL", offset),
-            opcode::TRUE => simple_instruction("OPCODE::TRUE", offset),
-            opcode::FALSE => simple_instruction("OPCODE::FALSE", offset),
-            opcode::NOT => simple_instruction("OPCODE::NOT", offset),
-            opcode::EQUAL => simple_instruction("OPCODE::EQUAL", offset),
-            opcode::GREATER => simple_instruction("OPCODE::GREATER", offset),
-            opcode::GREATERF => simple_instruction("OPCODE::GREATERF", offset),
-            opcode::LESS => simple_instruction("OPCODE::LESS", offset),
-            opcode::LESSF => simple_instruction("OPCODE::LESSF", offset),
-            opcode::ADD => simple_instruction("OPCODE::ADD", offset),
-            opcode::ADDF => simple_instruction("OPCODE::ADDF", offset),
-            opcode::SUB => simple_instruction("OPCODE::SUB", offset),
-            opcode::SUBF => simple_instruction("OPCODE::SUBF", offset),
-            opcode::MUL => simple_instruction("OPCODE::MUL", offset),
-            opcode::MULF => simple_instruction("OPCODE::MULF", offset),
-            opcode::DIV => simple_instruction("OPCODE::DIV", offset),
-            opcode::DIVF => simple_instruction("OPCODE::DIVF", offset),
-            opcode::JUMP => simple_instruction("OPCODE::JUMP", offset),
-            opcode::GETLOCAL => self.local_instruction("OPCODE::GETLOCAL", offset),
-            opcode::SETLOCAL => self.local_instruction("OPCODE::SETLOCAL", offset),
-            opcode::CALL => self.call_instruction("OPCODE::CALL", offset),
-            opcode::CALLCLOSURE => simple_instruction("OPCODE::CALLCLOSURE", offset),
-            opcode::JUMPIF => self.jump_instruction("OPCODE::JUMPIF", offset),
-            opcode::JUMPNOT => self.jump_instruction("OPCODE::JUMPNOT", offset),
-            opcode::LOOP => self.jump_instruction("OPCODE::LOOP", offset),
-            opcode::POP => simple_instruction("OPCODE::POP", offset),
-            opcode::CONCAT => simple_instruction("OPCODE::CONCAT", offset),
-            opcode::GETPARAM => self.local_instruction("OPCODE::GETPARAM", offset),
-            opcode::SETPARAM => self.local_instruction("OPCODE::SETPARAM", offset),
-            opcode::ARRAY => simple_instruction("OPCODE::ARRAY", offset),
-            opcode::INDEXARRAY => simple_instruction("OPCODE::INDEXARRAY", offset),
-            opcode::INDEXSTRING => simple_instruction("OPCODE::INDEXSTRING", offset),
-            opcode::GETPROPERTY => self.local_instruction("OPCODE::GETPROPERTY", offset),
-            opcode::SETPROPERTY => self.local_instruction("OPCODE::SETPROPERTY", offset),
-            opcode::GETMETHOD => self.local_instruction("OPCODE::GETMETHOD", offset),
-            opcode::CALLINSTANCEMETHOD => {
-                self.call_instruction("OPCODE::CALLINSTANCEMETHOD", offset)
+        let instruction = match OpCode::try_from(self.code[offset]) {
+            Ok(instruction) => instruction,
+            Err(e) => {
+                text.push_str(&e.to_string());
+                return (text, offset + 1);
             }
-            opcode::ENUM => self.enum_instruction("OPCODE::ENUM", offset),
-            opcode::CALLSTATICMETHOD => self.call_instruction("OPCODE::CALLSTATICMETHOD", offset),
-            opcode::CLASSINSTANCE => self.call_instruction("OPCODE::CLASSINSTANCE", offset),
-            _ => {
-                println!("UNKOWN OPCODE {}", instruction);
-                offset + 1
+        };
+
+        let (instruction, next) = match instruction {
+            OpCode::Igl => simple_instruction("OPCODE::IGL", offset),
+            OpCode::Hlt => simple_instruction("OPCODE::HLT", offset),
+            OpCode::Return => simple_instruction("OPCODE::RETURN", offset),
+            OpCode::Constant => self.constant_instruction("OPCODE::CONSTANT", offset, constants),
+            OpCode::Print => simple_instruction("OPCODE::PRINT", offset),
+            OpCode::Negate => simple_instruction("OPCODE::NEGATE", offset),
+            OpCode::NegateF => simple_instruction("OPCODE::NEGATEF", offset),
+            OpCode::Nil => simple_instruction("OPCODE::NIL", offset),
+            OpCode::True => simple_instruction("OPCODE::TRUE", offset),
+            OpCode::False => simple_instruction("OPCODE::FALSE", offset),
+            OpCode::Not => simple_instruction("OPCODE::NOT", offset),
+            OpCode::Equal => simple_instruction("OPCODE::EQUAL", offset),
+            OpCode::Greater => simple_instruction("OPCODE::GREATER", offset),
+            OpCode::GreaterF => simple_instruction("OPCODE::GREATERF", offset),
+            OpCode::Less => simple_instruction("OPCODE::LESS", offset),
+            OpCode::LessF => simple_instruction("OPCODE::LESSF", offset),
+            OpCode::LessEqualF => simple_instruction("OPCODE::LESSEQUALF", offset),
+            OpCode::GreaterEqualF => simple_instruction("OPCODE::GREATEREQUALF", offset),
+            OpCode::LessStr => simple_instruction("OPCODE::LESSSTR", offset),
+            OpCode::GreaterStr => simple_instruction("OPCODE::GREATERSTR", offset),
+            OpCode::Tuple => self.local_instruction("OPCODE::TUPLE", offset),
+            OpCode::IndexTuple => self.local_instruction("OPCODE::INDEXTUPLE", offset),
+            OpCode::CheckLen => self.local_instruction("OPCODE::CHECKLEN", offset),
+            OpCode::Add => simple_instruction("OPCODE::ADD", offset),
+            OpCode::AddF => simple_instruction("OPCODE::ADDF", offset),
+            OpCode::Sub => simple_instruction("OPCODE::SUB", offset),
+            OpCode::SubF => simple_instruction("OPCODE::SUBF", offset),
+            OpCode::Mul => simple_instruction("OPCODE::MUL", offset),
+            OpCode::MulF => simple_instruction("OPCODE::MULF", offset),
+            OpCode::Div => simple_instruction("OPCODE::DIV", offset),
+            OpCode::DivF => simple_instruction("OPCODE::DIVF", offset),
+            OpCode::Jump => self.jump_instruction("OPCODE::JUMP", offset),
+            OpCode::GetLocal => self.local_instruction("OPCODE::GETLOCAL", offset),
+            OpCode::SetLocal => self.local_instruction("OPCODE::SETLOCAL", offset),
+            OpCode::Call => self.call_instruction("OPCODE::CALL", offset, 3),
+            OpCode::CallValue => self.local_instruction("OPCODE::CALLVALUE", offset),
+            OpCode::GetFunction => self.local_instruction("OPCODE::GETFUNCTION", offset),
+            OpCode::JumpIf => self.jump_instruction("OPCODE::JUMPIF", offset),
+            OpCode::JumpNot => self.jump_instruction("OPCODE::JUMPNOT", offset),
+            OpCode::Loop => self.jump_instruction("OPCODE::LOOP", offset),
+            OpCode::Pop => simple_instruction("OPCODE::POP", offset),
+            OpCode::Dup => simple_instruction("OPCODE::DUP", offset),
+            OpCode::Swap => simple_instruction("OPCODE::SWAP", offset),
+            OpCode::Dup2 => simple_instruction("OPCODE::DUP2", offset),
+            OpCode::Concat => simple_instruction("OPCODE::CONCAT", offset),
+            OpCode::GetParam => self.local_instruction("OPCODE::GETPARAM", offset),
+            OpCode::SetParam => self.local_instruction("OPCODE::SETPARAM", offset),
+            OpCode::Array => self.local_instruction("OPCODE::ARRAY", offset),
+            OpCode::IndexArray => simple_instruction("OPCODE::INDEXARRAY", offset),
+            OpCode::SetIndexArray => simple_instruction("OPCODE::SETINDEXARRAY", offset),
+            OpCode::IndexString => simple_instruction("OPCODE::INDEXSTRING", offset),
+            OpCode::GetProperty => self.local_instruction("OPCODE::GETPROPERTY", offset),
+            OpCode::SetProperty => self.local_instruction("OPCODE::SETPROPERTY", offset),
+            OpCode::GetMethod => self.local_instruction("OPCODE::GETMETHOD", offset),
+            OpCode::CallInstanceMethod => {
+                self.call_instruction("OPCODE::CALLINSTANCEMETHOD", offset, 3)
             }
-        }
+            OpCode::Enum => self.enum_instruction("OPCODE::ENUM", offset),
+            OpCode::CallStaticMethod => {
+                self.call_instruction("OPCODE::CALLSTATICMETHOD", offset, 4)
+            }
+            OpCode::ClassInstance => {
+                // followed by the name of each property
+                let properties = self.code[offset + 2] as usize;
+                self.call_instruction("OPCODE::CLASSINSTANCE", offset, 3 + properties)
+            }
+            OpCode::EnumData => self.enum_instruction("OPCODE::ENUMDATA", offset),
+            OpCode::CallNative => self.local_instruction("OPCODE::CALLNATIVE", offset),
+            OpCode::Int2Float => simple_instruction("OPCODE::INT2FLOAT", offset),
+            OpCode::Float2Int => simple_instruction("OPCODE::FLOAT2INT", offset),
+            OpCode::Bool2Int => simple_instruction("OPCODE::BOOL2INT", offset),
+            OpCode::Int2Str => simple_instruction("OPCODE::INT2STR", offset),
+            OpCode::Float2Str => simple_instruction("OPCODE::FLOAT2STR", offset),
+            OpCode::Char2Int => simple_instruction("OPCODE::CHAR2INT", offset),
+            OpCode::Int2Char => simple_instruction("OPCODE::INT2CHAR", offset),
+            OpCode::Char2Str => simple_instruction("OPCODE::CHAR2STR", offset),
+            OpCode::EnumTag => simple_instruction("OPCODE::ENUMTAG", offset),
+            OpCode::EnumInner => simple_instruction("OPCODE::ENUMINNER", offset),
+            OpCode::EPrint => simple_instruction("OPCODE::EPRINT", offset),
+            OpCode::WrapInt => self.local_instruction("OPCODE::WRAPINT", offset),
+            OpCode::CheckInt => self.local_instruction("OPCODE::CHECKINT", offset),
+        };
+
+        text.push_str(&instruction);
+
+        (text, next)
     }
 
-    #[cfg(feature = "debug")]
-    pub fn constant_instruction(&self, name: &str, offset: usize) -> usize {
+    pub fn constant_instruction(
+        &self,
+        name: &str,
+        offset: usize,
+        constants: &[Value],
+    ) -> (String, usize) {
         let constant = self.code[offset + 1];
-        println!(
-            "{:16}{:4} '{}' ",
-            name, constant, self.constants[constant as usize]
-        );
-        offset + 2
+        let text = format!("{:16}{:4} '{}' ", name, constant, constants[constant as usize]);
+        (text, offset + 2)
     }
 
-    pub fn jump_instruction(&self, name: &str, offset: usize) -> usize {
+    pub fn jump_instruction(&self, name: &str, offset: usize) -> (String, usize) {
         let dest = u16::from(self.code[offset + 1]) << 8 | u16::from(self.code[offset + 2]);
 
-        println!("{:16}{:4}", name, dest,);
-
-        offset + 3
+        (format!("{:16}{:4}", name, dest,), offset + 3)
     }
 
-    pub fn local_instruction(&self, name: &str, offset: usize) -> usize {
+    pub fn local_instruction(&self, name: &str, offset: usize) -> (String, usize) {
         let symbol = self.code[offset + 1];
 
-        println!("{:16}  '{}'", name, symbol,);
-
-        offset + 2
+        (format!("{:16}  '{}'", name, symbol,), offset + 2)
     }
 
-    pub fn call_instruction(&self, name: &str, offset: usize) -> usize {
+    /// Disassembles an instruction that refers to a function, method or class and is `len`
+    /// bytes long
+    pub fn call_instruction(&self, name: &str, offset: usize, len: usize) -> (String, usize) {
         let symbol = self.code[offset + 1];
-        println!("{:16}  '{}' ", name, symbol);
-        offset + 4
+        (format!("{:16}  '{}' ", name, symbol), offset + len)
     }
 
-    pub fn enum_instruction(&self, name: &str, offset: usize) -> usize {
+    pub fn enum_instruction(&self, name: &str, offset: usize) -> (String, usize) {
         let tag = self.code[offset + 2];
-        println!("{:16}  tag '{}' ", name, tag);
 
-        offset + 3
+        (format!("{:16}  tag '{}' ", name, tag), offset + 3)
     }
 }
 
-#[cfg(feature = "debug")]
-pub fn simple_instruction(name: &str, offset: usize) -> usize {
-    println!("{}", name);
-    offset + 1
+pub fn simple_instruction(name: &str, offset: usize) -> (String, usize) {
+    (name.to_string(), offset + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Chunk;
+
+    #[test]
+    fn lines_are_run_length_encoded() {
+        let mut chunk = Chunk::new();
+
+        for i in 0..1000 {
+            let line = match i {
+                0..=99 => 1,
+                100..=599 => 2,
+                _ => 5,
+            };
+
+            chunk.write(0, line);
+        }
+
+        assert_eq!(chunk.lines, vec![(1, 100), (2, 600), (5, 1000)]);
+        assert_eq!(chunk.line_at(0), Some(1));
+        assert_eq!(chunk.line_at(99), Some(1));
+        assert_eq!(chunk.line_at(100), Some(2));
+        assert_eq!(chunk.line_at(599), Some(2));
+        assert_eq!(chunk.line_at(600), Some(5));
+        assert_eq!(chunk.line_at(999), Some(5));
+    }
+
+    #[test]
+    fn line_past_the_end() {
+        let mut chunk = Chunk::new();
+
+        assert_eq!(chunk.line_at(0), None);
+
+        chunk.write(0, 3);
+        chunk.write(0, 3);
+
+        assert_eq!(chunk.line_at(1), Some(3));
+        assert_eq!(chunk.line_at(2), None);
+        assert_eq!(chunk.line_at(usize::MAX), None);
+    }
 }
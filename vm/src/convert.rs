@@ -0,0 +1,170 @@
+//! Conversions between rust values and tox values for programs that embed the vm.
+//! Strings are copied into a new string object that isn't tracked by the vm
+use crate::object::StringObject;
+use crate::value::Value;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::ptr;
+
+/// A rust value that can be passed to tox code
+pub trait IntoTox {
+    fn into_tox(self) -> Value;
+}
+
+/// A rust value that can be read out of a tox value
+pub trait FromTox: Sized {
+    /// Returns `None` if the value holds a different type
+    fn from_tox(value: Value) -> Option<Self>;
+}
+
+/// A value was converted to a rust type other than the type it holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConversionError;
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The value holds a different type")
+    }
+}
+
+macro_rules! primitive {
+    ($ty:ty, $new:ident, $is:ident, $as:ident) => {
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Value {
+                Value::$new(value)
+            }
+        }
+
+        impl IntoTox for $ty {
+            fn into_tox(self) -> Value {
+                Value::$new(self)
+            }
+        }
+
+        impl FromTox for $ty {
+            fn from_tox(value: Value) -> Option<$ty> {
+                if value.$is() {
+                    Some(value.$as())
+                } else {
+                    None
+                }
+            }
+        }
+
+        impl TryFrom<Value> for $ty {
+            type Error = ConversionError;
+
+            fn try_from(value: Value) -> Result<$ty, ConversionError> {
+                <$ty>::from_tox(value).ok_or(ConversionError)
+            }
+        }
+    };
+}
+
+primitive!(i64, int, is_int, as_int);
+primitive!(f64, float, is_float, as_float);
+primitive!(bool, bool, is_bool, as_bool);
+primitive!(char, char, is_char, as_char);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::from(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::object(StringObject::from_owned(value, ptr::null_mut()))
+    }
+}
+
+impl IntoTox for &str {
+    fn into_tox(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoTox for String {
+    fn into_tox(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl FromTox for String {
+    fn from_tox(value: Value) -> Option<String> {
+        value
+            .as_string_object()
+            .map(|string| string.chars.string().to_owned())
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Value) -> Result<String, ConversionError> {
+        String::from_tox(value).ok_or(ConversionError)
+    }
+}
+
+/// `None` is passed to tox as `nil`
+impl<T: IntoTox> IntoTox for Option<T> {
+    fn into_tox(self) -> Value {
+        match self {
+            Some(value) => value.into_tox(),
+            None => Value::nil(),
+        }
+    }
+}
+
+impl<T: FromTox> FromTox for Option<T> {
+    fn from_tox(value: Value) -> Option<Option<T>> {
+        if value.is_nil() {
+            Some(None)
+        } else {
+            T::from_tox(value).map(Some)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConversionError, FromTox, IntoTox};
+    use crate::value::Value;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn round_trip_primitives() {
+        assert_eq!(i64::try_from(Value::from(42)), Ok(42));
+        assert_eq!(f64::try_from(Value::from(1.5)), Ok(1.5));
+        assert_eq!(bool::try_from(Value::from(true)), Ok(true));
+        assert_eq!(char::try_from(Value::from('x')), Ok('x'));
+        assert_eq!(String::try_from(Value::from("tox")), Ok("tox".to_string()));
+    }
+
+    #[test]
+    fn round_trip_through_traits() {
+        assert_eq!(i64::from_tox((-7i64).into_tox()), Some(-7));
+        assert_eq!(f64::from_tox(0.25f64.into_tox()), Some(0.25));
+        assert_eq!(bool::from_tox(false.into_tox()), Some(false));
+        assert_eq!(char::from_tox('λ'.into_tox()), Some('λ'));
+        assert_eq!(
+            String::from_tox(String::from("owned").into_tox()),
+            Some("owned".to_string())
+        );
+    }
+
+    #[test]
+    fn option_is_nil() {
+        assert!(None::<i64>.into_tox().is_nil());
+        assert_eq!(Option::<i64>::from_tox(Value::nil()), Some(None));
+        assert_eq!(Option::<i64>::from_tox(Some(3i64).into_tox()), Some(Some(3)));
+    }
+
+    #[test]
+    fn wrong_type_is_an_error() {
+        assert_eq!(i64::try_from(Value::from(1.0)), Err(ConversionError));
+        assert_eq!(bool::from_tox(Value::from(1)), None);
+        assert_eq!(String::from_tox(Value::from('s')), None);
+        assert_eq!(Option::<i64>::from_tox(Value::from("1")), None);
+    }
+}
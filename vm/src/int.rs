@@ -0,0 +1,95 @@
+//! The integer types that are smaller than an int.
+//! Their values are stored as an int and brought back into range after every operation
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum IntKind {
+    I8,
+    I16,
+    I32,
+    U8,
+    U16,
+    U32,
+}
+
+impl IntKind {
+    pub const ALL: [IntKind; 6] = [
+        IntKind::I8,
+        IntKind::I16,
+        IntKind::I32,
+        IntKind::U8,
+        IntKind::U16,
+        IntKind::U32,
+    ];
+
+    /// The kind encoded in the operand of `WRAPINT` and `CHECKINT`
+    pub fn from_byte(byte: u8) -> Option<IntKind> {
+        IntKind::ALL.get(byte as usize).cloned()
+    }
+
+    /// The name of the type in source code
+    pub fn name(self) -> &'static str {
+        match self {
+            IntKind::I8 => "i8",
+            IntKind::I16 => "i16",
+            IntKind::I32 => "i32",
+            IntKind::U8 => "u8",
+            IntKind::U16 => "u16",
+            IntKind::U32 => "u32",
+        }
+    }
+
+    /// Truncates `value` to the size of the kind, wrapping around on overflow
+    pub fn wrap(self, value: i64) -> i64 {
+        match self {
+            IntKind::I8 => i64::from(value as i8),
+            IntKind::I16 => i64::from(value as i16),
+            IntKind::I32 => i64::from(value as i32),
+            IntKind::U8 => i64::from(value as u8),
+            IntKind::U16 => i64::from(value as u16),
+            IntKind::U32 => i64::from(value as u32),
+        }
+    }
+
+    /// Whether `value` can be stored without being truncated
+    pub fn fits(self, value: i64) -> bool {
+        self.wrap(value) == value
+    }
+}
+
+impl Display for IntKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IntKind;
+
+    #[test]
+    fn wrap() {
+        assert_eq!(IntKind::U8.wrap(256), 0);
+        assert_eq!(IntKind::U8.wrap(-1), 255);
+        assert_eq!(IntKind::I8.wrap(128), -128);
+        assert_eq!(IntKind::U32.wrap(1 << 32), 0);
+    }
+
+    #[test]
+    fn fits() {
+        assert!(IntKind::U8.fits(255));
+        assert!(!IntKind::U8.fits(300));
+        assert!(!IntKind::U16.fits(-1));
+        assert!(IntKind::I32.fits(-1));
+    }
+
+    #[test]
+    fn byte_round_trip() {
+        for kind in IntKind::ALL.iter() {
+            assert_eq!(IntKind::from_byte(*kind as u8), Some(*kind));
+        }
+
+        assert_eq!(IntKind::from_byte(6), None);
+    }
+}
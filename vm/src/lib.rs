@@ -7,16 +7,23 @@ use opcode;
 #[macro_use]
 mod macros;
 mod chunk;
+mod convert;
+mod int;
 mod native;
 mod object;
 mod value;
+mod verifier;
 mod vm;
 
 pub use crate::chunk::Chunk;
+pub use crate::convert::{ConversionError, FromTox, IntoTox};
+pub use crate::int::IntKind;
 pub use crate::object::{FunctionObject, RawObject, StringObject};
 pub use crate::value::Value;
-pub use crate::vm::VM;
+pub use crate::verifier::{instruction_len, verify, VerifyError};
+pub use crate::vm::{Error, HostFunction, Profile, VM};
 use fnv::FnvHashMap;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Function {
@@ -28,11 +35,51 @@ pub struct Function {
 #[derive(Debug, Clone)]
 pub struct Class {
     pub name: ::util::symbol::Symbol,
-    pub methods: FnvHashMap<::util::symbol::Symbol, Function>,
+    /// Shared with the function objects made from them so getting a method doesn't copy it
+    pub methods: FnvHashMap<::util::symbol::Symbol, Rc<Function>>,
+    /// The class this class extends.
+    /// Methods not found on the class are looked up on the superclass
+    pub superclass: Option<::util::symbol::Symbol>,
+    /// The properties in the order they were declared
+    pub properties: Vec<::util::symbol::Symbol>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Program {
-    pub functions: FnvHashMap<::util::symbol::Symbol, Function>,
+    /// Shared with the function objects made from them so getting a function doesn't copy it
+    pub functions: FnvHashMap<::util::symbol::Symbol, Rc<Function>>,
     pub classes: FnvHashMap<::util::symbol::Symbol, Class>,
+    /// The names of the classes and their properties.
+    /// Used when printing a class instance
+    pub names: FnvHashMap<::util::symbol::Symbol, String>,
+    /// The constants used by every function and method.
+    /// `CONSTANT` refers to an index in this pool so a constant used in many places is stored once
+    pub constants: Vec<Value>,
+}
+
+impl Class {
+    /// The methods ordered by their symbol
+    pub fn ordered_methods(&self) -> Vec<&Function> {
+        let mut methods: Vec<&Function> = self.methods.values().map(|method| &**method).collect();
+        methods.sort_by_key(|method| method.name.0);
+        methods
+    }
+}
+
+impl Program {
+    /// The functions ordered by their symbol.
+    /// Used for output that has to be the same on every run
+    pub fn ordered_functions(&self) -> Vec<&Function> {
+        let mut functions: Vec<&Function> =
+            self.functions.values().map(|function| &**function).collect();
+        functions.sort_by_key(|function| function.name.0);
+        functions
+    }
+
+    /// The classes ordered by their symbol
+    pub fn ordered_classes(&self) -> Vec<&Class> {
+        let mut classes: Vec<&Class> = self.classes.values().collect();
+        classes.sort_by_key(|class| class.name.0);
+        classes
+    }
 }
@@ -1,12 +1,27 @@
 macro_rules! binary_op {
-    ($op:tt,$into:ident,$return_ty:ident,$_self:ident) => {{
+    ($op:tt,$pop:ident,$return_ty:ident,$_self:ident) => {{
 
 
-            let b = $_self.pop().$into();
+            let b = $_self.$pop()?;
 
-            let a = $_self.pop().$into();
+            let a = $_self.$pop()?;
 
-            $_self.push(Value::$return_ty(a $op b))
+            $_self.push(Value::$return_ty(a $op b))?
 
     }};
 }
+
+/// Applies a checked integer operation to the top two values on the stack.
+/// Returns `Error::Overflow` from the enclosing function if the result doesn't fit
+macro_rules! checked_int_op {
+    ($op:ident, $_self:ident) => {{
+        let b = $_self.pop_int()?;
+
+        let a = $_self.pop_int()?;
+
+        match a.$op(b) {
+            Some(result) => $_self.push(Value::int(result))?,
+            None => return Err(Error::Overflow),
+        }
+    }};
+}
@@ -1,11 +1,11 @@
 //! Contains all the values that are objects within tox
 //! When adding a new object make sure the first field is obj:Object otherwise the transmutes will fail
 #![allow(clippy::new_ret_no_self)]
-use super::Function;
 use crate::value::Value;
 use fnv::FnvHashMap;
 use std::fmt::{self, Debug, Display};
 use std::ops::Deref;
+use std::rc::Rc;
 use util::symbol::Symbol;
 
 pub type RawObject = *mut Object;
@@ -21,6 +21,7 @@ pub enum ObjectType {
     Instance,
     Native,
     Enum,
+    Tuple,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,6 +62,13 @@ pub struct ArrayObject {
     pub items: Vec<Value>,
 }
 
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct TupleObject {
+    pub obj: Object,
+    pub items: Vec<Value>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub struct ClassObject {
@@ -72,8 +80,9 @@ pub struct ClassObject {
 #[repr(C)]
 pub struct InstanceObject {
     pub obj: Object,
+    /// The class the instance was created from
+    pub name: Symbol,
     pub properties: FnvHashMap<Symbol, Value>,
-    pub methods: FnvHashMap<Symbol, Function>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -81,7 +90,7 @@ pub struct InstanceObject {
 pub struct FunctionObject {
     pub obj: Object,
     pub arity: usize,
-    pub function: super::Function,
+    pub function: Rc<super::Function>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -115,7 +124,7 @@ impl NativeObject {
 }
 
 impl FunctionObject {
-    pub fn new(arity: usize, function: super::Function, next: RawObject) -> RawObject {
+    pub fn new(arity: usize, function: Rc<super::Function>, next: RawObject) -> RawObject {
         let func = FunctionObject {
             obj: Object::new(ObjectType::Func, next),
             function,
@@ -137,6 +146,17 @@ impl ArrayObject {
     }
 }
 
+impl TupleObject {
+    pub fn new(items: Vec<Value>, next: RawObject) -> RawObject {
+        let tuple = TupleObject {
+            obj: Object::new(ObjectType::Tuple, next),
+            items,
+        };
+
+        Box::into_raw(Box::new(tuple)) as RawObject
+    }
+}
+
 impl EnumObject {
     pub fn new(name: Symbol, tag: u32, data: Option<Value>, next: RawObject) -> RawObject {
         let _enum = EnumObject {
@@ -151,14 +171,10 @@ impl EnumObject {
 }
 
 impl InstanceObject {
-    pub fn new(
-        methods: FnvHashMap<Symbol, Function>,
-        properties: FnvHashMap<Symbol, Value>,
-        next: RawObject,
-    ) -> RawObject {
+    pub fn new(name: Symbol, properties: FnvHashMap<Symbol, Value>, next: RawObject) -> RawObject {
         let array = InstanceObject {
             obj: Object::new(ObjectType::Instance, next),
-            methods,
+            name,
             properties,
         };
 
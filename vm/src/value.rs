@@ -8,8 +8,8 @@ pub use self::normal::*;
 mod NAN_tagging {
 
     use crate::object::{
-        ArrayObject, ClassObject, FunctionObject, InstanceObject, NativeObject, Object, ObjectType,
-        RawObject, StringObject,
+        ArrayObject, ClassObject, EnumObject, FunctionObject, InstanceObject, NativeObject, Object,
+        ObjectType, RawObject, StringObject, TupleObject,
     };
 
     use std::fmt::{self, Debug, Display};
@@ -111,6 +111,167 @@ mod NAN_tagging {
             self.is_obj_type(ObjectType::Native)
         }
 
+        #[inline]
+        pub fn is_instance(&self) -> bool {
+            self.is_obj_type(ObjectType::Instance)
+        }
+
+        #[inline]
+        pub fn is_array(&self) -> bool {
+            self.is_obj_type(ObjectType::Array)
+        }
+
+        #[inline]
+        pub fn is_function(&self) -> bool {
+            self.is_obj_type(ObjectType::Func)
+        }
+
+        #[inline]
+        pub fn is_tuple(&self) -> bool {
+            self.is_obj_type(ObjectType::Tuple)
+        }
+
+        #[inline]
+        pub fn is_enum(&self) -> bool {
+            self.is_obj_type(ObjectType::Enum)
+        }
+
+        /// The checked versions of the `as_` casts.
+        /// They return `None` when the value holds something other than the requested object
+        #[inline]
+        pub fn as_string_object<'a>(&self) -> Option<&'a StringObject<'a>> {
+            if self.is_string() {
+                Some(self.as_string())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_array_object<'a>(&self) -> Option<&'a ArrayObject> {
+            if self.is_array() {
+                Some(self.as_array())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_mut_array_object<'a>(&self) -> Option<&'a mut ArrayObject> {
+            if self.is_array() {
+                Some(self.as_mut_array())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_class_object<'a>(&self) -> Option<&'a ClassObject> {
+            if self.is_class() {
+                Some(self.as_class())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_function_object<'a>(&self) -> Option<&'a FunctionObject> {
+            if self.is_function() {
+                Some(self.as_function())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_native_object<'a>(&self) -> Option<&'a NativeObject> {
+            if self.is_native() {
+                Some(self.as_native())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_instance_object<'a>(&self) -> Option<&'a InstanceObject> {
+            if self.is_instance() {
+                Some(self.as_instance())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_mut_instance_object<'a>(&self) -> Option<&'a mut InstanceObject> {
+            if self.is_instance() {
+                Some(self.as_mut_instance())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_tuple_object<'a>(&self) -> Option<&'a TupleObject> {
+            if self.is_tuple() {
+                Some(self.as_tuple())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_enum_object<'a>(&self) -> Option<&'a EnumObject> {
+            if self.is_enum() {
+                Some(self.as_enum())
+            } else {
+                None
+            }
+        }
+
+        /// The value as shown by the repl.
+        /// Unlike printing, strings are quoted and arrays show their items
+        pub fn repr(&self) -> String {
+            self.repr_visiting(&mut Vec::new())
+        }
+
+        /// `visited` holds the arrays being shown further up so an array that contains itself
+        /// is shown as `[...]` instead of recursing forever
+        fn repr_visiting(&self, visited: &mut Vec<RawObject>) -> String {
+            if self.is_string() {
+                format!("{:?}", self.as_string().to_string())
+            } else if self.is_array() {
+                let array = self.as_object();
+
+                if visited.contains(&array) {
+                    return "[...]".into();
+                }
+
+                visited.push(array);
+
+                let items: Vec<String> = self
+                    .as_array()
+                    .items
+                    .iter()
+                    .map(|item| item.repr_visiting(visited))
+                    .collect();
+
+                visited.pop();
+
+                format!("[{}]", items.join(", "))
+            } else if self.is_tuple() {
+                let items: Vec<String> = self
+                    .as_tuple()
+                    .items
+                    .iter()
+                    .map(|item| item.repr_visiting(visited))
+                    .collect();
+
+                format!("({})", items.join(", "))
+            } else {
+                self.to_string()
+            }
+        }
+
         #[inline]
         pub fn is_int(&self) -> bool {
             (self.inner() & TAG_INT) == TAG_INT
@@ -177,6 +338,27 @@ mod NAN_tagging {
             unsafe { mem::transmute(ptr) }
         }
 
+        #[inline]
+        pub fn as_mut_array<'a>(&self) -> &'a mut ArrayObject {
+            let ptr = self.as_object();
+
+            unsafe { mem::transmute(ptr) }
+        }
+
+        #[inline]
+        pub fn as_tuple<'a>(&self) -> &'a TupleObject {
+            let ptr = self.as_object();
+
+            unsafe { mem::transmute(ptr) }
+        }
+
+        #[inline]
+        pub fn as_mut_tuple<'a>(&self) -> &'a mut TupleObject {
+            let ptr = self.as_object();
+
+            unsafe { mem::transmute(ptr) }
+        }
+
         #[inline]
         pub fn as_class<'a>(&self) -> &'a ClassObject {
             let ptr = self.as_object();
@@ -211,6 +393,20 @@ mod NAN_tagging {
 
             unsafe { mem::transmute(ptr) }
         }
+
+        #[inline]
+        pub fn as_enum<'a>(&self) -> &'a EnumObject {
+            let ptr = self.as_object();
+
+            unsafe { mem::transmute(ptr) }
+        }
+
+        #[inline]
+        pub fn as_mut_enum<'a>(&self) -> &'a mut EnumObject {
+            let ptr = self.as_object();
+
+            unsafe { mem::transmute(ptr) }
+        }
     }
 
     impl Debug for Value {
@@ -253,6 +449,11 @@ mod NAN_tagging {
                             "{:#?}",
                             mem::transmute::<RawObject, &NativeObject>(self.as_object())
                         )?,
+                        ObjectType::Tuple => write!(
+                            fmt,
+                            "{:#?}",
+                            mem::transmute::<RawObject, &TupleObject>(self.as_object())
+                        )?,
                     }
                 } else if self.is_nil() {
                     write!(fmt, "nil")?
@@ -293,6 +494,12 @@ mod NAN_tagging {
                         ObjectType::Instance => write!(fmt, "instance")?,
                         ObjectType::Native => write!(fmt, "native")?,
                         ObjectType::Enum => write!(fmt, "enum")?,
+                        ObjectType::Tuple => {
+                            let items: Vec<String> =
+                                self.as_tuple().items.iter().map(Value::to_string).collect();
+
+                            write!(fmt, "({})", items.join(", "))?
+                        }
                     }
                 }
             }
@@ -330,7 +537,7 @@ mod NAN_tagging {
 mod normal {
     use crate::object::{
         ArrayObject, ClassObject, EnumObject, FunctionObject, InstanceObject, NativeObject, Object,
-        ObjectType, RawObject, StringObject,
+        ObjectType, RawObject, StringObject, TupleObject,
     };
 
     use std::fmt::{self, Debug, Display};
@@ -349,6 +556,7 @@ mod normal {
         Float,
         Nil,
         Bool,
+        Char,
         Object,
     }
 
@@ -359,6 +567,7 @@ mod normal {
         boolean: bool,
         float: f64,
         int: i64,
+        character: char,
         /// A values whos state is stored on the heap
         object: RawObject,
     }
@@ -392,6 +601,13 @@ mod normal {
             }
         }
 
+        pub fn char(character: char) -> Value {
+            Value {
+                val: As { character },
+                ty: ValueType::Char,
+            }
+        }
+
         pub fn object(object: RawObject) -> Value {
             Value {
                 val: As { object },
@@ -438,6 +654,19 @@ mod normal {
             unsafe { self.val.float }
         }
 
+        #[inline]
+        pub fn as_char(&self) -> char {
+            debug_assert_eq!(
+                self.ty,
+                ValueType::Char,
+                "Value is type `{:?}` instead of {:?}",
+                self.ty,
+                ValueType::Char
+            );
+
+            unsafe { self.val.character }
+        }
+
         #[inline]
         pub fn as_object(&self) -> RawObject {
             debug_assert_eq!(
@@ -465,6 +694,27 @@ mod normal {
             unsafe { &*(ptr as *const ArrayObject) }
         }
 
+        #[inline]
+        pub fn as_mut_array<'a>(&self) -> &'a mut ArrayObject {
+            let ptr = self.as_object();
+
+            unsafe { &mut *(ptr as *mut ArrayObject) }
+        }
+
+        #[inline]
+        pub fn as_tuple<'a>(&self) -> &'a TupleObject {
+            let ptr = self.as_object();
+
+            unsafe { &*(ptr as *const TupleObject) }
+        }
+
+        #[inline]
+        pub fn as_mut_tuple<'a>(&self) -> &'a mut TupleObject {
+            let ptr = self.as_object();
+
+            unsafe { &mut *(ptr as *mut TupleObject) }
+        }
+
         #[inline]
         pub fn as_class<'a>(&self) -> &'a ClassObject {
             let ptr = self.as_object();
@@ -500,6 +750,45 @@ mod normal {
             unsafe { &mut *(ptr as *mut InstanceObject) }
         }
 
+        #[inline]
+        pub fn as_enum<'a>(&self) -> &'a EnumObject {
+            let ptr = self.as_object();
+
+            unsafe { &*(ptr as *const EnumObject) }
+        }
+
+        #[inline]
+        pub fn as_mut_enum<'a>(&self) -> &'a mut EnumObject {
+            let ptr = self.as_object();
+
+            unsafe { &mut *(ptr as *mut EnumObject) }
+        }
+
+        #[inline]
+        pub fn is_int(&self) -> bool {
+            self.ty == ValueType::Int
+        }
+
+        #[inline]
+        pub fn is_float(&self) -> bool {
+            self.ty == ValueType::Float
+        }
+
+        #[inline]
+        pub fn is_nil(&self) -> bool {
+            self.ty == ValueType::Nil
+        }
+
+        #[inline]
+        pub fn is_bool(&self) -> bool {
+            self.ty == ValueType::Bool
+        }
+
+        #[inline]
+        pub fn is_char(&self) -> bool {
+            self.ty == ValueType::Char
+        }
+
         #[inline]
         pub fn is_object(&self) -> bool {
             self.ty == ValueType::Object
@@ -518,6 +807,169 @@ mod normal {
         pub fn is_native(&self) -> bool {
             unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Native }
         }
+
+        #[inline]
+        pub fn is_instance(&self) -> bool {
+            unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Instance }
+        }
+
+        #[inline]
+        pub fn is_array(&self) -> bool {
+            unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Array }
+        }
+
+        #[inline]
+        pub fn is_function(&self) -> bool {
+            unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Func }
+        }
+
+        #[inline]
+        pub fn is_tuple(&self) -> bool {
+            unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Tuple }
+        }
+
+        #[inline]
+        pub fn is_enum(&self) -> bool {
+            unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Enum }
+        }
+
+        /// The checked versions of the `as_` casts.
+        /// They return `None` when the value holds something other than the requested object
+        #[inline]
+        pub fn as_string_object<'a>(&self) -> Option<&StringObject<'a>> {
+            if self.is_string() {
+                Some(self.as_string())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_array_object<'a>(&self) -> Option<&'a ArrayObject> {
+            if self.is_array() {
+                Some(self.as_array())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_mut_array_object<'a>(&self) -> Option<&'a mut ArrayObject> {
+            if self.is_array() {
+                Some(self.as_mut_array())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_class_object<'a>(&self) -> Option<&'a ClassObject> {
+            if self.is_class() {
+                Some(self.as_class())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_function_object<'a>(&self) -> Option<&'a FunctionObject> {
+            if self.is_function() {
+                Some(self.as_function())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_native_object<'a>(&self) -> Option<&'a NativeObject> {
+            if self.is_native() {
+                Some(self.as_native())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_instance_object<'a>(&self) -> Option<&'a InstanceObject> {
+            if self.is_instance() {
+                Some(self.as_instance())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_mut_instance_object<'a>(&self) -> Option<&'a mut InstanceObject> {
+            if self.is_instance() {
+                Some(self.as_mut_instance())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_tuple_object<'a>(&self) -> Option<&'a TupleObject> {
+            if self.is_tuple() {
+                Some(self.as_tuple())
+            } else {
+                None
+            }
+        }
+
+        #[inline]
+        pub fn as_enum_object<'a>(&self) -> Option<&'a EnumObject> {
+            if self.is_enum() {
+                Some(self.as_enum())
+            } else {
+                None
+            }
+        }
+
+        /// The value as shown by the repl.
+        /// Unlike printing, strings are quoted and arrays show their items
+        pub fn repr(&self) -> String {
+            self.repr_visiting(&mut Vec::new())
+        }
+
+        /// `visited` holds the arrays being shown further up so an array that contains itself
+        /// is shown as `[...]` instead of recursing forever
+        fn repr_visiting(&self, visited: &mut Vec<RawObject>) -> String {
+            if self.is_string() {
+                format!("{:?}", self.as_string().to_string())
+            } else if self.ty == ValueType::Char {
+                format!("{:?}", self.as_char())
+            } else if self.is_array() {
+                let array = self.as_object();
+
+                if visited.contains(&array) {
+                    return "[...]".into();
+                }
+
+                visited.push(array);
+
+                let items: Vec<String> = self
+                    .as_array()
+                    .items
+                    .iter()
+                    .map(|item| item.repr_visiting(visited))
+                    .collect();
+
+                visited.pop();
+
+                format!("[{}]", items.join(", "))
+            } else if self.is_tuple() {
+                let items: Vec<String> = self
+                    .as_tuple()
+                    .items
+                    .iter()
+                    .map(|item| item.repr_visiting(visited))
+                    .collect();
+
+                format!("({})", items.join(", "))
+            } else {
+                self.to_string()
+            }
+        }
     }
 
     impl Debug for Value {
@@ -535,6 +987,10 @@ mod normal {
                         write!(fmt, "val:{:?},", self.val.boolean)?;
                     }
 
+                    ValueType::Char => {
+                        write!(fmt, "val:{:?},", self.val.character)?;
+                    }
+
                     ValueType::Object => {
                         let obj: &Object = &*self.as_object();
 
@@ -562,6 +1018,10 @@ mod normal {
                             ObjectType::Native => {
                                 write!(fmt, "{:#?}", &*(self.val.object as *const NativeObject))?
                             }
+
+                            ObjectType::Tuple => {
+                                write!(fmt, "{:#?}", &*(self.val.object as *const TupleObject))?
+                            }
                         }
                     }
                 }
@@ -584,6 +1044,8 @@ mod normal {
                     write!(fmt, "{}", self.val.float)?;
                 } else if self.ty == ValueType::Bool {
                     write!(fmt, "{}", self.val.boolean)?;
+                } else if self.ty == ValueType::Char {
+                    write!(fmt, "{}", self.val.character)?;
                 } else if self.ty == ValueType::Object {
                     let obj: &Object = &*self.as_object();
 
@@ -595,6 +1057,12 @@ mod normal {
                         ObjectType::Instance => write!(fmt, "instance")?,
                         ObjectType::Native => write!(fmt, "native")?,
                         ObjectType::Enum => write!(fmt, "enum")?,
+                        ObjectType::Tuple => {
+                            let items: Vec<String> =
+                                self.as_tuple().items.iter().map(Value::to_string).collect();
+
+                            write!(fmt, "({})", items.join(", "))?
+                        }
                     }
                 }
             }
@@ -605,85 +1073,159 @@ mod normal {
 
     impl PartialEq for Value {
         fn eq(&self, other: &Value) -> bool {
+            self.equals(other, &mut Vec::new())
+        }
+    }
+
+    impl Value {
+        /// Arrays are compared element-wise and instances field-wise.
+        /// `visited` holds the pairs of objects being compared further up so a cycle is treated
+        /// as equal instead of recursing forever
+        fn equals(&self, other: &Value, visited: &mut Vec<(RawObject, RawObject)>) -> bool {
             if self.ty != other.ty {
-                false
-            } else {
-                match self.ty {
-                    ValueType::Bool => self.as_bool() == other.as_bool(),
-                    ValueType::Nil => false,
-                    ValueType::Int => self.as_int() == other.as_int(),
-                    ValueType::Float => self.as_float() == other.as_float(),
-                    ValueType::Object => unsafe {
-                        let self_object: &Object = &*self.as_object();
-
-                        match self_object.ty {
-                            ObjectType::String => {
-                                let self_string: &StringObject =
-                                    &*(self.as_object() as *const StringObject<'_>);
-                                let other_string: &StringObject =
-                                    &*(other.as_object() as *const StringObject<'_>);
+                return false;
+            }
 
-                                self_string.chars == other_string.chars
-                            }
-                            ObjectType::Array => {
-                                let self_array: &ArrayObject =
-                                    &*(self.as_object() as *const ArrayObject);
-                                let other_array: &ArrayObject =
-                                    &*(other.as_object() as *const ArrayObject);
+            match self.ty {
+                ValueType::Bool => self.as_bool() == other.as_bool(),
+                ValueType::Nil => true,
+                ValueType::Int => self.as_int() == other.as_int(),
+                // compared as floats so NaN != NaN and -0.0 == 0.0
+                ValueType::Float => self.as_float() == other.as_float(),
+                ValueType::Char => self.as_char() == other.as_char(),
+                ValueType::Object => unsafe {
+                    let self_object: &Object = &*self.as_object();
+                    let other_object: &Object = &*other.as_object();
+
+                    if self_object.ty != other_object.ty {
+                        return false;
+                    }
 
-                                self_array.items == other_array.items
-                            }
+                    match self_object.ty {
+                        ObjectType::String => {
+                            let self_string: &StringObject =
+                                &*(self.as_object() as *const StringObject<'_>);
+                            let other_string: &StringObject =
+                                &*(other.as_object() as *const StringObject<'_>);
 
-                            ObjectType::Instance => {
-                                let self_instance: &InstanceObject =
-                                    &*(self.as_object() as *const InstanceObject);
-                                let other_instance: &InstanceObject =
-                                    &*(other.as_object() as *const InstanceObject);
+                            self_string.chars == other_string.chars
+                        }
+
+                        ObjectType::Array | ObjectType::Tuple | ObjectType::Instance => {
+                            let pair = (self.as_object(), other.as_object());
 
-                                self_instance.properties == other_instance.properties
-                                //shoud we check if methods are the same as well ?
+                            // an object isn't equal to itself just because it is the same
+                            // object as it could hold a NaN
+                            if visited.contains(&pair) {
+                                return true;
                             }
 
-                            ObjectType::Class => {
-                                let self_class: &ClassObject =
-                                    &*(self.as_object() as *const ClassObject);
-                                let other_class: &ClassObject =
-                                    &*(other.as_object() as *const ClassObject);
+                            visited.push(pair);
+
+                            let equal = if self_object.ty != ObjectType::Instance {
+                                let (self_items, other_items) =
+                                    if self_object.ty == ObjectType::Array {
+                                        (&self.as_array().items, &other.as_array().items)
+                                    } else {
+                                        (&self.as_tuple().items, &other.as_tuple().items)
+                                    };
+
+                                self_items.len() == other_items.len()
+                                    && self_items
+                                        .iter()
+                                        .zip(other_items)
+                                        .all(|(a, b)| a.equals(b, visited))
+                            } else {
+                                let self_instance = self.as_instance();
+                                let other_instance = other.as_instance();
+
+                                self_instance.name == other_instance.name
+                                    && self_instance.properties.len()
+                                        == other_instance.properties.len()
+                                    && self_instance.properties.iter().all(|(name, a)| {
+                                        let b = other_instance.properties.get(name);
+                                        b.map_or(false, |b| a.equals(b, visited))
+                                    })
+                            };
+
+                            visited.pop();
+
+                            equal
+                        }
 
-                                self_class == other_class
-                            }
-                            ObjectType::Func => {
-                                let self_func: &FunctionObject =
-                                    &*(self.as_object() as *const FunctionObject);
-                                let other_func: &FunctionObject =
-                                    &*(other.as_object() as *const FunctionObject);
+                        ObjectType::Class => {
+                            let self_class: &ClassObject =
+                                &*(self.as_object() as *const ClassObject);
+                            let other_class: &ClassObject =
+                                &*(other.as_object() as *const ClassObject);
 
-                                self_func == other_func
-                            }
+                            self_class == other_class
+                        }
+                        ObjectType::Func => {
+                            let self_func: &FunctionObject =
+                                &*(self.as_object() as *const FunctionObject);
+                            let other_func: &FunctionObject =
+                                &*(other.as_object() as *const FunctionObject);
 
-                            ObjectType::Enum => {
-                                let self_enum: &EnumObject =
-                                    &*(self.as_object() as *const EnumObject);
+                            self_func == other_func
+                        }
 
-                                let other_enum: &EnumObject =
-                                    &*(other.as_object() as *const EnumObject);
+                        ObjectType::Enum => {
+                            let self_enum: &EnumObject =
+                                &*(self.as_object() as *const EnumObject);
 
-                                self_enum.name == other_enum.name
-                                    && self_enum.tag == other_enum.tag
-                                    && self_enum.data == other_enum.data
-                            }
-                            ObjectType::Native => {
-                                let self_native: &NativeObject =
-                                    &*(self.as_object() as *const NativeObject);
-                                let other_native: &NativeObject =
-                                    &*(other.as_object() as *const NativeObject);
+                            let other_enum: &EnumObject =
+                                &*(other.as_object() as *const EnumObject);
 
-                                self_native == other_native
-                            }
+                            self_enum.name == other_enum.name
+                                && self_enum.tag == other_enum.tag
+                                && match (self_enum.data, other_enum.data) {
+                                    (Some(a), Some(b)) => a.equals(&b, visited),
+                                    (None, None) => true,
+                                    _ => false,
+                                }
                         }
-                    },
-                }
+                        ObjectType::Native => {
+                            let self_native: &NativeObject =
+                                &*(self.as_object() as *const NativeObject);
+                            let other_native: &NativeObject =
+                                &*(other.as_object() as *const NativeObject);
+
+                            self_native == other_native
+                        }
+                    }
+                },
             }
         }
     }
+
+    #[cfg(test)]
+    mod test {
+        use super::Value;
+        use crate::object::ArrayObject;
+        use std::ptr;
+
+        #[test]
+        fn floats_follow_ieee_equality() {
+            let nan = Value::float(f64::NAN);
+
+            assert_ne!(nan, nan);
+            assert_eq!(Value::float(0.0), Value::float(-0.0));
+            assert_eq!(Value::float(1.5), Value::float(1.5));
+        }
+
+        #[test]
+        fn arrays_holding_nan_are_not_equal_to_themselves() {
+            let nan = Value::object(ArrayObject::new(
+                vec![Value::float(f64::NAN)],
+                ptr::null_mut(),
+            ));
+            let zero = Value::object(ArrayObject::new(vec![Value::float(0.0)], ptr::null_mut()));
+            let negative_zero =
+                Value::object(ArrayObject::new(vec![Value::float(-0.0)], ptr::null_mut()));
+
+            assert_ne!(nan, nan);
+            assert_eq!(zero, negative_zero);
+        }
+    }
 }
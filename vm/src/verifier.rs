@@ -0,0 +1,402 @@
+//! A pass that checks the stack usage of a chunk without running it.
+//! Every path through a function must reach each instruction with the same
+//! number of values on the stack and return with only the return value left on it.
+use crate::chunk::Chunk;
+use crate::opcode;
+use std::fmt::{self, Display};
+use util::symbol::{Symbol, CLOCK, CLONE, FILTER, FOPEN, MAP, RANDOM, READ, REDUCE};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// An instruction pops more values than there are on the stack
+    Underflow { offset: usize },
+    /// Two paths reach the instruction with a different number of values on the stack
+    Mismatch {
+        offset: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The function returns or ends with values left on the stack
+    Unbalanced { offset: usize, depth: usize },
+    /// An operand or a jump goes past the end of the chunk
+    OutOfBounds { offset: usize },
+    UnknownOpcode { offset: usize, opcode: u8 },
+    /// A `CALLNATIVE` refers to a function that isn't one of the built in natives.
+    /// Functions registered by the host aren't known until the program is run
+    UnknownNative { offset: usize, native: u8 },
+}
+
+/// The number of values an instruction pops and pushes
+struct Effect {
+    len: usize,
+    pops: usize,
+    pushes: usize,
+}
+
+/// Checks that the stack is balanced in `chunk`.
+/// Returns the offset of the first imbalance found
+pub fn verify(chunk: &Chunk) -> Result<(), VerifyError> {
+    let code = &chunk.code;
+
+    let mut depths: Vec<Option<usize>> = vec![None; code.len()];
+    let mut pending = vec![(0, 0)];
+
+    while let Some((offset, depth)) = pending.pop() {
+        if offset == code.len() {
+            // fell off the end of the function
+            if depth != 0 {
+                return Err(VerifyError::Unbalanced { offset, depth });
+            }
+
+            continue;
+        }
+
+        match depths[offset] {
+            Some(expected) if expected != depth => {
+                return Err(VerifyError::Mismatch {
+                    offset,
+                    expected,
+                    found: depth,
+                });
+            }
+            Some(_) => continue, // already checked from this depth
+            None => depths[offset] = Some(depth),
+        }
+
+        let effect = effect(code, offset)?;
+
+        if depth < effect.pops {
+            return Err(VerifyError::Underflow { offset });
+        }
+
+        let next = offset + effect.len;
+        let after = depth - effect.pops + effect.pushes;
+
+        match code[offset] {
+            opcode::HLT => (),
+            opcode::RETURN => {
+                if depth != 1 {
+                    return Err(VerifyError::Unbalanced { offset, depth });
+                }
+            }
+
+            opcode::JUMP => pending.push((jump_target(code, offset, next)?, after)),
+            opcode::LOOP => pending.push((loop_target(code, offset, next)?, after)),
+            opcode::JUMPIF | opcode::JUMPNOT => {
+                pending.push((jump_target(code, offset, next)?, after));
+                pending.push((next, after));
+            }
+
+            _ => pending.push((next, after)),
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of bytes taken by the instruction at `offset` including its operands
+pub fn instruction_len(code: &[u8], offset: usize) -> Result<usize, VerifyError> {
+    match effect(code, offset) {
+        // the length doesn't depend on the arity of the native
+        Err(VerifyError::UnknownNative { .. }) => Ok(2),
+        effect => Ok(effect?.len),
+    }
+}
+
+fn effect(code: &[u8], offset: usize) -> Result<Effect, VerifyError> {
+    let operand = |n: usize| {
+        code.get(offset + n)
+            .map(|byte| *byte as usize)
+            .ok_or(VerifyError::OutOfBounds { offset })
+    };
+
+    let (len, pops, pushes) = match code[offset] {
+        opcode::HLT => (1, 0, 0),
+        opcode::RETURN => (1, 1, 0),
+        opcode::NIL | opcode::TRUE | opcode::FALSE => (1, 0, 1),
+        opcode::PRINT | opcode::EPRINT | opcode::POP => (1, 1, 0),
+
+        opcode::NEGATE
+        | opcode::NEGATEF
+        | opcode::NOT
+        | opcode::INT2FLOAT
+        | opcode::FLOAT2INT
+        | opcode::BOOL2INT
+        | opcode::INT2STR
+        | opcode::FLOAT2STR
+        | opcode::CHAR2INT
+        | opcode::INT2CHAR
+        | opcode::CHAR2STR
+        | opcode::ENUMTAG
+        | opcode::ENUMINNER => (1, 1, 1),
+
+        opcode::EQUAL
+        | opcode::GREATER
+        | opcode::GREATERF
+        | opcode::LESS
+        | opcode::LESSF
+        | opcode::LESSEQUALF
+        | opcode::GREATEREQUALF
+        | opcode::LESSSTR
+        | opcode::GREATERSTR
+        | opcode::ADD
+        | opcode::ADDF
+        | opcode::SUB
+        | opcode::SUBF
+        | opcode::MUL
+        | opcode::MULF
+        | opcode::DIV
+        | opcode::DIVF
+        | opcode::CONCAT
+        | opcode::INDEXARRAY
+        | opcode::INDEXSTRING => (1, 2, 1),
+
+        opcode::DUP => (1, 1, 2),
+        opcode::SWAP => (1, 2, 2),
+        opcode::DUP2 => (1, 2, 4),
+        opcode::SETINDEXARRAY => (1, 3, 0),
+
+        opcode::CONSTANT | opcode::GETLOCAL | opcode::GETPARAM | opcode::GETFUNCTION => {
+            (2, 0, 1)
+        }
+        // the value is left on the stack
+        opcode::SETLOCAL | opcode::SETPARAM => (2, 1, 1),
+        opcode::GETPROPERTY | opcode::GETMETHOD => (2, 1, 1),
+        opcode::WRAPINT | opcode::CHECKINT | opcode::INDEXTUPLE | opcode::CHECKLEN => (2, 1, 1),
+        opcode::SETPROPERTY => (2, 2, 0),
+
+        opcode::JUMP | opcode::LOOP | opcode::JUMPIF | opcode::JUMPNOT => (3, 0, 0),
+
+        opcode::ENUM => (3, 0, 1),
+        opcode::ENUMDATA => (3, 1, 1),
+
+        opcode::ARRAY | opcode::TUPLE => (2, operand(1)?, 1),
+        opcode::CALL => (3, operand(2)?, 1),
+        opcode::CALLVALUE => (2, operand(1)? + 1, 1), // the callee is popped too
+        opcode::CALLNATIVE => {
+            let native = operand(1)?;
+
+            match native_arity(Symbol(native as u64)) {
+                Some(arity) => (2, arity, 1),
+                None => {
+                    return Err(VerifyError::UnknownNative {
+                        offset,
+                        native: native as u8,
+                    })
+                }
+            }
+        }
+        opcode::CALLINSTANCEMETHOD => (3, operand(2)? + 1, 1),
+        opcode::CALLSTATICMETHOD => (4, operand(3)?, 1),
+        opcode::CLASSINSTANCE => {
+            let properties = operand(2)?;
+            (3 + properties, properties, 1) // followed by the name of each property
+        }
+
+        opcode => return Err(VerifyError::UnknownOpcode { offset, opcode }),
+    };
+
+    if offset + len > code.len() {
+        return Err(VerifyError::OutOfBounds { offset });
+    }
+
+    Ok(Effect { len, pops, pushes })
+}
+
+/// The number of arguments taken by the built in native `symbol`
+fn native_arity(symbol: Symbol) -> Option<usize> {
+    match symbol {
+        RANDOM => Some(2),
+        CLOCK => Some(0),
+        READ => Some(0),
+        FOPEN => Some(1),
+        MAP => Some(2),
+        FILTER => Some(2),
+        REDUCE => Some(3),
+        CLONE => Some(1),
+        _ => None,
+    }
+}
+
+fn read_u16(code: &[u8], offset: usize) -> usize {
+    (usize::from(code[offset + 1]) << 8) | usize::from(code[offset + 2])
+}
+
+fn jump_target(code: &[u8], offset: usize, next: usize) -> Result<usize, VerifyError> {
+    let target = next + read_u16(code, offset);
+
+    if target > code.len() {
+        Err(VerifyError::OutOfBounds { offset })
+    } else {
+        Ok(target)
+    }
+}
+
+fn loop_target(code: &[u8], offset: usize, next: usize) -> Result<usize, VerifyError> {
+    next.checked_sub(read_u16(code, offset))
+        .ok_or(VerifyError::OutOfBounds { offset })
+}
+
+impl VerifyError {
+    /// The offset of the instruction the error was found at
+    pub fn offset(&self) -> usize {
+        match *self {
+            VerifyError::Underflow { offset }
+            | VerifyError::Mismatch { offset, .. }
+            | VerifyError::Unbalanced { offset, .. }
+            | VerifyError::OutOfBounds { offset }
+            | VerifyError::UnknownOpcode { offset, .. }
+            | VerifyError::UnknownNative { offset, .. } => offset,
+        }
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Underflow { offset } => write!(f, "{:04}: stack underflow", offset),
+            VerifyError::Mismatch {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{:04}: expected a stack depth of {} but found {}",
+                offset, expected, found
+            ),
+            VerifyError::Unbalanced { offset, depth } => write!(
+                f,
+                "{:04}: function ends with a stack depth of {}",
+                offset, depth
+            ),
+            VerifyError::OutOfBounds { offset } => {
+                write!(f, "{:04}: instruction goes past the end of the chunk", offset)
+            }
+            VerifyError::UnknownOpcode { offset, opcode } => {
+                write!(f, "{:04}: unknown opcode {}", offset, opcode)
+            }
+            VerifyError::UnknownNative { offset, native } => {
+                write!(f, "{:04}: unknown native {}", offset, native)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{instruction_len, verify, VerifyError};
+    use crate::chunk::Chunk;
+    use crate::opcode;
+    use util::symbol::CLONE;
+
+    fn chunk(code: Vec<u8>) -> Chunk {
+        Chunk {
+            lines: vec![(0, code.len())],
+            code,
+        }
+    }
+
+    #[test]
+    fn balanced() {
+        let chunk = chunk(vec![
+            opcode::CONSTANT,
+            0,
+            opcode::CONSTANT,
+            1,
+            opcode::ADD,
+            opcode::RETURN,
+        ]);
+
+        assert_eq!(verify(&chunk), Ok(()));
+    }
+
+    #[test]
+    fn balanced_branches() {
+        // if true { 1 } else { 2 }
+        let chunk = chunk(vec![
+            opcode::TRUE,
+            opcode::JUMPNOT,
+            0,
+            6,
+            opcode::POP,
+            opcode::CONSTANT,
+            0,
+            opcode::JUMP,
+            0,
+            3,
+            opcode::POP,
+            opcode::CONSTANT,
+            1,
+            opcode::RETURN,
+        ]);
+
+        assert_eq!(verify(&chunk), Ok(()));
+    }
+
+    #[test]
+    fn leaky() {
+        let chunk = chunk(vec![
+            opcode::CONSTANT,
+            0,
+            opcode::CONSTANT,
+            1,
+            opcode::RETURN,
+        ]);
+
+        let err = verify(&chunk).unwrap_err();
+
+        assert_eq!(err, VerifyError::Unbalanced { offset: 4, depth: 2 });
+        assert_eq!(err.offset(), 4);
+    }
+
+    #[test]
+    fn mismatched_branches() {
+        // only one branch pushes a value before they join
+        let chunk = chunk(vec![
+            opcode::TRUE,
+            opcode::JUMPNOT,
+            0,
+            2,
+            opcode::CONSTANT,
+            0,
+            opcode::POP,
+            opcode::RETURN,
+        ]);
+
+        assert_eq!(
+            verify(&chunk),
+            Err(VerifyError::Mismatch {
+                offset: 6,
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn builtin_native() {
+        let chunk = chunk(vec![
+            opcode::NIL,
+            opcode::CALLNATIVE,
+            CLONE.0 as u8,
+            opcode::RETURN,
+        ]);
+
+        assert_eq!(verify(&chunk), Ok(()));
+    }
+
+    #[test]
+    fn unknown_native() {
+        // a native registered by the host
+        let chunk = chunk(vec![opcode::NIL, opcode::CALLNATIVE, 200, opcode::RETURN]);
+
+        assert_eq!(
+            verify(&chunk),
+            Err(VerifyError::UnknownNative {
+                offset: 1,
+                native: 200
+            })
+        );
+        assert_eq!(instruction_len(&chunk.code, 1), Ok(2));
+    }
+}
@@ -1,12 +1,19 @@
-use super::{Function, Program};
+use super::{Function, IntKind, Program};
 use crate::native;
 use crate::object::{
     ArrayObject, EnumObject, FunctionObject, InstanceObject, NativeObject, RawObject, StringObject,
+    TupleObject,
 };
-use crate::opcode;
+use crate::opcode::OpCode;
 use crate::value::Value;
 use fnv::FnvHashMap;
-use util::symbol::Symbol;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::ptr;
+use std::rc::Rc;
+use util::symbol::{
+    Symbol, Symbols, CLOCK, CLONE, FILTER, FOPEN, MAP, RANDOM, READ, REDUCE, TO_STRING,
+};
 /// The max size of the stack
 const STACK_MAX: usize = 256;
 
@@ -18,20 +25,78 @@ pub struct StackFrame<'a> {
     params: FnvHashMap<u8, Value>,
 }
 
+/// A function provided by the program embedding the vm.
+/// It is given its arguments in the order they were passed
+pub type HostFunction<'a> = Box<dyn Fn(&[Value]) -> Result<Value, Error> + 'a>;
+
 pub struct VM<'a> {
     stack: [Value; STACK_MAX],
     frames: Vec<StackFrame<'a>>,
     current_frame: StackFrame<'a>,
     native_functions: FnvHashMap<Symbol, Value>,
+    /// The functions registered with `register_native` and their arity
+    host_functions: FnvHashMap<Symbol, (usize, HostFunction<'a>)>,
     program: &'a Program,
     objects: RawObject,
     stack_top: usize,
+    /// Where `print` writes to
+    out: Box<dyn Write + 'a>,
+    /// Where `eprint` writes to
+    err: Box<dyn Write + 'a>,
+    /// When set each instruction is written here along with the stack before it is executed
+    trace: Option<Box<dyn Write + 'a>>,
+    /// Counts of what has been executed, only collected when profiling is enabled
+    profile: Option<Profile>,
+    /// When set the natives that touch the filesystem, the clock or the rng are an error
+    sandboxed: bool,
+    /// The number of instructions left to run when a step limit is set
+    steps: Option<u64>,
+    /// Replaces the wall clock read by `clock`
+    clock: Option<Box<dyn Fn() -> f64 + 'a>>,
+}
+
+/// A summary of what the vm executed
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// The number of times each opcode was executed, indexed by the opcode
+    opcodes: [u64; 256],
+    /// The number of times each function was called
+    pub calls: FnvHashMap<Symbol, u64>,
 }
 
 #[derive(Debug)]
 pub enum Error {
     NoMain,
     UnknownOpcode,
+    IndexOutOfBounds { index: i64, len: usize },
+    ArityMismatch { expected: usize, found: usize },
+    UndefinedMethod,
+    /// A call or `GETFUNCTION` refers to a function or native that doesn't exist
+    UndefinedFunction,
+    /// The bytecode ends in the middle of an instruction or refers to a constant that doesn't exist
+    MalformedBytecode,
+    /// A checked cast to a sized int would truncate the value
+    CastOverflow { value: i64, kind: IntKind },
+    /// An int cast to a char isn't a valid code point
+    InvalidChar { value: i64 },
+    /// An instruction was given an object of the wrong type
+    TypeMismatch,
+    /// The result of an integer operation doesn't fit in an `int`
+    Overflow,
+    /// An integer was divided by zero
+    DivisionByZero,
+    /// An array being destructured doesn't have as many items as there are variables
+    LengthMismatch { expected: usize, len: usize },
+    /// Writing the output of `print` or `eprint` failed
+    Io(io::Error),
+    /// A side effecting native was called in sandbox mode
+    NativeDisabled { name: &'static str },
+    /// The program ran more instructions than the step limit allows
+    StepLimitExceeded,
+    /// A value was pushed onto a full stack
+    StackOverflow,
+    /// A value was popped off an empty stack
+    StackUnderflow,
 }
 
 impl<'a> VM<'a> {
@@ -51,22 +116,22 @@ impl<'a> VM<'a> {
 
         let mut native_functions = FnvHashMap::default();
         native_functions.insert(
-            Symbol(1),
+            RANDOM,
             Value::object(NativeObject::new(2, native::random, objects)),
         );
 
         native_functions.insert(
-            Symbol(2),
+            CLOCK,
             Value::object(NativeObject::new(0, native::clock, objects)),
         );
 
         native_functions.insert(
-            Symbol(3),
+            READ,
             Value::object(NativeObject::new(0, native::read, objects)),
         );
 
         native_functions.insert(
-            Symbol(4),
+            FOPEN,
             Value::object(NativeObject::new(1, native::fopen, objects)),
         );
 
@@ -77,29 +142,110 @@ impl<'a> VM<'a> {
             frames: Vec::new(),
             stack_top: 4,
             native_functions,
+            host_functions: FnvHashMap::default(),
             objects,
+            out: Box::new(io::stdout()),
+            err: Box::new(io::stderr()),
+            trace: None,
+            profile: None,
+            sandboxed: false,
+            steps: None,
+            clock: None,
         })
     }
 
-    pub fn run(&mut self) {
+    /// Makes `function` callable with `CALLNATIVE` as `name`.
+    /// The compiler emits `CALLNATIVE` for the functions given to `Infer::with_natives`
+    pub fn register_native(&mut self, name: Symbol, arity: usize, function: HostFunction<'a>) {
+        self.host_functions.insert(name, (arity, function));
+    }
+
+    /// Makes `clock` return the result of `clock` instead of the seconds since the UNIX_EPOCH
+    pub fn set_clock(&mut self, clock: Box<dyn Fn() -> f64 + 'a>) {
+        self.clock = Some(clock);
+    }
+
+    /// Makes `print` write to `out` instead of stdout
+    pub fn with_output<W: Write + 'a>(mut self, out: W) -> Self {
+        self.out = Box::new(out);
+        self
+    }
+
+    /// Makes `eprint` write to `err` instead of stderr
+    pub fn with_error_output<W: Write + 'a>(mut self, err: W) -> Self {
+        self.err = Box::new(err);
+        self
+    }
+
+    /// Traces the execution of every instruction to `trace`
+    pub fn with_trace<W: Write + 'a>(mut self, trace: W) -> Self {
+        self.trace = Some(Box::new(trace));
+        self
+    }
+
+    /// Counts the instructions executed and the functions called
+    pub fn with_profiling(mut self) -> Self {
+        self.profile = Some(Profile::new());
+        self
+    }
+
+    /// Makes calls to `fopen`, `read`, `clock` and `random` an error so untrusted programs can be
+    /// run
+    pub fn with_sandbox(mut self) -> Self {
+        self.sandboxed = true;
+        self
+    }
+
+    /// Stops the program with `Error::StepLimitExceeded` once it has run `limit` instructions
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.steps = Some(limit);
+        self
+    }
+
+    /// The counts collected so far if profiling is enabled
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Locates the entry point of the program, a function named `main`, and
+    /// runs it to completion writing anything printed to `out`.
+    /// Returns the value returned from `main`
+    pub fn run_program(
+        program: &'a Program,
+        objects: RawObject,
+        symbols: &mut Symbols<()>,
+        out: &'a mut dyn Write,
+    ) -> Result<Value, Error> {
+        VM::new(symbols.symbol("main"), program, objects)?
+            .with_output(out)
+            .run()
+    }
+
+    pub fn run(&mut self) -> Result<Value, Error> {
         #[cfg(feature = "debug")]
         {
-            for (_, func) in self.program.functions.iter() {
-                func.body.disassemble(&format!("{}", func.name))
+            for func in self.program.ordered_functions() {
+                func.body.disassemble(&format!("{}", func.name), &self.program.constants)
             }
 
-            for (_, class) in self.program.classes.iter() {
-                let _: Vec<()> = class
-                    .methods
-                    .iter()
-                    .map(|(_, func)| func.body.disassemble(&format!("{}", func.name)))
-                    .collect();
+            for class in self.program.ordered_classes() {
+                for func in class.ordered_methods() {
+                    func.body.disassemble(&format!("{}", func.name), &self.program.constants)
+                }
             }
         }
 
+        self.execute(None)
+    }
+
+    /// Runs the instructions of the current frame.
+    /// When `return_depth` is set execution stops once a frame returns and
+    /// leaves `return_depth` frames on the call stack, allowing the vm to call back
+    /// into functions
+    fn execute(&mut self, return_depth: Option<usize>) -> Result<Value, Error> {
         loop {
             if self.current_frame.ip >= self.current_frame.function.body.code.len() {
-                return;
+                return Ok(Value::nil());
             }
 
             #[cfg(feature = "stack")]
@@ -113,243 +259,447 @@ impl<'a> VM<'a> {
                 println!("]")
             }
 
-            match self.read_byte() {
-                opcode::HLT => {
+            if let Some(ref mut trace) = self.trace {
+                let (instruction, _) = self
+                    .current_frame
+                    .function
+                    .body
+                    .instruction_text(self.current_frame.ip, &self.program.constants);
+
+                let stack: Vec<String> = self.stack[0..self.stack_top]
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect();
+
+                writeln!(trace, "{:40}[{}]", instruction, stack.join(", ")).map_err(Error::Io)?;
+            }
+
+            if let Some(ref mut steps) = self.steps {
+                if *steps == 0 {
+                    return Err(Error::StepLimitExceeded);
+                }
+
+                *steps -= 1;
+            }
+
+            let byte = self.read_byte()?;
+
+            if let Some(ref mut profile) = self.profile {
+                profile.opcodes[byte as usize] += 1;
+            }
+
+            match OpCode::try_from(byte).map_err(|_| Error::UnknownOpcode)? {
+                OpCode::Hlt => {
                     break;
                 }
 
-                opcode::RETURN => {
-                    let value = self.pop();
+                OpCode::Return => {
+                    let value = self.pop()?;
 
                     match self.frames.pop() {
                         Some(frame) => {
                             self.current_frame = frame;
-                            self.push(value);
+
+                            if return_depth == Some(self.frames.len()) {
+                                return Ok(value);
+                            }
+
+                            self.push(value)?;
                         }
 
                         None => {
-                            continue; // Were are return from a top level function main
+                            return Ok(value); // Were are return from a top level function main
                         }
                     }
                 }
 
-                opcode::CONSTANT => {
-                    let constant = self.read_constant();
-                    self.push(constant);
+                OpCode::Constant => {
+                    let constant = self.read_constant()?;
+                    self.push(constant)?;
                 }
 
-                opcode::PRINT => {
-                    let value = self.pop();
-                    println!("{}", value);
+                OpCode::Print => {
+                    let value = self.pop()?;
+                    let value = self.display(value)?;
+                    writeln!(self.out, "{}", value).map_err(Error::Io)?;
                 }
 
-                opcode::NEGATE => {
-                    let val = Value::int(-self.pop().as_int());
-                    self.push(val)
+                OpCode::EPrint => {
+                    let value = self.pop()?;
+                    let value = self.display(value)?;
+                    writeln!(self.err, "{}", value).map_err(Error::Io)?;
                 }
 
-                opcode::NEGATEF => {
-                    let val = Value::float(-self.pop().as_float());
-                    self.push(val)
+                OpCode::Negate => match self.pop_int()?.checked_neg() {
+                    Some(val) => self.push(Value::int(val))?,
+                    None => return Err(Error::Overflow),
+                },
+
+                OpCode::NegateF => {
+                    let val = Value::float(-self.pop_float()?);
+                    self.push(val)?
                 }
 
-                opcode::NIL => self.push(Value::nil()),
-                opcode::TRUE => self.push(Value::bool(true)),
-                opcode::FALSE => self.push(Value::bool(false)),
+                OpCode::Nil => self.push(Value::nil())?,
+                OpCode::True => self.push(Value::bool(true))?,
+                OpCode::False => self.push(Value::bool(false))?,
 
-                opcode::NOT => {
-                    let val = Value::bool(!self.pop().as_bool());
-                    self.push(val)
+                OpCode::Not => {
+                    let val = Value::bool(!self.pop_bool()?);
+                    self.push(val)?
                 }
-                opcode::EQUAL => {
-                    let b = self.pop();
-                    let a = self.pop();
+                OpCode::Equal => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
 
-                    self.push(Value::bool(a == b));
+                    self.push(Value::bool(a == b))?;
                 }
 
-                opcode::ARRAY => {
-                    let len = self.read_byte();
+                OpCode::Array => {
+                    let len = self.read_byte()?;
 
-                    let items: Vec<Value> = (0..len).map(|_| self.pop()).collect();
+                    let items = (0..len)
+                        .map(|_| self.pop())
+                        .collect::<Result<Vec<Value>, Error>>()?;
 
                     let array = ArrayObject::new(items, self.objects);
 
-                    self.push(Value::object(array));
+                    self.push(Value::object(array))?;
+                }
+
+                OpCode::Tuple => {
+                    let len = self.read_byte()?;
+
+                    let items = (0..len)
+                        .map(|_| self.pop())
+                        .collect::<Result<Vec<Value>, Error>>()?;
+
+                    let tuple = TupleObject::new(items, self.objects);
+
+                    self.push(Value::object(tuple))?;
+                }
+
+                OpCode::IndexTuple => {
+                    let index = self.read_byte()? as usize;
+
+                    let tuple = self.pop_tuple()?;
+
+                    match tuple.items.get(index) {
+                        Some(item) => self.push(*item)?,
+                        None => {
+                            return Err(Error::IndexOutOfBounds {
+                                index: index as i64,
+                                len: tuple.items.len(),
+                            })
+                        }
+                    }
+                }
+
+                OpCode::CheckLen => {
+                    let expected = self.read_byte()? as usize;
+
+                    let array = self.peek(0)?;
+                    let len = array.as_array_object().ok_or(Error::TypeMismatch)?.items.len();
+
+                    if len != expected {
+                        return Err(Error::LengthMismatch { expected, len });
+                    }
+                }
+
+                OpCode::Less => binary_op!(<,pop_int,bool,self),
+                OpCode::LessF => binary_op!(<,pop_float,bool,self),
+                OpCode::Greater => binary_op!(>,pop_int,bool,self),
+                OpCode::GreaterF => binary_op!(>,pop_float,bool,self),
+                OpCode::LessEqualF => binary_op!(<=,pop_float,bool,self),
+                OpCode::GreaterEqualF => binary_op!(>=,pop_float,bool,self),
+                OpCode::LessStr => {
+                    let b = self.pop()?;
+                    let b = b.as_string_object().ok_or(Error::TypeMismatch)?;
+                    let a = self.pop()?;
+                    let a = a.as_string_object().ok_or(Error::TypeMismatch)?;
+
+                    self.push(Value::bool(a.value() < b.value()))?;
+                }
+                OpCode::GreaterStr => {
+                    let b = self.pop()?;
+                    let b = b.as_string_object().ok_or(Error::TypeMismatch)?;
+                    let a = self.pop()?;
+                    let a = a.as_string_object().ok_or(Error::TypeMismatch)?;
+
+                    self.push(Value::bool(a.value() > b.value()))?;
                 }
+                OpCode::Add => checked_int_op!(checked_add, self),
+                OpCode::AddF => binary_op!(+,pop_float,float,self),
+                OpCode::Sub => checked_int_op!(checked_sub, self),
+                OpCode::SubF => binary_op!(-,pop_float,float,self),
+                OpCode::Mul => checked_int_op!(checked_mul, self),
+                OpCode::MulF => binary_op!(*,pop_float,float,self),
+                OpCode::Div => {
+                    if self.peek(0)? == Value::int(0) {
+                        return Err(Error::DivisionByZero);
+                    }
 
-                opcode::LESS => binary_op!(<,as_int,bool,self),
-                opcode::LESSF => binary_op!(<,as_float,bool,self),
-                opcode::GREATER => binary_op!(>,as_int,bool,self),
-                opcode::GREATERF => binary_op!(>,as_float,bool,self),
-                opcode::ADD => binary_op!(+,as_int,int,self),
-                opcode::ADDF => binary_op!(+,as_float,float,self),
-                opcode::SUB => binary_op!(-,as_int,int,self),
-                opcode::SUBF => binary_op!(-,as_float,float,self),
-                opcode::MUL => binary_op!(*,as_int,int,self),
-                opcode::MULF => binary_op!(*,as_float,float,self),
-                opcode::DIV => binary_op!(/,as_int,int,self),
-                opcode::DIVF => binary_op!(/,as_float,float,self),
-                opcode::INT2FLOAT => {
-                    let value = self.pop().as_int();
-                    self.push(Value::float(value as f64))
+                    checked_int_op!(checked_div, self)
+                }
+                OpCode::DivF => binary_op!(/,pop_float,float,self),
+                OpCode::Int2Float => {
+                    let value = self.pop_int()?;
+                    self.push(Value::float(value as f64))?
                 }
-                opcode::FLOAT2INT => {
-                    let value = self.pop().as_float();
-                    self.push(Value::int(value as i64))
+                OpCode::Float2Int => {
+                    let value = self.pop_float()?;
+                    self.push(Value::int(value as i64))?
                 }
-                opcode::BOOL2INT => {
-                    let value = self.pop().as_bool();
-                    self.push(Value::int(value as i64))
+                OpCode::Bool2Int => {
+                    let value = self.pop_bool()?;
+                    self.push(Value::int(value as i64))?
                 }
 
-                opcode::FLOAT2STR => {
-                    let value = self.pop().as_float();
+                OpCode::Float2Str => {
+                    let value = self.pop_float()?;
                     let value = format!("{}", value);
-                    self.push(Value::object(StringObject::from_owned(value, self.objects)));
+                    self.push(Value::object(StringObject::from_owned(value, self.objects)))?;
                 }
 
-                opcode::INT2STR => {
-                    let value = self.pop().as_int();
+                OpCode::Int2Str => {
+                    let value = self.pop_int()?;
                     let value = format!("{}", value);
-                    self.push(Value::object(StringObject::from_owned(value, self.objects)));
+                    self.push(Value::object(StringObject::from_owned(value, self.objects)))?;
+                }
+
+                OpCode::Char2Int => {
+                    let value = self.pop_char()?;
+                    self.push(Value::int(i64::from(u32::from(value))))?;
+                }
+
+                OpCode::Int2Char => {
+                    let value = self.pop_int()?;
+
+                    let character = if value >= 0 && value <= i64::from(u32::max_value()) {
+                        std::char::from_u32(value as u32)
+                    } else {
+                        None
+                    };
+
+                    match character {
+                        Some(character) => self.push(Value::char(character))?,
+                        None => return Err(Error::InvalidChar { value }),
+                    }
+                }
+
+                OpCode::Char2Str => {
+                    let value = self.pop_char()?.to_string();
+                    self.push(Value::object(StringObject::from_owned(value, self.objects)))?;
                 }
 
-                opcode::LOOP => {
-                    let address = self.read_16_bits();
+                OpCode::WrapInt => {
+                    let kind = self.read_int_kind()?;
+                    let value = self.pop_int()?;
 
-                    self.current_frame.ip -= address as usize
+                    self.push(Value::int(kind.wrap(value)))?;
                 }
-                opcode::JUMP => {
-                    let address = self.read_16_bits();
-                    self.current_frame.ip += address as usize;
+
+                OpCode::CheckInt => {
+                    let kind = self.read_int_kind()?;
+                    let value = self.pop_int()?;
+
+                    if !kind.fits(value) {
+                        return Err(Error::CastOverflow { value, kind });
+                    }
+
+                    self.push(Value::int(value))?;
+                }
+
+                OpCode::Loop => {
+                    let address = self.read_16_bits()?;
+
+                    self.current_frame.ip = self
+                        .current_frame
+                        .ip
+                        .checked_sub(address as usize)
+                        .ok_or(Error::MalformedBytecode)?;
+                }
+                OpCode::Jump => {
+                    let address = self.read_16_bits()?;
+                    self.jump(address)?;
                 }
 
-                opcode::JUMPIF => {
-                    let address = self.read_16_bits();
+                OpCode::JumpIf => {
+                    let address = self.read_16_bits()?;
 
-                    if self.stack[self.stack_top - 1].as_bool() {
-                        self.current_frame.ip += address as usize;
+                    if self.peek_bool()? {
+                        self.jump(address)?;
                     }
                 }
-                opcode::JUMPNOT => {
-                    let address = self.read_16_bits();
+                OpCode::JumpNot => {
+                    let address = self.read_16_bits()?;
 
-                    if !self.stack[self.stack_top - 1].as_bool() {
-                        self.current_frame.ip += address as usize;
+                    if !self.peek_bool()? {
+                        self.jump(address)?;
                     }
                 }
-                opcode::GETLOCAL => {
-                    let local = self.read_byte();
+                OpCode::GetLocal => {
+                    let local = self.read_byte()?;
 
-                    let val = self.current_frame.locals[&local];
+                    let val = *self
+                        .current_frame
+                        .locals
+                        .get(&local)
+                        .ok_or(Error::MalformedBytecode)?;
 
-                    self.push(val);
+                    self.push(val)?;
                 }
 
-                opcode::SETLOCAL => {
-                    let ident = self.read_byte();
+                OpCode::SetLocal => {
+                    let ident = self.read_byte()?;
 
-                    let val = self.stack[self.stack_top - 1]; // do it manually because we don't  want to modify the stack
+                    let val = self.peek(0)?; // do it manually because we don't  want to modify the stack
 
                     self.current_frame.locals.insert(ident, val);
                 }
 
-                opcode::GETPARAM => {
-                    let param = self.read_byte();
+                OpCode::SetParam => {
+                    let param = self.read_byte()?;
 
-                    let val = self.current_frame.params[&param];
+                    let val = self.peek(0)?;
 
-                    self.push(val);
+                    self.current_frame.params.insert(param, val);
                 }
 
-                opcode::GETPROPERTY => {
-                    let instance = self.pop();
-                    let instance = instance.as_instance();
+                OpCode::GetParam => {
+                    let param = self.read_byte()?;
 
-                    let property = Symbol(u64::from(self.read_byte()));
-                    let value = instance.properties[&property];
+                    let val = *self
+                        .current_frame
+                        .params
+                        .get(&param)
+                        .ok_or(Error::MalformedBytecode)?;
 
-                    self.push(value);
+                    self.push(val)?;
                 }
 
-                opcode::GETMETHOD => {
-                    let instance = self.pop();
-                    let instance = instance.as_instance();
+                OpCode::GetProperty => {
+                    let instance = self.pop_instance()?;
 
-                    let method_name = Symbol(u64::from(self.read_byte()));
+                    let property = Symbol(u64::from(self.read_byte()?));
+                    let value = *instance
+                        .properties
+                        .get(&property)
+                        .ok_or(Error::MalformedBytecode)?;
+
+                    self.push(value)?;
+                }
+
+                OpCode::GetMethod => {
+                    let instance = self.pop_instance()?;
+
+                    let method_name = Symbol(u64::from(self.read_byte()?));
+
+                    let method = match self.find_method(instance.name, method_name) {
+                        Some(method) => method,
+                        None => return Err(Error::UndefinedMethod),
+                    };
 
-                    let method = &instance.methods[&method_name];
                     let value = Value::object(FunctionObject::new(
                         method.params.len(),
-                        method.clone(),
+                        Rc::clone(method),
                         self.objects,
                     ));
 
-                    self.push(value)
+                    self.push(value)?
                 }
 
-                opcode::ENUM => {
-                    let enum_name = Symbol(u64::from(self.read_byte()));
-                    let tag = u32::from(self.read_byte());
+                OpCode::Enum => {
+                    let enum_name = Symbol(u64::from(self.read_byte()?));
+                    let tag = u32::from(self.read_byte()?);
                     let object = EnumObject::new(enum_name, tag, None, self.objects);
-                    self.push(Value::object(object))
+                    self.push(Value::object(object))?
                 }
 
-                opcode::ENUMDATA => {
-                    let enum_name = Symbol(u64::from(self.read_byte()));
-                    let tag = u32::from(self.read_byte());
-                    let data = self.pop();
+                OpCode::EnumData => {
+                    let enum_name = Symbol(u64::from(self.read_byte()?));
+                    let tag = u32::from(self.read_byte()?);
+                    let data = self.pop()?;
                     let object = EnumObject::new(enum_name, tag, Some(data), self.objects);
-                    self.push(Value::object(object))
+                    self.push(Value::object(object))?
+                }
+
+                OpCode::EnumTag => {
+                    let tag = self.pop_enum()?.tag;
+                    self.push(Value::int(i64::from(tag)))?
                 }
 
-                opcode::SETPROPERTY => {
-                    let instance = self.pop();
-                    let instance = instance.as_mut_instance();
+                OpCode::EnumInner => {
+                    let data = self.pop_enum()?.data.unwrap_or_else(Value::nil);
+                    self.push(data)?
+                }
+
+                OpCode::SetProperty => {
+                    let instance = self.pop_mut_instance()?;
 
-                    let value = self.pop();
+                    let value = self.pop()?;
 
-                    let property = Symbol(u64::from(self.read_byte()));
+                    let property = Symbol(u64::from(self.read_byte()?));
 
                     instance.properties.insert(property, value);
                 }
 
-                opcode::CALLCLOSURE => {
-                    let arg_count = self.read_byte();
+                OpCode::GetFunction => {
+                    let function_name = Symbol(u64::from(self.read_byte()?));
+
+                    let value = match self.program.functions.get(&function_name) {
+                        Some(function) => Value::object(FunctionObject::new(
+                            function.params.len(),
+                            Rc::clone(function),
+                            self.objects,
+                        )),
+                        None => {
+                            self.check_sandbox(function_name)?;
+                            self.native(function_name)?
+                        }
+                    };
+
+                    self.push(value)?
+                }
+
+                OpCode::CallValue => {
+                    let arg_count = self.read_byte()?;
 
-                    let mut params = FnvHashMap::default();
+                    let callee = self.pop()?;
 
-                    for i in 0..arg_count {
-                        params.insert(i, self.pop());
+                    if let Some(native) = callee.as_native_object() {
+                        self.call_native(native)?;
+                        continue;
                     }
 
-                    let closure = self.pop();
+                    let function = &callee
+                        .as_function_object()
+                        .ok_or(Error::TypeMismatch)?
+                        .function;
 
-                    let closure = &closure.as_function().function;
+                    let params = self.pop_params(arg_count)?;
 
                     let call_frame = StackFrame {
                         ip: 0,
                         locals: FnvHashMap::default(),
-                        function: closure,
+                        function,
                         params,
                     };
 
-                    self.frames
-                        .push(::std::mem::replace(&mut self.current_frame, call_frame));
+                    self.push_frame(call_frame);
                 }
 
-                opcode::CALL => {
-                    let function_name = Symbol(u64::from(self.read_byte()));
-                    let arg_count = self.read_byte();
+                OpCode::Call => {
+                    let function_name = Symbol(u64::from(self.read_byte()?));
+                    let arg_count = self.read_byte()?;
 
-                    let function = &self.program.functions[&function_name];
+                    let function = self
+                        .program
+                        .functions
+                        .get(&function_name)
+                        .ok_or(Error::UndefinedFunction)?;
 
-                    let mut params = FnvHashMap::default();
-
-                    for i in 0..arg_count {
-                        params.insert(i, self.pop());
-                    }
+                    let params = self.pop_params(arg_count)?;
 
                     let call_frame = StackFrame {
                         ip: 0,
@@ -358,202 +708,1422 @@ impl<'a> VM<'a> {
                         params,
                     };
 
-                    self.frames
-                        .push(::std::mem::replace(&mut self.current_frame, call_frame));
+                    self.push_frame(call_frame);
                     // swaps the current frame with the one we are one and then
                 }
 
-                opcode::CALLNATIVE => {
-                    let function_name = Symbol(u64::from(self.read_byte()));
-                    let function = &self.native_functions[&function_name];
-                    let function = function.as_native();
+                OpCode::CallNative => {
+                    let function_name = Symbol(u64::from(self.read_byte()?));
 
-                    let arg_count = function.arity;
-                    let result = (function.function)(
-                        self.stack[self.stack_top - arg_count as usize..self.stack_top].as_ptr(),
-                    );
+                    match function_name {
+                        MAP => self.map()?,
+                        FILTER => self.filter()?,
+                        REDUCE => self.reduce()?,
+                        CLONE => self.clone_value()?,
+                        _ if self.host_functions.contains_key(&function_name) => {
+                            self.call_host(function_name)?
+                        }
+                        _ => {
+                            self.check_sandbox(function_name)?;
+
+                            let function = self.native(function_name)?.as_native();
 
-                    self.stack_top -= arg_count as usize;
-                    {
-                        self.push(result);
+                            self.call_native(function)?;
+                        }
                     }
                 }
 
-                opcode::CALLINSTANCEMETHOD => {
-                    let method_name = Symbol(u64::from(self.read_byte()));
-                    let arg_count = self.read_byte();
-
-                    let instance = self.pop();
-                    let instance = instance.as_instance();
+                OpCode::CallInstanceMethod => {
+                    let method_name = Symbol(u64::from(self.read_byte()?));
+                    let arg_count = self.read_byte()?;
 
-                    let function = &instance.methods[&method_name];
+                    let receiver = self.pop()?;
+                    let instance = receiver.as_instance_object().ok_or(Error::TypeMismatch)?;
 
-                    let mut params = FnvHashMap::default();
+                    let function = match self.find_method(instance.name, method_name) {
+                        Some(function) => function,
+                        None => return Err(Error::UndefinedMethod),
+                    };
 
-                    for i in 0..arg_count {
-                        params.insert(i, self.pop());
-                    }
+                    let params = self.pop_params(arg_count)?;
 
                     let call_frame = StackFrame {
                         ip: 0,
-                        locals: FnvHashMap::default(),
+                        locals: method_locals(receiver),
                         function,
                         params,
                     };
 
-                    self.frames
-                        .push(::std::mem::replace(&mut self.current_frame, call_frame));
+                    self.push_frame(call_frame);
                 }
 
-                opcode::CALLSTATICMETHOD => {
-                    let class_name = Symbol(u64::from(self.read_byte()));
-                    let method_name = Symbol(u64::from(self.read_byte()));
-                    let arg_count = self.read_byte();
-                    let function = &self.program.classes[&class_name].methods[&method_name];
+                OpCode::CallStaticMethod => {
+                    let class_name = Symbol(u64::from(self.read_byte()?));
+                    let method_name = Symbol(u64::from(self.read_byte()?));
+                    let arg_count = self.read_byte()?;
 
-                    let mut params = FnvHashMap::default();
+                    let function = match self.find_method(class_name, method_name) {
+                        Some(function) => function,
+                        None => return Err(Error::UndefinedMethod),
+                    };
 
-                    for i in 0..arg_count {
-                        params.insert(i, self.pop());
-                    }
+                    let params = self.pop_params(arg_count)?;
 
                     let call_frame = StackFrame {
                         ip: 0,
-                        locals: FnvHashMap::default(),
+                        locals: method_locals(Value::nil()), // there is no instance
                         function,
                         params,
                     };
 
-                    self.frames
-                        .push(::std::mem::replace(&mut self.current_frame, call_frame));
+                    self.push_frame(call_frame);
+                }
+
+                OpCode::Pop => {
+                    self.pop()?;
                 }
 
-                opcode::POP => {
-                    self.pop();
+                OpCode::Dup => {
+                    let value = self.peek(0)?;
+                    self.push(value)?;
                 }
 
-                opcode::INDEXARRAY => {
-                    let index = self.pop().as_int() as usize;
+                OpCode::Swap => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
 
-                    let array = self.pop();
-                    let array = array.as_array();
+                    self.push(b)?;
+                    self.push(a)?;
+                }
+
+                OpCode::Dup2 => {
+                    let a = self.peek(1)?;
+                    let b = self.peek(0)?;
+
+                    self.push(a)?;
+                    self.push(b)?;
+                }
+
+                OpCode::IndexArray => {
+                    let index = self.pop_int()?;
+
+                    let array = self.pop_array()?;
+
+                    if index < 0 || index as usize >= array.items.len() {
+                        return Err(Error::IndexOutOfBounds {
+                            index,
+                            len: array.items.len(),
+                        });
+                    }
 
-                    self.push(array.items[index]);
+                    self.push(array.items[index as usize])?;
                 }
 
-                opcode::INDEXSTRING => {
-                    let index = self.pop().as_int() as usize;
+                OpCode::SetIndexArray => {
+                    let value = self.pop()?;
+                    let index = self.pop_int()?;
 
-                    let string = self.pop();
-                    let string = string.as_string();
+                    let array = self.pop_mut_array()?;
 
-                    let slice = &string.chars.string()[index..=index];
-                    let result = StringObject::new(slice, self.objects);
+                    if index < 0 || index as usize >= array.items.len() {
+                        return Err(Error::IndexOutOfBounds {
+                            index,
+                            len: array.items.len(),
+                        });
+                    }
 
-                    self.push(Value::object(result))
+                    array.items[index as usize] = value;
                 }
 
-                opcode::CLASSINSTANCE => {
-                    let class_name = Symbol(u64::from(self.read_byte()));
+                OpCode::IndexString => {
+                    let index = self.pop_int()?;
+
+                    let string = self.pop()?;
+                    let string = string
+                        .as_string_object()
+                        .ok_or(Error::TypeMismatch)?
+                        .chars
+                        .string();
+
+                    // indexed by char rather than byte so a multi-byte char isn't split
+                    let character = if index < 0 {
+                        None
+                    } else {
+                        string.chars().nth(index as usize)
+                    };
 
-                    let num_properties = self.read_byte() as usize;
+                    match character {
+                        Some(character) => self.push(Value::char(character))?,
+                        None => {
+                            return Err(Error::IndexOutOfBounds {
+                                index,
+                                len: string.chars().count(),
+                            })
+                        }
+                    }
+                }
 
-                    let class = &self.program.classes[&class_name];
+                OpCode::ClassInstance => {
+                    let class_name = Symbol(u64::from(self.read_byte()?));
 
-                    let methods = class.methods.clone();
+                    let num_properties = self.read_byte()? as usize;
 
                     let mut properties = FnvHashMap::default();
 
                     for _ in 0..num_properties {
-                        properties.insert(Symbol(u64::from(self.read_byte())), self.pop());
+                        properties.insert(Symbol(u64::from(self.read_byte()?)), self.pop()?);
                     }
 
-                    let instance = InstanceObject::new(methods, properties, self.objects);
+                    let instance = InstanceObject::new(class_name, properties, self.objects);
 
-                    self.push(Value::object(instance));
+                    self.push(Value::object(instance))?;
                 }
 
-                opcode::CONCAT => self.concat(),
+                OpCode::Concat => self.concat()?,
 
-                #[cfg(not(feature = "debug"))]
-                _ => unsafe {
-                    use std::hint::unreachable_unchecked;
-                    unreachable_unchecked()
-                },
-                #[cfg(feature = "debug")]
-                ref e => {
-                    {
-                        println!("unknown opcode found :{}", e);
-                    }
-                    continue;
-                }
+                OpCode::Igl => return Err(Error::UnknownOpcode),
             }
         }
+
+        Ok(Value::nil())
     }
 
-    fn concat(&mut self) {
-        let b = self.pop();
-        let b = b.as_string();
-        let a = self.pop();
-        let a = a.as_string();
+    /// Pops `arg_count` arguments off the stack.
+    /// The first argument is stored in the first param slot
+    fn pop_params(&mut self, arg_count: u8) -> Result<FnvHashMap<u8, Value>, Error> {
+        let mut params = FnvHashMap::default();
 
-        let length = a.chars.string().len() + b.chars.string().len();
+        for i in (0..arg_count).rev() {
+            params.insert(i, self.pop()?);
+        }
 
-        let mut new = String::with_capacity(length);
+        Ok(params)
+    }
 
-        new.push_str(a.chars.string());
-        new.push_str(b.chars.string());
+    /// The native called `name`
+    fn native(&self, name: Symbol) -> Result<Value, Error> {
+        self.native_functions
+            .get(&name)
+            .cloned()
+            .ok_or(Error::UndefinedFunction)
+    }
 
-        #[cfg(feature = "debug")]
-        {
-            println!("{:?}", a.chars);
-            println!("{:?}", b.chars);
+    /// Returns an error if `name` is a side effecting native and the vm is sandboxed
+    fn check_sandbox(&self, name: Symbol) -> Result<(), Error> {
+        if !self.sandboxed {
+            return Ok(());
         }
 
-        let result = StringObject::from_owned(new, self.objects);
+        let name = match name {
+            RANDOM => "random",
+            CLOCK => "clock",
+            READ => "read",
+            FOPEN => "fopen",
+            _ => return Ok(()),
+        };
 
-        self.push(Value::object(result));
+        Err(Error::NativeDisabled { name })
     }
 
-    fn read_constant(&mut self) -> Value {
-        let index = self.read_byte() as usize;
-        self.current_frame.function.body.constants[index]
+    /// Calls a function registered by the host with the arguments that are on the stack
+    fn call_host(&mut self, name: Symbol) -> Result<(), Error> {
+        let (arity, ref function) = self.host_functions[&name];
+
+        let result = function(self.top(arity)?)?;
+
+        self.stack_top -= arity;
+
+        self.push(result)
     }
 
-    fn read_16_bits(&mut self) -> u16 {
-        let result = (u16::from(self.current_frame.function.body.code[self.current_frame.ip]) << 8)
-            | u16::from(self.current_frame.function.body.code[self.current_frame.ip + 1]);
-        // Shifts the instruction by 8 to the right and or all the 1's and 0's
-        self.current_frame.ip += 2;
+    /// Calls a native function with arguments that are on the stack
+    fn call_native(&mut self, native: &NativeObject) -> Result<(), Error> {
+        let arg_count = native.arity as usize;
+        let args = self.top(arg_count)?;
+
+        let result = match self.clock {
+            Some(ref clock) if ptr::eq(native, self.native_functions[&CLOCK].as_native()) => {
+                Value::float(clock())
+            }
+            _ => (native.function)(args.as_ptr()),
+        };
 
-        result
+        self.stack_top -= arg_count;
+
+        self.push(result)
     }
 
-    fn read_byte(&mut self) -> u8 {
-        let byte = self.current_frame.function.body.code[self.current_frame.ip];
-        self.current_frame.ip += 1;
-        byte
+    /// Calls `callee` with `args` and runs it until it returns
+    fn call_value(&mut self, callee: Value, args: &[Value]) -> Result<Value, Error> {
+        if let Some(native) = callee.as_native_object() {
+            check_arity(native.arity as usize, args.len())?;
+
+            for arg in args {
+                self.push(*arg)?;
+            }
+
+            self.call_native(native)?;
+
+            return self.pop();
+        }
+
+        let function = callee.as_function_object().ok_or(Error::TypeMismatch)?;
+
+        check_arity(function.arity, args.len())?;
+
+        let mut params = FnvHashMap::default();
+
+        for (i, arg) in args.iter().enumerate() {
+            params.insert(i as u8, *arg);
+        }
+
+        self.call_function(&function.function, FnvHashMap::default(), params)
     }
 
-    fn push(&mut self, val: Value) {
-        self.stack[self.stack_top] = val;
-        self.stack_top += 1;
+    /// Calls `function` with `params` and runs it until it returns
+    fn call_function(
+        &mut self,
+        function: &'a Function,
+        locals: FnvHashMap<u8, Value>,
+        params: FnvHashMap<u8, Value>,
+    ) -> Result<Value, Error> {
+        let call_frame = StackFrame {
+            ip: 0,
+            locals,
+            function,
+            params,
+        };
+
+        let return_depth = self.frames.len();
+
+        self.push_frame(call_frame);
+
+        self.execute(Some(return_depth))
     }
 
-    fn pop(&mut self) -> Value {
-        self.stack_top -= 1;
-        self.stack[self.stack_top]
+    /// Finds the method `name` on `class` or on one of its superclasses
+    fn find_method(&self, class: Symbol, name: Symbol) -> Option<&'a Rc<Function>> {
+        let program = self.program;
+        let mut class = program.classes.get(&class);
+
+        while let Some(current) = class {
+            if let Some(method) = current.methods.get(&name) {
+                return Some(method);
+            }
+
+            class = current
+                .superclass
+                .and_then(|superclass| program.classes.get(&superclass));
+        }
+
+        None
     }
-}
 
-use std::fmt::{self, Debug};
+    /// Renders a value for printing.
+    /// A class instance is rendered with the class's `to_string` method if it defines one
+    /// otherwise its properties are listed
+    fn display(&mut self, value: Value) -> Result<String, Error> {
+        self.display_visiting(value, &mut Vec::new())
+    }
 
-impl<'a> Debug for VM<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut debug_trait_builder = f.debug_struct("VM");
+    /// `visited` holds the instances being shown further up so an instance that refers to
+    /// itself is shown as `Name {...}` instead of recursing forever
+    fn display_visiting(
+        &mut self,
+        value: Value,
+        visited: &mut Vec<RawObject>,
+    ) -> Result<String, Error> {
+        if !value.is_instance() {
+            return Ok(value.to_string());
+        }
 
-        let _ = debug_trait_builder.field("stack", &self.stack[0..].iter());
-        let _ = debug_trait_builder.field("ip", &self.current_frame.ip);
-        debug_trait_builder.finish()
+        let instance = value.as_instance();
+
+        if let Some(method) = self.find_method(instance.name, TO_STRING) {
+            check_arity(method.params.len(), 0)?;
+
+            let locals = method_locals(value);
+
+            return Ok(self.call_function(method, locals, FnvHashMap::default())?.to_string());
+        }
+
+        let program = self.program;
+        let class = &program.classes[&instance.name];
+
+        if class.properties.is_empty() {
+            return Ok(format!("{} {{}}", program.names[&instance.name]));
+        }
+
+        let object = value.as_object();
+
+        if visited.contains(&object) {
+            return Ok(format!("{} {{...}}", program.names[&instance.name]));
+        }
+
+        visited.push(object);
+
+        let mut properties = Vec::with_capacity(class.properties.len());
+
+        for property in class.properties.iter() {
+            let value = self.display_visiting(instance.properties[property], visited)?;
+
+            properties.push(format!("{}: {}", program.names[property], value));
+        }
+
+        visited.pop();
+
+        Ok(format!(
+            "{} {{ {} }}",
+            program.names[&instance.name],
+            properties.join(", ")
+        ))
+    }
+
+    /// map(arr, fn)
+    /// Creates a new array from the results of calling fn on each item
+    fn map(&mut self) -> Result<(), Error> {
+        let callee = self.pop()?;
+        let array = self.pop_array()?;
+
+        let mut items = Vec::with_capacity(array.items.len());
+
+        for item in array.items.iter() {
+            items.push(self.call_value(callee, &[*item])?);
+        }
+
+        let result = ArrayObject::new(items, self.objects);
+
+        self.push(Value::object(result))
+    }
+
+    /// filter(arr, pred)
+    /// Creates a new array with the items that pred returns true for
+    fn filter(&mut self) -> Result<(), Error> {
+        let callee = self.pop()?;
+        let array = self.pop_array()?;
+
+        let mut items = Vec::new();
+
+        for item in array.items.iter() {
+            let keep = self.call_value(callee, &[*item])?;
+
+            if !keep.is_bool() {
+                return Err(Error::TypeMismatch);
+            }
+
+            if keep.as_bool() {
+                items.push(*item);
+            }
+        }
+
+        let result = ArrayObject::new(items, self.objects);
+
+        self.push(Value::object(result))
+    }
+
+    /// reduce(arr, init, fn)
+    /// Folds the items into a single value by calling fn with the accumulator and each item.
+    /// An empty array returns init
+    fn reduce(&mut self) -> Result<(), Error> {
+        let callee = self.pop()?;
+        let mut accumulator = self.pop()?;
+        let array = self.pop_array()?;
+
+        for item in array.items.iter() {
+            accumulator = self.call_value(callee, &[accumulator, *item])?;
+        }
+
+        self.push(accumulator)
+    }
+
+    /// clone(value)
+    /// Deep copies arrays, tuples, instances and enums into new objects.
+    /// Every other value is returned as is
+    fn clone_value(&mut self) -> Result<(), Error> {
+        let value = self.pop()?;
+        let copy = self.deep_copy(value, &mut FnvHashMap::default());
+
+        self.push(copy)
+    }
+
+    /// `copies` maps each object that has already been copied to its copy,
+    /// so an object reached twice is only copied once and cycles are kept
+    fn deep_copy(&self, value: Value, copies: &mut FnvHashMap<RawObject, Value>) -> Value {
+        if !value.is_array() && !value.is_tuple() && !value.is_instance() && !value.is_enum() {
+            return value;
+        }
+
+        if let Some(copy) = copies.get(&value.as_object()) {
+            return *copy;
+        }
+
+        if value.is_array() {
+            let copy = Value::object(ArrayObject::new(Vec::new(), self.objects));
+
+            copies.insert(value.as_object(), copy);
+
+            let items = value
+                .as_array()
+                .items
+                .iter()
+                .map(|item| self.deep_copy(*item, copies))
+                .collect();
+
+            copy.as_mut_array().items = items;
+
+            copy
+        } else if value.is_tuple() {
+            let copy = Value::object(TupleObject::new(Vec::new(), self.objects));
+
+            copies.insert(value.as_object(), copy);
+
+            let items = value
+                .as_tuple()
+                .items
+                .iter()
+                .map(|item| self.deep_copy(*item, copies))
+                .collect();
+
+            copy.as_mut_tuple().items = items;
+
+            copy
+        } else if value.is_enum() {
+            let variant = value.as_enum();
+            let copy = Value::object(EnumObject::new(
+                variant.name,
+                variant.tag,
+                None,
+                self.objects,
+            ));
+
+            copies.insert(value.as_object(), copy);
+
+            copy.as_mut_enum().data = variant.data.map(|data| self.deep_copy(data, copies));
+
+            copy
+        } else {
+            let instance = value.as_instance();
+            let copy = Value::object(InstanceObject::new(
+                instance.name,
+                FnvHashMap::default(),
+                self.objects,
+            ));
+
+            copies.insert(value.as_object(), copy);
+
+            let properties = instance
+                .properties
+                .iter()
+                .map(|(name, property)| (*name, self.deep_copy(*property, copies)))
+                .collect();
+
+            copy.as_mut_instance().properties = properties;
+
+            copy
+        }
+    }
+
+    fn concat(&mut self) -> Result<(), Error> {
+        let b = self.pop()?;
+        let b = b.as_string_object().ok_or(Error::TypeMismatch)?;
+        let a = self.pop()?;
+        let a = a.as_string_object().ok_or(Error::TypeMismatch)?;
+
+        let length = a.chars.string().len() + b.chars.string().len();
+
+        let mut new = String::with_capacity(length);
+
+        new.push_str(a.chars.string());
+        new.push_str(b.chars.string());
+
+        #[cfg(feature = "debug")]
+        {
+            println!("{:?}", a.chars);
+            println!("{:?}", b.chars);
+        }
+
+        let result = StringObject::from_owned(new, self.objects);
+
+        self.push(Value::object(result))
+    }
+
+    /// Makes `frame` the current frame saving the caller's frame
+    fn push_frame(&mut self, frame: StackFrame<'a>) {
+        if let Some(ref mut profile) = self.profile {
+            *profile.calls.entry(frame.function.name).or_insert(0) += 1;
+        }
+
+        self.frames.push(::std::mem::replace(&mut self.current_frame, frame));
+    }
+
+    fn read_constant(&mut self) -> Result<Value, Error> {
+        let index = self.read_byte()? as usize;
+
+        self.program
+            .constants
+            .get(index)
+            .cloned()
+            .ok_or(Error::MalformedBytecode)
+    }
+
+    fn read_int_kind(&mut self) -> Result<IntKind, Error> {
+        IntKind::from_byte(self.read_byte()?).ok_or(Error::MalformedBytecode)
+    }
+
+    fn read_16_bits(&mut self) -> Result<u16, Error> {
+        // Shifts the instruction by 8 to the right and or all the 1's and 0's
+        Ok((u16::from(self.read_byte()?) << 8) | u16::from(self.read_byte()?))
+    }
+
+    /// Moves the ip forward by `offset`.
+    /// Jumping past the end of the code is an error as the bytecode is malformed
+    fn jump(&mut self, offset: u16) -> Result<(), Error> {
+        let ip = self.current_frame.ip + offset as usize;
+
+        if ip > self.current_frame.function.body.code.len() {
+            return Err(Error::MalformedBytecode);
+        }
+
+        self.current_frame.ip = ip;
+
+        Ok(())
+    }
+
+    /// Reads the next byte of the current function.
+    /// Reading past the end of the code is an error as the bytecode is malformed
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        let byte = *self
+            .current_frame
+            .function
+            .body
+            .code
+            .get(self.current_frame.ip)
+            .ok_or(Error::MalformedBytecode)?;
+
+        self.current_frame.ip += 1;
+
+        Ok(byte)
+    }
+
+    /// Pushing onto a full stack is an error rather than a panic so malformed bytecode can't
+    /// crash the vm
+    fn push(&mut self, val: Value) -> Result<(), Error> {
+        if self.stack_top == STACK_MAX {
+            return Err(Error::StackOverflow);
+        }
+
+        self.stack[self.stack_top] = val;
+        self.stack_top += 1;
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, Error> {
+        if self.stack_top == 0 {
+            return Err(Error::StackUnderflow);
+        }
+
+        self.stack_top -= 1;
+
+        Ok(self.stack[self.stack_top])
+    }
+
+    /// The value `distance` slots below the top of the stack
+    fn peek(&self, distance: usize) -> Result<Value, Error> {
+        if distance >= self.stack_top {
+            return Err(Error::StackUnderflow);
+        }
+
+        Ok(self.stack[self.stack_top - 1 - distance])
+    }
+
+    /// The top `len` values of the stack, the topmost last
+    fn top(&self, len: usize) -> Result<&[Value], Error> {
+        if len > self.stack_top {
+            return Err(Error::StackUnderflow);
+        }
+
+        Ok(&self.stack[self.stack_top - len..self.stack_top])
+    }
+
+    /// The typed versions of `pop`.
+    /// They return `Error::TypeMismatch` when the value on the stack is of another type
+    fn pop_int(&mut self) -> Result<i64, Error> {
+        let value = self.pop()?;
+
+        if value.is_int() {
+            Ok(value.as_int())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    fn pop_float(&mut self) -> Result<f64, Error> {
+        let value = self.pop()?;
+
+        if value.is_float() {
+            Ok(value.as_float())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, Error> {
+        let value = self.pop()?;
+
+        if value.is_bool() {
+            Ok(value.as_bool())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    fn pop_char(&mut self) -> Result<char, Error> {
+        let value = self.pop()?;
+
+        if value.is_char() {
+            Ok(value.as_char())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    fn pop_array<'b>(&mut self) -> Result<&'b ArrayObject, Error> {
+        self.pop()?.as_array_object().ok_or(Error::TypeMismatch)
+    }
+
+    fn pop_mut_array<'b>(&mut self) -> Result<&'b mut ArrayObject, Error> {
+        self.pop()?.as_mut_array_object().ok_or(Error::TypeMismatch)
+    }
+
+    fn pop_tuple<'b>(&mut self) -> Result<&'b TupleObject, Error> {
+        self.pop()?.as_tuple_object().ok_or(Error::TypeMismatch)
+    }
+
+    fn pop_instance<'b>(&mut self) -> Result<&'b InstanceObject, Error> {
+        self.pop()?.as_instance_object().ok_or(Error::TypeMismatch)
+    }
+
+    fn pop_mut_instance<'b>(&mut self) -> Result<&'b mut InstanceObject, Error> {
+        self.pop()?
+            .as_mut_instance_object()
+            .ok_or(Error::TypeMismatch)
+    }
+
+    fn pop_enum<'b>(&mut self) -> Result<&'b EnumObject, Error> {
+        self.pop()?.as_enum_object().ok_or(Error::TypeMismatch)
+    }
+
+    /// The bool on top of the stack, without popping it
+    fn peek_bool(&self) -> Result<bool, Error> {
+        let value = self.peek(0)?;
+
+        if value.is_bool() {
+            Ok(value.as_bool())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+}
+
+use std::fmt::{self, Debug, Display};
+
+/// The locals of a method's frame. The receiver is stored in the first slot
+fn method_locals(receiver: Value) -> FnvHashMap<u8, Value> {
+    let mut locals = FnvHashMap::default();
+    locals.insert(0, receiver);
+    locals
+}
+
+impl Profile {
+    fn new() -> Self {
+        Profile {
+            opcodes: [0; 256],
+            calls: FnvHashMap::default(),
+        }
+    }
+
+    /// The total number of instructions executed
+    pub fn instructions(&self) -> u64 {
+        self.opcodes.iter().sum()
+    }
+
+    /// The number of times `opcode` was executed
+    pub fn count(&self, opcode: OpCode) -> u64 {
+        self.opcodes[opcode as usize]
+    }
+}
+
+fn check_arity(expected: usize, found: usize) -> Result<(), Error> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::ArityMismatch { expected, found })
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoMain => write!(f, "Main method is missing"),
+            Error::UnknownOpcode => write!(f, "Unknown opcode"),
+            Error::IndexOutOfBounds { index, len } => write!(
+                f,
+                "Index out of bounds: the len is {} but the index is {}",
+                len, index
+            ),
+            Error::ArityMismatch { expected, found } => {
+                write!(f, "Expected `{}` args found `{}`", expected, found)
+            }
+            Error::UndefinedMethod => write!(f, "Undefined method"),
+            Error::UndefinedFunction => write!(f, "Undefined function"),
+            Error::MalformedBytecode => write!(f, "Malformed bytecode"),
+            Error::CastOverflow { value, kind } => {
+                write!(f, "Cannot cast `{}` to `{}` without truncating it", value, kind)
+            }
+            Error::InvalidChar { value } => write!(f, "`{}` is not a valid char", value),
+            Error::TypeMismatch => write!(f, "Type mismatch"),
+            Error::Overflow => write!(f, "Integer overflow"),
+            Error::DivisionByZero => write!(f, "Division by zero"),
+            Error::LengthMismatch { expected, len } => write!(
+                f,
+                "Expected an array of {} items but the len is {}",
+                expected, len
+            ),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::NativeDisabled { name } => write!(f, "`{}` is disabled in the sandbox", name),
+            Error::StepLimitExceeded => write!(f, "Step limit exceeded"),
+            Error::StackOverflow => write!(f, "Stack overflow"),
+            Error::StackUnderflow => write!(f, "Stack underflow"),
+        }
+    }
+}
+
+impl<'a> Debug for VM<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug_trait_builder = f.debug_struct("VM");
+
+        let _ = debug_trait_builder.field("stack", &self.stack[0..].iter());
+        let _ = debug_trait_builder.field("ip", &self.current_frame.ip);
+        debug_trait_builder.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, VM};
+    use crate::chunk::Chunk;
+    use crate::object::{ArrayObject, EnumObject, InstanceObject, StringObject};
+    use crate::opcode;
+    use crate::value::Value;
+    use crate::{Class, Function, IntKind, Program};
+    use fnv::FnvHashMap;
+    use std::rc::Rc;
+    use util::symbol::{Symbol, SymbolFactory, Symbols, CLONE};
+
+    fn program(code: Vec<u8>, constants: Vec<Value>) -> Program {
+        let main = Function {
+            name: Symbol(0),
+            body: Chunk {
+                lines: vec![(0, code.len())],
+                code,
+            },
+            params: FnvHashMap::default(),
+        };
+
+        let mut functions = FnvHashMap::default();
+        functions.insert(Symbol(0), Rc::new(main));
+
+        Program {
+            functions,
+            classes: FnvHashMap::default(),
+            names: FnvHashMap::default(),
+            constants,
+        }
+    }
+
+    #[test]
+    fn dup() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::DUP, opcode::ADD],
+            vec![Value::int(2)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert_eq!(vm.pop().unwrap().as_int(), 4);
+    }
+
+    #[test]
+    fn swap() {
+        let program = program(
+            vec![
+                opcode::CONSTANT,
+                0,
+                opcode::CONSTANT,
+                1,
+                opcode::SWAP,
+                opcode::SUB,
+            ],
+            vec![Value::int(5), Value::int(3)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert_eq!(vm.pop().unwrap().as_int(), -2);
+    }
+
+    #[test]
+    fn no_main() {
+        let program = program(vec![], vec![]);
+        let mut symbols = Symbols::new(Rc::new(SymbolFactory::new()));
+
+        let mut out = Vec::new();
+
+        match VM::run_program(&program, ::std::ptr::null_mut(), &mut symbols, &mut out) {
+            Err(Error::NoMain) => (),
+            _ => panic!("Expected Error::NoMain"),
+        }
+    }
+
+    #[test]
+    fn missing_operand() {
+        let program = program(vec![opcode::CONSTANT], vec![Value::int(1)]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::MalformedBytecode) => (),
+            _ => panic!("Expected Error::MalformedBytecode"),
+        }
+    }
+
+    #[test]
+    fn truncated_jump() {
+        let program = program(vec![opcode::CONSTANT, 0, opcode::JUMP, 0], vec![Value::int(1)]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::MalformedBytecode) => (),
+            _ => panic!("Expected Error::MalformedBytecode"),
+        }
+    }
+
+    #[test]
+    fn jump_past_the_end() {
+        let program = program(vec![opcode::JUMP, 0, 5], vec![]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::MalformedBytecode) => (),
+            _ => panic!("Expected Error::MalformedBytecode"),
+        }
+    }
+
+    #[test]
+    fn missing_constant() {
+        let program = program(vec![opcode::CONSTANT, 3], vec![Value::int(1)]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::MalformedBytecode) => (),
+            _ => panic!("Expected Error::MalformedBytecode"),
+        }
+    }
+
+    #[test]
+    fn undefined_static_method() {
+        let mut program = program(vec![opcode::CALLSTATICMETHOD, 1, 2, 0], vec![]);
+
+        program.classes.insert(
+            Symbol(1),
+            Class {
+                name: Symbol(1),
+                methods: FnvHashMap::default(),
+                superclass: None,
+                properties: vec![],
+            },
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::UndefinedMethod) => (),
+            _ => panic!("Expected Error::UndefinedMethod"),
+        }
+    }
+
+    #[test]
+    fn undefined_function() {
+        let codes = [
+            vec![opcode::GETFUNCTION, 20],
+            vec![opcode::CALL, 20, 0],
+            vec![opcode::CALLNATIVE, 20],
+        ];
+
+        for code in codes.iter() {
+            let program = program(code.clone(), vec![]);
+            let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+            match vm.run() {
+                Err(Error::UndefinedFunction) => (),
+                _ => panic!("Expected Error::UndefinedFunction"),
+            }
+        }
+    }
+
+    #[test]
+    fn function_objects_share_their_function() {
+        let program = program(vec![opcode::GETFUNCTION, 0, opcode::RETURN], vec![]);
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        let value = vm.run().unwrap();
+        let function = &value.as_function_object().unwrap().function;
+
+        assert!(Rc::ptr_eq(function, &program.functions[&Symbol(0)]));
+    }
+
+    #[test]
+    fn repr() {
+        let string = Value::object(StringObject::new("a", ::std::ptr::null_mut()));
+        let array = Value::object(ArrayObject::new(
+            vec![Value::int(1), string],
+            ::std::ptr::null_mut(),
+        ));
+
+        assert_eq!(string.repr(), "\"a\"");
+        assert_eq!(string.to_string(), "a");
+        assert_eq!(array.repr(), "[1, \"a\"]");
+        assert_eq!(Value::nil().repr(), "nil");
+    }
+
+    fn array(items: Vec<Value>) -> Value {
+        Value::object(ArrayObject::new(items, ::std::ptr::null_mut()))
+    }
+
+    #[test]
+    fn nested_array_equality() {
+        let a = array(vec![array(vec![Value::int(1)]), array(vec![Value::int(2)])]);
+        let b = array(vec![array(vec![Value::int(1)]), array(vec![Value::int(2)])]);
+        let c = array(vec![array(vec![Value::int(1)]), array(vec![Value::int(3)])]);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, array(vec![array(vec![Value::int(1)])]));
+    }
+
+    #[test]
+    fn self_referential_equality() {
+        let a = array(vec![Value::int(1)]);
+        let b = array(vec![Value::int(1)]);
+
+        a.as_mut_array().items.push(a);
+        b.as_mut_array().items.push(b);
+
+        assert_eq!(a, b);
+
+        let name = Symbol(0);
+        let x = Value::object(InstanceObject::new(
+            name,
+            FnvHashMap::default(),
+            ::std::ptr::null_mut(),
+        ));
+        let y = Value::object(InstanceObject::new(
+            name,
+            FnvHashMap::default(),
+            ::std::ptr::null_mut(),
+        ));
+
+        x.as_mut_instance().properties.insert(name, y);
+        y.as_mut_instance().properties.insert(name, x);
+
+        assert_eq!(x, y);
+    }
+
+    #[test]
+    fn self_referential_repr() {
+        let a = array(vec![Value::int(1)]);
+
+        a.as_mut_array().items.push(a);
+
+        assert_eq!(a.repr(), "[1, [...]]");
+        assert_eq!(array(vec![a, a]).repr(), "[[1, [...]], [1, [...]]]");
+    }
+
+    #[test]
+    fn checked_object_accessors() {
+        let string = Value::object(StringObject::new("a", ::std::ptr::null_mut()));
+        let items = array(vec![Value::int(1)]);
+
+        assert!(string.as_string_object().is_some());
+        assert!(string.as_array_object().is_none());
+        assert!(string.as_function_object().is_none());
+
+        assert!(items.as_array_object().is_some());
+        assert!(items.as_string_object().is_none());
+        assert!(items.as_instance_object().is_none());
+        assert!(items.as_native_object().is_none());
+
+        assert!(Value::int(1).as_string_object().is_none());
+        assert!(Value::nil().as_class_object().is_none());
+    }
+
+    #[test]
+    fn concat_rejects_non_strings() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::CONSTANT, 1, opcode::CONCAT],
+            vec![Value::int(1), Value::int(2)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::TypeMismatch) => (),
+            _ => panic!("Expected Error::TypeMismatch"),
+        }
+    }
+
+    #[test]
+    fn indexing_a_non_array_is_a_type_mismatch() {
+        let program = program(
+            vec![opcode::NIL, opcode::NIL, opcode::INDEXARRAY, opcode::RETURN],
+            vec![],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::TypeMismatch) => (),
+            _ => panic!("Expected Error::TypeMismatch"),
+        }
+    }
+
+    #[test]
+    fn overflowing_the_stack_is_an_error() {
+        let program = program(vec![opcode::NIL; 300], vec![]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::StackOverflow) => (),
+            _ => panic!("Expected Error::StackOverflow"),
+        }
+    }
+
+    #[test]
+    fn popping_an_empty_stack_is_an_error() {
+        let program = program(vec![opcode::POP; 10], vec![]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::StackUnderflow) => (),
+            _ => panic!("Expected Error::StackUnderflow"),
+        }
+    }
+
+    #[test]
+    fn clone_copies_arrays() {
+        let program = program(vec![opcode::CALLNATIVE, CLONE.0 as u8], vec![]);
+        let original = array(vec![Value::int(1), array(vec![Value::int(2)])]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.push(original).unwrap();
+        vm.run().unwrap();
+
+        let copy = vm.pop().unwrap();
+
+        assert_eq!(copy, original);
+
+        copy.as_mut_array().items[0] = Value::int(10);
+        copy.as_mut_array().items[1].as_mut_array().items[0] = Value::int(20);
+
+        assert_eq!(original.as_array().items[0].as_int(), 1);
+        assert_eq!(original.as_array().items[1].as_array().items[0].as_int(), 2);
+    }
+
+    #[test]
+    fn clone_copies_enum_payloads() {
+        let program = program(vec![opcode::CALLNATIVE, CLONE.0 as u8], vec![]);
+        let payload = array(vec![Value::int(1)]);
+        let original = Value::object(EnumObject::new(
+            Symbol(1),
+            0,
+            Some(payload),
+            ::std::ptr::null_mut(),
+        ));
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.push(original).unwrap();
+        vm.run().unwrap();
+
+        let copy = vm.pop().unwrap();
+
+        assert_eq!(copy, original);
+
+        copy.as_enum().data.unwrap().as_mut_array().items[0] = Value::int(10);
+
+        assert_eq!(payload.as_array().items[0].as_int(), 1);
+    }
+
+    #[test]
+    fn clone_keeps_cycles() {
+        let program = program(vec![opcode::CALLNATIVE, CLONE.0 as u8], vec![]);
+        let original = array(vec![Value::int(1)]);
+
+        original.as_mut_array().items.push(original);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.push(original).unwrap();
+        vm.run().unwrap();
+
+        let copy = vm.pop().unwrap();
+
+        assert_ne!(copy.as_object(), original.as_object());
+        assert_eq!(copy.as_array().items[1].as_object(), copy.as_object());
+    }
+
+    #[test]
+    fn check_int() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::CHECKINT, IntKind::U8 as u8],
+            vec![Value::int(300)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::CastOverflow {
+                value: 300,
+                kind: IntKind::U8,
+            }) => (),
+            _ => panic!("Expected Error::CastOverflow"),
+        }
+    }
+
+    #[test]
+    fn wrap_int() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::WRAPINT, IntKind::U8 as u8],
+            vec![Value::int(256)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert_eq!(vm.pop().unwrap().as_int(), 0);
+    }
+
+    #[test]
+    fn enum_tag_and_inner() {
+        let program = program(
+            vec![
+                opcode::CONSTANT,
+                0,
+                opcode::ENUMDATA,
+                1,
+                1,
+                opcode::DUP,
+                opcode::ENUMTAG,
+                opcode::SWAP,
+                opcode::ENUMINNER,
+            ],
+            vec![Value::int(7)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert_eq!(vm.pop().unwrap().as_int(), 7);
+        assert_eq!(vm.pop().unwrap().as_int(), 1);
+    }
+
+    #[test]
+    fn tuple_items() {
+        let program = program(
+            vec![
+                opcode::CONSTANT,
+                1,
+                opcode::CONSTANT,
+                0,
+                opcode::TUPLE,
+                2,
+                opcode::DUP,
+                opcode::INDEXTUPLE,
+                1,
+                opcode::SWAP,
+                opcode::INDEXTUPLE,
+                0,
+            ],
+            vec![Value::int(1), Value::bool(true)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert_eq!(vm.pop().unwrap().as_int(), 1);
+        assert!(vm.pop().unwrap().as_bool());
+    }
+
+    #[test]
+    fn check_len() {
+        let items = Value::object(ArrayObject::new(
+            vec![Value::int(1), Value::int(2)],
+            ::std::ptr::null_mut(),
+        ));
+
+        let program = program(vec![opcode::CONSTANT, 0, opcode::CHECKLEN, 3], vec![items]);
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::LengthMismatch {
+                expected: 3,
+                len: 2,
+            }) => (),
+            _ => panic!("Expected Error::LengthMismatch"),
+        }
+    }
+
+    #[test]
+    fn add_overflows() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::CONSTANT, 1, opcode::ADD],
+            vec![Value::int(i64::max_value()), Value::int(1)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::Overflow) => (),
+            _ => panic!("Expected Error::Overflow"),
+        }
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::CONSTANT, 1, opcode::DIV],
+            vec![Value::int(1), Value::int(0)],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+        match vm.run() {
+            Err(Error::DivisionByZero) => (),
+            _ => panic!("Expected Error::DivisionByZero"),
+        }
+    }
+
+    #[test]
+    fn malformed_bytecode_is_an_error() {
+        // the same inputs the fuzz target starts from
+        let inputs = vec![
+            vec![opcode::CONSTANT],
+            vec![opcode::CONSTANT, 3],
+            vec![opcode::JUMP, 0],
+            vec![opcode::LOOP, 0, 10],
+            vec![255],
+        ];
+
+        for code in inputs {
+            let program = program(code.clone(), vec![]);
+            let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+
+            match vm.run() {
+                Err(Error::MalformedBytecode) | Err(Error::UnknownOpcode) => (),
+                other => panic!("{:?} ran with {:?}", code, other),
+            }
+        }
+    }
+
+    #[test]
+    fn compare_strings() {
+        let apple = Value::object(StringObject::new("apple", ::std::ptr::null_mut()));
+        let banana = Value::object(StringObject::new("banana", ::std::ptr::null_mut()));
+
+        let program = program(
+            vec![
+                opcode::CONSTANT,
+                0,
+                opcode::CONSTANT,
+                1,
+                opcode::LESSSTR,
+                opcode::CONSTANT,
+                0,
+                opcode::CONSTANT,
+                1,
+                opcode::GREATERSTR,
+            ],
+            vec![apple, banana],
+        );
+
+        let mut vm = VM::new(Symbol(0), &program, ::std::ptr::null_mut()).unwrap();
+        vm.run().unwrap();
+
+        assert!(!vm.pop().unwrap().as_bool());
+        assert!(vm.pop().unwrap().as_bool());
+    }
+
+    #[test]
+    fn print_writes_to_the_output() {
+        let program = program(vec![opcode::CONSTANT, 0, opcode::PRINT], vec![Value::int(42)]);
+        let mut out = Vec::new();
+
+        VM::new(Symbol(0), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_output(&mut out)
+            .run()
+            .unwrap();
+
+        assert_eq!(out, b"42\n");
+    }
+
+    #[test]
+    fn eprint_writes_to_the_error_output() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::PRINT, opcode::CONSTANT, 1, opcode::EPRINT],
+            vec![Value::int(1), Value::int(2)],
+        );
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+
+        VM::new(Symbol(0), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_output(&mut out)
+            .with_error_output(&mut err)
+            .run()
+            .unwrap();
+
+        assert_eq!(out, b"1\n");
+        assert_eq!(err, b"2\n");
+    }
+
+    #[test]
+    fn trace_writes_a_line_per_instruction() {
+        let program = program(
+            vec![opcode::CONSTANT, 0, opcode::CONSTANT, 1, opcode::ADD],
+            vec![Value::int(1), Value::int(2)],
+        );
+        let mut trace = Vec::new();
+
+        VM::new(Symbol(0), &program, ::std::ptr::null_mut())
+            .unwrap()
+            .with_trace(&mut trace)
+            .run()
+            .unwrap();
+
+        let trace = String::from_utf8(trace).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("OPCODE::CONSTANT"));
+        assert!(lines[1].contains("OPCODE::CONSTANT"));
+        assert!(lines[2].contains("OPCODE::ADD"));
     }
 }